@@ -0,0 +1,13 @@
+CREATE TABLE rocketchat_servers_backup (
+  id VARCHAR NOT NULL,
+  rocketchat_url VARCHAR NOT NULL,
+  rocketchat_token VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT rocketchat_servers_pk PRIMARY KEY (id)
+  UNIQUE (rocketchat_url),
+  UNIQUE (rocketchat_token)
+);
+INSERT INTO rocketchat_servers_backup SELECT id, rocketchat_url, rocketchat_token, created_at, updated_at FROM rocketchat_servers;
+DROP TABLE rocketchat_servers;
+ALTER TABLE rocketchat_servers_backup RENAME TO rocketchat_servers;
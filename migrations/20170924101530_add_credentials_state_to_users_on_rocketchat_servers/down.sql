@@ -0,0 +1,13 @@
+CREATE TABLE users_on_rocketchat_servers_backup (
+  is_virtual_user BOOLEAN NOT NULL,
+  matrix_user_id VARCHAR NOT NULL,
+  rocketchat_server_id VARCHAR NOT NULL,
+  rocketchat_user_id VARCHAR,
+  rocketchat_auth_token VARCHAR,
+  rocketchat_username VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT INTO users_on_rocketchat_servers_backup SELECT is_virtual_user, matrix_user_id, rocketchat_server_id, rocketchat_user_id, rocketchat_auth_token, rocketchat_username, created_at, updated_at FROM users_on_rocketchat_servers;
+DROP TABLE users_on_rocketchat_servers;
+ALTER TABLE users_on_rocketchat_servers_backup RENAME TO users_on_rocketchat_servers;
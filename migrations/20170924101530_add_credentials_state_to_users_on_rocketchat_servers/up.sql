@@ -0,0 +1,2 @@
+ALTER TABLE users_on_rocketchat_servers ADD COLUMN credentials_valid BOOLEAN NOT NULL DEFAULT 1;
+ALTER TABLE users_on_rocketchat_servers ADD COLUMN admin_room_id VARCHAR
@@ -0,0 +1,14 @@
+CREATE TABLE login_tokens_backup (
+  token VARCHAR NOT NULL,
+  matrix_user_id VARCHAR NOT NULL,
+  rocketchat_server_id VARCHAR NOT NULL,
+  admin_room_id VARCHAR NOT NULL,
+  csrf_token VARCHAR NOT NULL,
+  expires_at BIGINT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT login_tokens_pk PRIMARY KEY (token)
+);
+INSERT INTO login_tokens_backup SELECT token, matrix_user_id, rocketchat_server_id, admin_room_id, csrf_token, expires_at, created_at, updated_at FROM login_tokens;
+DROP TABLE login_tokens;
+ALTER TABLE login_tokens_backup RENAME TO login_tokens;
@@ -0,0 +1,2 @@
+ALTER TABLE rocketchat_servers ADD COLUMN rocketchat_token_hash VARCHAR;
+CREATE UNIQUE INDEX rocketchat_servers_rocketchat_token_hash ON rocketchat_servers (rocketchat_token_hash)
@@ -0,0 +1,7 @@
+CREATE TABLE linked_rooms (
+  matrix_room_id VARCHAR NOT NULL,
+  previous_canonical_alias VARCHAR,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT linked_rooms_pk PRIMARY KEY (matrix_room_id)
+)
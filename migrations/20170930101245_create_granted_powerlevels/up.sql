@@ -0,0 +1,9 @@
+CREATE TABLE granted_powerlevels (
+  matrix_room_id VARCHAR NOT NULL,
+  matrix_user_id VARCHAR NOT NULL,
+  powerlevel BIG INT NOT NULL,
+  previous_powerlevel BIG INT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  CONSTRAINT granted_powerlevels_pk PRIMARY KEY (matrix_room_id, matrix_user_id)
+)
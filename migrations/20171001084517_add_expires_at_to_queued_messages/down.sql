@@ -0,0 +1,12 @@
+CREATE TABLE queued_messages_backup (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  matrix_user_id VARCHAR NOT NULL,
+  rocketchat_server_id VARCHAR NOT NULL,
+  rocketchat_channel_id VARCHAR NOT NULL,
+  message TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+INSERT INTO queued_messages_backup SELECT id, matrix_user_id, rocketchat_server_id, rocketchat_channel_id, message, created_at, updated_at FROM queued_messages;
+DROP TABLE queued_messages;
+ALTER TABLE queued_messages_backup RENAME TO queued_messages;
@@ -0,0 +1 @@
+ALTER TABLE queued_messages ADD COLUMN expires_at BIGINT NOT NULL DEFAULT 0
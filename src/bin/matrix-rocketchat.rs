@@ -13,15 +13,24 @@ extern crate slog_json;
 extern crate slog_stream;
 extern crate slog_term;
 
+use std::env;
 use std::fs::OpenOptions;
 use std::path::Path;
 
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 use iron::Listening;
 use matrix_rocketchat::{Config, Server};
+use matrix_rocketchat::db::ConnectionPool;
+use matrix_rocketchat::db::encryption::{self, EncryptionKey};
 use matrix_rocketchat::errors::*;
 use slog::{Drain, FnValue, Level, LevelFilter, Record};
 
+/// Environment variable that contains the key the tokens are currently encrypted with
+const OLD_KEY_ENV_VARIABLE: &'static str = "MATRIX_ROCKETCHAT_OLD_DATABASE_ENCRYPTION_KEY";
+/// Help text of the `--old-key` argument
+const OLD_KEY_HELP: &'static str = "Base64 encoded key the tokens are currently encrypted with, it can also be set via \
+                                    MATRIX_ROCKETCHAT_OLD_DATABASE_ENCRYPTION_KEY. Omit it to encrypt plaintext tokens.";
+
 fn main() {
     if let Err(ref e) = run() {
         println!("error: {}", e);
@@ -32,7 +41,7 @@ fn main() {
     }
 }
 
-fn run() -> Result<Listening> {
+fn run() -> Result<()> {
     let matches = App::new("matrix-rocketchat")
         .version("0.1")
         .author("Andreas Brönnimann <foss@exul.org>")
@@ -40,15 +49,48 @@ fn run() -> Result<Listening> {
         .arg(Arg::with_name("config").short("c").long("config").help("Path to config file").takes_value(true))
         .arg(Arg::with_name("log-file").short("f").long("log-file").help("Path to log file").takes_value(true))
         .arg(Arg::with_name("log-level").short("l").long("log-level").help("Log level").takes_value(true))
+        .subcommand(
+            SubCommand::with_name("reencrypt-tokens")
+                .about("Re-encrypts the tokens in the database with the key from the config or the environment")
+                .arg(Arg::with_name("old-key").long("old-key").help(OLD_KEY_HELP).takes_value(true)),
+        )
         .get_matches();
 
     let config_path = matches.value_of("config").unwrap_or("config.yaml").to_string();
     let config = Config::read_from_file(&config_path).chain_err(|| ErrorKind::ReadFileError(config_path))?;
+
+    if let Some(reencrypt_matches) = matches.subcommand_matches("reencrypt-tokens") {
+        return reencrypt_tokens(&config, reencrypt_matches.value_of("old-key"));
+    }
+
     let log_file_path = matches.value_of("log-file").unwrap_or("matrix-rocketchat.log");
     let log_level = matches.value_of("log-level").unwrap_or("info");
     let log = build_logger(log_file_path, log_level);
     let threads = num_cpus::get() * 8;
-    Server::new(&config, log).run(threads)
+    // the server runs until the listener is dropped
+    let _listening: Listening = Server::new(&config, log).run(threads)?;
+    Ok(())
+}
+
+fn reencrypt_tokens(config: &Config, old_key: Option<&str>) -> Result<()> {
+    let old_key = match old_key {
+        Some(old_key) => Some(old_key.to_string()),
+        None => env::var(OLD_KEY_ENV_VARIABLE).ok(),
+    };
+    let old_encryption_key = match old_key {
+        Some(old_key) => Some(EncryptionKey::from_base64(&old_key)?),
+        None => None,
+    };
+    let new_encryption_key = match config.database_encryption_key()? {
+        Some(new_encryption_key) => new_encryption_key,
+        None => return Err(Error::from(ErrorKind::DatabaseEncryptionKeyMissing)),
+    };
+
+    let connection_pool = ConnectionPool::create(&config.database_url)?;
+    let connection = connection_pool.get().chain_err(|| ErrorKind::GetConnectionError)?;
+    let reencrypted_tokens = encryption::reencrypt_tokens(&connection, old_encryption_key.as_ref(), &new_encryption_key)?;
+    println!("Re-encrypted {} tokens", reencrypted_tokens);
+    Ok(())
 }
 
 fn build_logger(log_file_path: &str, log_level: &str) -> slog::Logger {
@@ -3,8 +3,11 @@ use std::collections::HashMap;
 use ruma_client_api::Endpoint;
 use ruma_client_api::unversioned::get_supported_versions::{Endpoint as GetSupportedVersionsEndpoint,
                                                            Response as GetSupportedVersionsResponse};
+use ruma_events::EventType;
+use ruma_events::room::history_visibility::HistoryVisibility;
+use ruma_events::room::join_rules::JoinRule;
 use ruma_events::room::member::MemberEvent;
-use ruma_identifiers::{RoomAliasId, RoomId, UserId};
+use ruma_identifiers::{EventId, RoomAliasId, RoomId, UserId};
 use serde_json;
 use slog::Logger;
 
@@ -31,6 +34,8 @@ pub trait MatrixApi: Send + Sync + MatrixApiClone {
     fn get_room_creator(&self, matrix_room_id: RoomId) -> Result<UserId>;
     /// Get the list of members for this room.
     fn get_room_members(&self, matrix_room_id: RoomId, sender_id: Option<UserId>) -> Result<Vec<MemberEvent>>;
+    /// Get the power levels of a room.
+    fn get_room_powerlevels(&self, matrix_room_id: RoomId) -> Result<PowerLevels>;
     /// Get the topic for a room.
     fn get_room_topic(&self, matrix_room_id: RoomId) -> Result<Option<String>>;
     /// Invite a user to a room.
@@ -39,10 +44,21 @@ pub trait MatrixApi: Send + Sync + MatrixApiClone {
     fn is_room_accessible_by_bot(&self, matrix_room_id: RoomId) -> Result<bool>;
     /// Join a room with a user.
     fn join(&self, matrix_room_id: RoomId, matrix_user_id: UserId) -> Result<()>;
+    /// Kick a user from a room, the user is kicked by the bot user.
+    fn kick_user(&self, matrix_room_id: RoomId, matrix_user_id: UserId, reason: Option<String>) -> Result<()>;
     /// Leave a room.
     fn leave_room(&self, matrix_room_id: RoomId, matrix_user_id: UserId) -> Result<()>;
-    /// Set the canonical alias for a room.
-    fn put_canonical_room_alias(&self, matrix_room_id: RoomId, matrix_room_alias_id: Option<RoomAliasId>) -> Result<()>;
+    /// Set the canonical alias for a room, the event is sent by the bot user if no sender is passed.
+    fn put_canonical_room_alias(
+        &self,
+        matrix_room_id: RoomId,
+        matrix_room_alias_id: Option<RoomAliasId>,
+        sender_id: Option<UserId>,
+    ) -> Result<()>;
+    /// Add an alias for a room to the room directory.
+    fn put_room_alias(&self, matrix_room_id: RoomId, matrix_room_alias_id: RoomAliasId) -> Result<()>;
+    /// Redact an event, the content of the event is removed from the room history.
+    fn redact_event(&self, matrix_room_id: RoomId, matrix_event_id: EventId, reason: Option<String>) -> Result<()>;
     /// Register a user.
     fn register(&self, user_id_local_part: String) -> Result<()>;
     /// Send a text message to a room.
@@ -52,10 +68,72 @@ pub trait MatrixApi: Send + Sync + MatrixApiClone {
     fn set_default_powerlevels(&self, matrix_room_id: RoomId, room_creator_matrix_user_id: UserId) -> Result<()>;
     /// Set the display name for a user
     fn set_display_name(&self, matrix_user_id: UserId, name: String) -> Result<()>;
+    /// Set the history visibility for a room, it defines which events new members can read.
+    fn set_room_history_visibility(&self, matrix_room_id: RoomId, history_visibility: HistoryVisibility) -> Result<()>;
+    /// Set the join rule for a room, a public room can be joined by everybody who knows the alias.
+    fn set_room_join_rule(&self, matrix_room_id: RoomId, join_rule: JoinRule) -> Result<()>;
     /// Set the name for a room
     fn set_room_name(&self, matrix_room_id: RoomId, name: String) -> Result<()>;
     /// Set the topic for a room.
     fn set_room_topic(&self, matrix_room_id: RoomId, topic: String) -> Result<()>;
+    /// Set the power levels of users in a room, the other power levels of the room are kept. Users
+    /// that get the power level 0 are removed from the list. The power levels of users that have
+    /// the same or a higher power level than the sender are not changed, the homeserver would
+    /// reject the change. The sender can only lower its own power level.
+    fn set_user_powerlevels(
+        &self,
+        matrix_room_id: RoomId,
+        sender_id: UserId,
+        user_powerlevels: HashMap<UserId, i64>,
+    ) -> Result<()>;
+}
+
+/// The power levels of a room. Fields that are missing in the `m.room.power_levels` event get the
+/// default values of the Matrix specification.
+#[derive(Debug, Deserialize)]
+pub struct PowerLevels {
+    /// The power levels that are needed to send specific state events
+    #[serde(default)]
+    pub events: HashMap<String, i64>,
+    /// The power level that is needed to send state events that are not listed in `events`
+    #[serde(default = "default_state_powerlevel")]
+    pub state_default: i64,
+    /// The power levels of the users in the room
+    #[serde(default)]
+    pub users: HashMap<String, i64>,
+    /// The power level of users that are not listed in `users`
+    #[serde(default)]
+    pub users_default: i64,
+}
+
+impl PowerLevels {
+    /// The power levels of a room that doesn't have a `m.room.power_levels` event, only the room
+    /// creator gets a higher power level than the other users.
+    pub fn without_event(room_creator_id: &UserId) -> PowerLevels {
+        let mut users = HashMap::new();
+        users.insert(room_creator_id.to_string(), 100);
+
+        PowerLevels {
+            events: HashMap::new(),
+            state_default: 0,
+            users: users,
+            users_default: 0,
+        }
+    }
+
+    /// The power level of a user in the room.
+    pub fn user_powerlevel(&self, matrix_user_id: &UserId) -> i64 {
+        self.users.get(&matrix_user_id.to_string()).cloned().unwrap_or(self.users_default)
+    }
+
+    /// The power level that is needed to send a state event of the given type.
+    pub fn state_event_powerlevel(&self, event_type: EventType) -> i64 {
+        self.events.get(&event_type.to_string()).cloned().unwrap_or(self.state_default)
+    }
+}
+
+fn default_state_powerlevel() -> i64 {
+    50
 }
 
 /// Helper trait because Clone cannot be part of the `MatrixApi` trait since that would cause the
@@ -5,19 +5,24 @@ use pulldown_cmark::{Options, Parser, html};
 use reqwest::StatusCode;
 use ruma_client_api::Endpoint;
 use ruma_client_api::r0::alias::get_alias::{self, Endpoint as GetAliasEndpoint};
+use ruma_client_api::r0::alias::create_alias::Endpoint as CreateAliasEndpoint;
 use ruma_client_api::r0::alias::delete_alias::Endpoint as DeleteAliasEndpoint;
 use ruma_client_api::r0::account::register::{self, Endpoint as RegisterEndpoint};
 use ruma_client_api::r0::membership::forget_room::{self, Endpoint as ForgetRoomEndpoint};
 use ruma_client_api::r0::membership::invite_user::{self, Endpoint as InviteUserEndpoint};
 use ruma_client_api::r0::membership::join_room_by_id::{self, Endpoint as JoinRoomByIdEndpoint};
+use ruma_client_api::r0::membership::kick_user::{self, Endpoint as KickUserEndpoint};
 use ruma_client_api::r0::membership::leave_room::{self, Endpoint as LeaveRoomEndpoint};
 use ruma_client_api::r0::profile::set_display_name::{self, Endpoint as SetDisplayNameEndpoint};
+use ruma_client_api::r0::redact::redact_event::{self, Endpoint as RedactEventEndpoint};
 use ruma_client_api::r0::room::create_room::{self, Endpoint as CreateRoomEndpoint, RoomPreset};
 use ruma_client_api::r0::send::send_message_event::{self, Endpoint as SendMessageEventEndpoint};
 use ruma_client_api::r0::send::send_state_event_for_empty_key::{self, Endpoint as SendStateEventForEmptyKeyEndpoint};
 use ruma_client_api::r0::sync::get_member_events::{self, Endpoint as GetMemberEventsEndpoint};
 use ruma_client_api::r0::sync::get_state_events_for_empty_key::{self, Endpoint as GetStateEventsForEmptyKeyEndpoint};
 use ruma_events::EventType;
+use ruma_events::room::history_visibility::HistoryVisibility;
+use ruma_events::room::join_rules::JoinRule;
 use ruma_events::room::member::MemberEvent;
 use ruma_events::room::message::MessageType;
 use ruma_identifiers::{EventId, RoomAliasId, RoomId, UserId};
@@ -28,6 +33,7 @@ use url;
 use api::RestApi;
 use config::Config;
 use errors::*;
+use super::PowerLevels;
 
 #[derive(Clone)]
 /// Rocket.Chat REST API v0
@@ -55,6 +61,41 @@ impl MatrixApi {
         params.insert("access_token", &self.access_token);
         params
     }
+
+    // Returns `None` if the room doesn't have a power levels event
+    fn get_powerlevels_event_content(
+        &self,
+        matrix_room_id: RoomId,
+        sender_id: Option<UserId>,
+    ) -> Result<Option<Map<String, Value>>> {
+        let path_params = get_state_events_for_empty_key::PathParams {
+            room_id: matrix_room_id,
+            event_type: EventType::RoomPowerLevels.to_string(),
+        };
+        let endpoint = self.base_url.clone() + &GetStateEventsForEmptyKeyEndpoint::request_path(path_params);
+        let user_id;
+        let mut params = self.params_hash();
+        if let Some(matrix_user_id) = sender_id {
+            user_id = matrix_user_id.to_string();
+            params.insert("user_id", &user_id);
+        }
+
+        let (body, status_code) = RestApi::call_matrix(GetStateEventsForEmptyKeyEndpoint::method(), &endpoint, "{}", &params)?;
+        if status_code == StatusCode::NotFound {
+            return Ok(None);
+        }
+
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+
+        let power_levels: Map<String, Value> = serde_json::from_str(&body).chain_err(|| {
+            ErrorKind::InvalidJSON(
+                format!("Could not deserialize response from Matrix get_state_events_for_empty_key API endpoint: `{}`", body),
+            )
+        })?;
+        Ok(Some(power_levels))
+    }
 }
 
 impl super::MatrixApi for MatrixApi {
@@ -165,7 +206,8 @@ impl super::MatrixApi for MatrixApi {
             )
         })?;
 
-        let alias = room_canonical_alias_response["alias"].to_string().replace("\"", "");
+        // the alias is empty or missing when the canonical alias was removed
+        let alias = room_canonical_alias_response["alias"].as_str().unwrap_or_default().to_string();
         if alias.is_empty() {
             return Ok(None);
         }
@@ -222,6 +264,21 @@ impl super::MatrixApi for MatrixApi {
         Ok(room_member_events.chunk)
     }
 
+    fn get_room_powerlevels(&self, matrix_room_id: RoomId) -> Result<PowerLevels> {
+        let power_levels = match self.get_powerlevels_event_content(matrix_room_id.clone(), None)? {
+            Some(power_levels) => power_levels,
+            None => {
+                let room_creator_id = self.get_room_creator(matrix_room_id)?;
+                return Ok(PowerLevels::without_event(&room_creator_id));
+            }
+        };
+
+        let power_levels: PowerLevels = serde_json::from_value(Value::Object(power_levels)).chain_err(|| {
+            ErrorKind::InvalidJSON(format!("Could not deserialize power levels of room {}", matrix_room_id))
+        })?;
+        Ok(power_levels)
+    }
+
     fn get_room_topic(&self, matrix_room_id: RoomId) -> Result<Option<String>> {
         let path_params = get_state_events_for_empty_key::PathParams {
             room_id: matrix_room_id,
@@ -301,6 +358,25 @@ impl super::MatrixApi for MatrixApi {
         Ok(())
     }
 
+    fn kick_user(&self, matrix_room_id: RoomId, matrix_user_id: UserId, reason: Option<String>) -> Result<()> {
+        let path_params = kick_user::PathParams { room_id: matrix_room_id.clone() };
+        let endpoint = self.base_url.clone() + &KickUserEndpoint::request_path(path_params);
+        let params = self.params_hash();
+        let body_params = kick_user::BodyParams {
+            reason: reason,
+            user_id: matrix_user_id.clone(),
+        };
+        let payload = serde_json::to_string(&body_params).chain_err(|| body_params_error!("kick user"))?;
+
+        let (body, status_code) = RestApi::call_matrix(KickUserEndpoint::method(), &endpoint, &payload, &params)?;
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+
+        debug!(self.logger, "User {} successfully kicked from room {}", matrix_user_id, matrix_room_id);
+        Ok(())
+    }
+
     fn leave_room(&self, matrix_room_id: RoomId, matrix_user_id: UserId) -> Result<()> {
         let path_params = leave_room::PathParams { room_id: matrix_room_id };
         let endpoint = self.base_url.clone() + &LeaveRoomEndpoint::request_path(path_params);
@@ -316,7 +392,12 @@ impl super::MatrixApi for MatrixApi {
         Ok(())
     }
 
-    fn put_canonical_room_alias(&self, matrix_room_id: RoomId, matrix_room_alias_id: Option<RoomAliasId>) -> Result<()> {
+    fn put_canonical_room_alias(
+        &self,
+        matrix_room_id: RoomId,
+        matrix_room_alias_id: Option<RoomAliasId>,
+        sender_id: Option<UserId>,
+    ) -> Result<()> {
         let path_params = send_state_event_for_empty_key::PathParams {
             room_id: matrix_room_id,
             event_type: EventType::RoomCanonicalAlias,
@@ -326,7 +407,12 @@ impl super::MatrixApi for MatrixApi {
             Some(matrix_room_alias_id) => matrix_room_alias_id.to_string(),
             None => String::new(),
         };
-        let params = self.params_hash();
+        let user_id;
+        let mut params = self.params_hash();
+        if let Some(matrix_user_id) = sender_id {
+            user_id = matrix_user_id.to_string();
+            params.insert("user_id", &user_id);
+        }
 
         let mut body_params = serde_json::Map::new();
         body_params.insert("alias".to_string(), json!(room_alias));
@@ -340,6 +426,47 @@ impl super::MatrixApi for MatrixApi {
         Ok(())
     }
 
+    fn put_room_alias(&self, matrix_room_id: RoomId, matrix_room_alias_id: RoomAliasId) -> Result<()> {
+        // the ruma client api path params cannot be used here, because they are not url encoded
+        let encoded_room_alias = url::form_urlencoded::byte_serialize(matrix_room_alias_id.to_string().as_bytes())
+            .collect::<String>();
+        let endpoint = self.base_url.clone() + &format!("/_matrix/client/r0/directory/room/{}", &encoded_room_alias);
+        let params = self.params_hash();
+
+        let mut body_params = serde_json::Map::new();
+        body_params.insert("room_id".to_string(), json!(matrix_room_id.to_string()));
+        let payload = serde_json::to_string(&body_params).chain_err(|| body_params_error!("room alias"))?;
+
+        let (body, status_code) = RestApi::call_matrix(CreateAliasEndpoint::method(), &endpoint, &payload, &params)?;
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+
+        debug!(self.logger, "Successfully added alias {} for room {}", matrix_room_alias_id, matrix_room_id);
+        Ok(())
+    }
+
+    fn redact_event(&self, matrix_room_id: RoomId, matrix_event_id: EventId, reason: Option<String>) -> Result<()> {
+        let txn_id = EventId::new(&self.base_url).chain_err(|| ErrorKind::EventIdGenerationFailed)?;
+        let path_params = redact_event::PathParams {
+            room_id: matrix_room_id.clone(),
+            event_id: matrix_event_id.clone(),
+            txn_id: txn_id.to_string(),
+        };
+        let endpoint = self.base_url.clone() + &RedactEventEndpoint::request_path(path_params);
+        let params = self.params_hash();
+        let body_params = redact_event::BodyParams { reason: reason };
+        let payload = serde_json::to_string(&body_params).chain_err(|| body_params_error!("redact event"))?;
+
+        let (body, status_code) = RestApi::call_matrix(RedactEventEndpoint::method(), &endpoint, &payload, &params)?;
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+
+        debug!(self.logger, "Successfully redacted event {} in room {}", matrix_event_id, matrix_room_id);
+        Ok(())
+    }
+
     fn register(&self, user_id_local_part: String) -> Result<()> {
         let endpoint = self.base_url.clone() + &RegisterEndpoint::request_path(());
         let params = self.params_hash();
@@ -433,6 +560,47 @@ impl super::MatrixApi for MatrixApi {
         Ok(())
     }
 
+    fn set_room_history_visibility(&self, matrix_room_id: RoomId, history_visibility: HistoryVisibility) -> Result<()> {
+        let path_params = send_state_event_for_empty_key::PathParams {
+            room_id: matrix_room_id,
+            event_type: EventType::RoomHistoryVisibility,
+        };
+        let endpoint = self.base_url.clone() + &SendStateEventForEmptyKeyEndpoint::request_path(path_params);
+        let params = self.params_hash();
+        let history_visibility =
+            serde_json::to_value(history_visibility).chain_err(|| body_params_error!("history visibility"))?;
+        let mut body_params = serde_json::Map::new();
+        body_params.insert("history_visibility".to_string(), history_visibility);
+        let payload = serde_json::to_string(&body_params).chain_err(|| body_params_error!("history visibility"))?;
+
+        let (body, status_code) =
+            RestApi::call_matrix(SendStateEventForEmptyKeyEndpoint::method(), &endpoint, &payload, &params)?;
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+        Ok(())
+    }
+
+    fn set_room_join_rule(&self, matrix_room_id: RoomId, join_rule: JoinRule) -> Result<()> {
+        let path_params = send_state_event_for_empty_key::PathParams {
+            room_id: matrix_room_id,
+            event_type: EventType::RoomJoinRules,
+        };
+        let endpoint = self.base_url.clone() + &SendStateEventForEmptyKeyEndpoint::request_path(path_params);
+        let params = self.params_hash();
+        let join_rule = serde_json::to_value(join_rule).chain_err(|| body_params_error!("join rule"))?;
+        let mut body_params = serde_json::Map::new();
+        body_params.insert("join_rule".to_string(), join_rule);
+        let payload = serde_json::to_string(&body_params).chain_err(|| body_params_error!("join rule"))?;
+
+        let (body, status_code) =
+            RestApi::call_matrix(SendStateEventForEmptyKeyEndpoint::method(), &endpoint, &payload, &params)?;
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+        Ok(())
+    }
+
     fn set_room_name(&self, matrix_room_id: RoomId, name: String) -> Result<()> {
         let path_params = send_state_event_for_empty_key::PathParams {
             room_id: matrix_room_id,
@@ -470,6 +638,71 @@ impl super::MatrixApi for MatrixApi {
         }
         Ok(())
     }
+
+    fn set_user_powerlevels(
+        &self,
+        matrix_room_id: RoomId,
+        sender_id: UserId,
+        user_powerlevels: HashMap<UserId, i64>,
+    ) -> Result<()> {
+        // sending a power levels event without the users would take the power from the room creator
+        let mut power_levels = match self.get_powerlevels_event_content(matrix_room_id.clone(), Some(sender_id.clone()))? {
+            Some(power_levels) => power_levels,
+            None => {
+                debug!(self.logger, "Room {} has no power levels event, not updating the power levels", matrix_room_id);
+                return Ok(());
+            }
+        };
+
+        let mut users = match power_levels.get("users") {
+            Some(&Value::Object(ref users)) => users.clone(),
+            _ => Map::new(),
+        };
+        let sender_powerlevel = users.get(&sender_id.to_string()).and_then(|level| level.as_i64()).unwrap_or_default();
+        let mut changed = false;
+        for (matrix_user_id, powerlevel) in user_powerlevels {
+            let matrix_user_id = matrix_user_id.to_string();
+            let current_powerlevel = users.get(&matrix_user_id).and_then(|level| level.as_i64()).unwrap_or_default();
+            let is_sender = matrix_user_id == sender_id.to_string();
+            if (is_sender && powerlevel > current_powerlevel) || (!is_sender && current_powerlevel >= sender_powerlevel) ||
+                current_powerlevel == powerlevel
+            {
+                continue;
+            }
+
+            if powerlevel == 0 {
+                users.remove(&matrix_user_id);
+            } else {
+                users.insert(matrix_user_id, json!(powerlevel));
+            }
+            changed = true;
+        }
+
+        if !changed {
+            debug!(self.logger, "Power levels of the users in room {} are up to date", matrix_room_id);
+            return Ok(());
+        }
+
+        power_levels.insert("users".to_string(), Value::Object(users));
+        let payload = serde_json::to_string(&power_levels).chain_err(|| body_params_error!("power levels"))?;
+        let path_params = send_state_event_for_empty_key::PathParams {
+            room_id: matrix_room_id.clone(),
+            event_type: EventType::RoomPowerLevels,
+        };
+        let endpoint = self.base_url.clone() + &SendStateEventForEmptyKeyEndpoint::request_path(path_params);
+        let user_id = sender_id.to_string();
+        let mut params = self.params_hash();
+        params.insert("user_id", &user_id);
+
+        let (body, status_code) =
+            RestApi::call_matrix(SendStateEventForEmptyKeyEndpoint::method(), &endpoint, &payload, &params)?;
+        if !status_code.is_success() {
+            return Err(build_error(&endpoint, &body, &status_code));
+        }
+
+        debug!(self.logger, "Successfully updated the power levels of the users in room {}", matrix_room_id);
+        Ok(())
+    }
 }
 
 fn build_error(endpoint: &str, body: &str, status_code: &StatusCode) -> Error {
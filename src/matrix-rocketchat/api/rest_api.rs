@@ -3,7 +3,7 @@ use std::io::Read;
 
 use url;
 use reqwest::{Client, Method, StatusCode, Url};
-use reqwest::header::Headers;
+use reqwest::header::{Authorization, Bearer, Headers};
 use ruma_client_api::Method as RumaHttpMethod;
 
 use errors::*;
@@ -13,7 +13,8 @@ use api::rocketchat::Endpoint;
 pub struct RestApi {}
 
 impl RestApi {
-    /// Call a matrix REST API endpoint
+    /// Call a matrix REST API endpoint. An `access_token` parameter is sent in the `Authorization`
+    /// header instead of the query string, so that it doesn't end up in access logs.
     pub fn call_matrix<'a>(
         method: RumaHttpMethod,
         url: &str,
@@ -27,7 +28,14 @@ impl RestApi {
             RumaHttpMethod::Put => Method::Put,
         };
 
-        RestApi::call(method, url, payload, params, None)
+        let mut params = params.clone();
+        let headers = params.remove("access_token").map(|access_token| {
+            let mut headers = Headers::new();
+            headers.set(Authorization(Bearer { token: access_token.to_string() }));
+            headers
+        });
+
+        RestApi::call(method, url, payload, &params, headers)
     }
 
     /// Call a Rocket.Chat API endpoint
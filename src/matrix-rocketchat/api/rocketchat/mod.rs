@@ -9,10 +9,20 @@ use slog::Logger;
 use api::RestApi;
 use errors::*;
 use i18n::*;
+use log;
 
 /// Rocket.Chat REST API v1
 pub mod v1;
 
+/// Type of the system message that is sent when a user joined a channel
+pub const USER_JOINED_MESSAGE_TYPE: &'static str = "uj";
+/// Type of the system message that is sent when a user left a channel
+pub const USER_LEFT_MESSAGE_TYPE: &'static str = "ul";
+/// Type of the system message that is sent when a user got a role in a channel
+pub const ROLE_ADDED_MESSAGE_TYPE: &'static str = "subscription-role-added";
+/// Type of the system message that is sent when a user lost a role in a channel
+pub const ROLE_REMOVED_MESSAGE_TYPE: &'static str = "subscription-role-removed";
+
 /// A Rocket.Chat REST API endpoint.
 pub trait Endpoint {
     /// HTTP Method
@@ -37,8 +47,13 @@ pub struct Channel {
     pub id: String,
     /// Name of the Rocket.Chat room
     pub name: Option<String>,
-    /// List of users in the room
+    /// List of users in the room, not all endpoints include it
+    #[serde(default)]
     pub usernames: Vec<String>,
+    /// Type of the room, `c` for public channels, `p` for private groups and `d` for direct
+    /// messages
+    #[serde(default, rename = "t")]
+    pub channel_type: String,
 }
 
 /// A Rocket.Chat message
@@ -58,6 +73,28 @@ pub struct Message {
     pub user_name: String,
     /// Message content
     pub text: String,
+    /// Type of system messages, like `uj` when a user joined the channel, not set for messages
+    /// that were sent by a user
+    #[serde(default, rename = "t", skip_serializing_if = "Option::is_none")]
+    pub message_type: Option<String>,
+}
+
+impl Message {
+    /// Messages that Rocket.Chat sends when a user joined or left a channel
+    pub fn is_membership_change(&self) -> bool {
+        match self.message_type {
+            Some(ref message_type) => message_type == USER_JOINED_MESSAGE_TYPE || message_type == USER_LEFT_MESSAGE_TYPE,
+            None => false,
+        }
+    }
+
+    /// Messages that Rocket.Chat sends when a user got or lost a role in a channel
+    pub fn is_role_change(&self) -> bool {
+        match self.message_type {
+            Some(ref message_type) => message_type == ROLE_ADDED_MESSAGE_TYPE || message_type == ROLE_REMOVED_MESSAGE_TYPE,
+            None => false,
+        }
+    }
 }
 
 /// A Rocket.Chat user
@@ -70,20 +107,60 @@ pub struct User {
     pub username: String,
 }
 
+/// Roles of a user in a Rocket.Chat room
+#[derive(Deserialize, Debug, Serialize)]
+pub struct RoomRoles {
+    /// The user that has the roles
+    #[serde(rename = "u")]
+    pub user: User,
+    /// Roles of the user in the room, like `owner` or `moderator`
+    pub roles: Vec<String>,
+}
+
 /// Rocket.Chat REST API
 pub trait RocketchatApi {
+    /// Get information like the members of a public channel
+    fn channels_info(&self, room_id: &str) -> Result<Channel>;
+    /// Join a channel
+    fn channels_join(&self, room_id: &str) -> Result<()>;
+    /// Remove another user from a channel
+    fn channels_kick(&self, room_id: &str, user_id: &str) -> Result<()>;
+    /// Leave a channel
+    fn channels_leave(&self, room_id: &str) -> Result<()>;
     /// List of channels on the Rocket.Chat server
     fn channels_list(&self) -> Result<Vec<Channel>>;
+    /// List of users that have a role in a channel
+    fn channels_roles(&self, room_id: &str) -> Result<Vec<RoomRoles>>;
     /// Get the logged in users username
     fn current_username(&self) -> Result<String>;
+    /// Create a direct message channel with a user, the existing channel is returned if there
+    /// already is one
+    fn direct_messages_create(&self, username: &str) -> Result<Channel>;
     /// List of direct messages the user is part of
     fn direct_messages_list(&self) -> Result<Vec<Channel>>;
-    /// Login a user on the Rocket.Chat server
-    fn login(&self, username: &str, password: &str) -> Result<(String, String)>;
+    /// Get information like the members of a private group
+    fn groups_info(&self, room_id: &str) -> Result<Channel>;
+    /// Remove another user from a private group
+    fn groups_kick(&self, room_id: &str, user_id: &str) -> Result<()>;
+    /// Leave a private group
+    fn groups_leave(&self, room_id: &str) -> Result<()>;
+    /// List of private groups the user is part of
+    fn groups_list(&self) -> Result<Vec<Channel>>;
+    /// List of users that have a role in a private group
+    fn groups_roles(&self, room_id: &str) -> Result<Vec<RoomRoles>>;
+    /// Login a user on the Rocket.Chat server, the `totp_code` is only needed when two-factor
+    /// authentication is enabled for the user
+    fn login(&self, username: &str, password: &str, totp_code: Option<&str>) -> Result<(String, String)>;
+    /// Logout the user and invalidate the auth token on the Rocket.Chat server
+    fn logout(&self) -> Result<()>;
     /// Post a chat message
     fn post_chat_message(&self, text: &str, room_id: &str) -> Result<()>;
+    /// Get information like the type and the members of any room the user can see
+    fn rooms_info(&self, room_id: &str) -> Result<Channel>;
     /// Get information like user_id, status, etc. about a user
     fn users_info(&self, username: &str) -> Result<User>;
+    /// Get information like username, status, etc. about a user by the users ID
+    fn users_info_by_id(&self, rocketchat_user_id: &str) -> Result<User>;
     /// Set credentials that are used for all API calls that need authentication
     fn with_credentials(self: Box<Self>, user_id: String, auth_token: String) -> Box<RocketchatApi>;
 }
@@ -104,7 +181,7 @@ impl RocketchatApi {
         let (body, status_code) = match RestApi::call(Method::Get, &url, "", &params, None) {
             Ok((body, status_code)) => (body, status_code),
             Err(err) => {
-                debug!(logger, "{}", err);
+                log::log_debug(&logger, &err);
                 bail_error!(
                     ErrorKind::RocketchatServerUnreachable(url.clone()),
                     t!(["errors", "rocketchat_server_unreachable"]).with_vars(vec![("rocketchat_url", url)])
@@ -2,26 +2,59 @@ use std::collections::HashMap;
 
 use reqwest::header::{ContentType, Headers};
 use reqwest::{Method, StatusCode};
+use serde::de::DeserializeOwned;
 use serde_json;
 use slog::Logger;
 
 use api::RestApi;
 use errors::*;
 use i18n::*;
-use super::{Channel, Endpoint, User};
+use super::{Channel, Endpoint, RoomRoles, User};
 
 /// Login endpoint path
 pub const LOGIN_PATH: &'static str = "/api/v1/login";
+/// Logout endpoint path
+pub const LOGOUT_PATH: &'static str = "/api/v1/logout";
 /// Me endpoint path
 pub const ME_PATH: &'static str = "/api/v1/me";
 /// Users list endpoint path
 pub const USERS_INFO_PATH: &'static str = "/api/v1/users.info";
+/// Channels info endpoint path
+pub const CHANNELS_INFO_PATH: &'static str = "/api/v1/channels.info";
+/// Channels join endpoint path
+pub const CHANNELS_JOIN_PATH: &'static str = "/api/v1/channels.join";
+/// Channels kick endpoint path
+pub const CHANNELS_KICK_PATH: &'static str = "/api/v1/channels.kick";
+/// Channels leave endpoint path
+pub const CHANNELS_LEAVE_PATH: &'static str = "/api/v1/channels.leave";
 /// Channels list endpoint path
 pub const CHANNELS_LIST_PATH: &'static str = "/api/v1/channels.list";
+/// Channels roles endpoint path
+pub const CHANNELS_ROLES_PATH: &'static str = "/api/v1/channels.roles";
+/// Direct messages create endpoint path
+pub const DIRECT_MESSAGES_CREATE_PATH: &'static str = "/api/v1/im.create";
 /// Direct messages list endpoint path
 pub const DIRECT_MESSAGES_LIST_PATH: &'static str = "/api/v1/dm.list";
+/// Groups info endpoint path
+pub const GROUPS_INFO_PATH: &'static str = "/api/v1/groups.info";
+/// Groups kick endpoint path
+pub const GROUPS_KICK_PATH: &'static str = "/api/v1/groups.kick";
+/// Groups leave endpoint path
+pub const GROUPS_LEAVE_PATH: &'static str = "/api/v1/groups.leave";
+/// Groups list endpoint path
+pub const GROUPS_LIST_PATH: &'static str = "/api/v1/groups.list";
+/// Groups roles endpoint path
+pub const GROUPS_ROLES_PATH: &'static str = "/api/v1/groups.roles";
 /// Post chat message endpoint path
 pub const POST_CHAT_MESSAGE_PATH: &'static str = "/api/v1/chat.postMessage";
+/// Rooms info endpoint path
+pub const ROOMS_INFO_PATH: &'static str = "/api/v1/rooms.info";
+/// Number of entries that are requested per page from endpoints that support pagination
+pub const PAGE_SIZE: usize = 100;
+/// Error returned by the login endpoint when the user has to provide a two-factor authentication code
+const TOTP_REQUIRED_ERROR: &'static str = "totp-required";
+/// Error returned by the login endpoint when the two-factor authentication code is invalid
+const TOTP_INVALID_ERROR: &'static str = "totp-invalid";
 
 /// V1 get endpoints that require authentication
 pub struct GetWithAuthEndpoint<'a> {
@@ -58,6 +91,129 @@ impl<'a> Endpoint for GetWithAuthEndpoint<'a> {
     }
 }
 
+/// V1 channels join endpoint
+pub struct ChannelsJoinEndpoint<'a> {
+    base_url: String,
+    user_id: String,
+    auth_token: String,
+    payload: ChannelsJoinPayload<'a>,
+}
+
+/// Payload of the channels join endpoint
+#[derive(Serialize)]
+pub struct ChannelsJoinPayload<'a> {
+    #[serde(rename = "roomId")]
+    room_id: &'a str,
+}
+
+impl<'a> Endpoint for ChannelsJoinEndpoint<'a> {
+    fn method(&self) -> Method {
+        Method::Post
+    }
+
+    fn url(&self) -> String {
+        self.base_url.clone() + CHANNELS_JOIN_PATH
+    }
+
+    fn payload(&self) -> Result<String> {
+        let payload = serde_json::to_string(&self.payload).chain_err(|| {
+            ErrorKind::InvalidJSON("Could not serialize channels join payload".to_string())
+        })?;
+        Ok(payload)
+    }
+
+    fn headers(&self) -> Option<Headers> {
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set_raw("X-User-Id", vec![self.user_id.clone().into_bytes()]);
+        headers.set_raw("X-Auth-Token", vec![self.auth_token.clone().into_bytes()]);
+        Some(headers)
+    }
+}
+
+/// V1 direct messages create endpoint
+pub struct DirectMessagesCreateEndpoint<'a> {
+    base_url: String,
+    user_id: String,
+    auth_token: String,
+    payload: DirectMessagesCreatePayload<'a>,
+}
+
+/// Payload of the direct messages create endpoint
+#[derive(Serialize)]
+pub struct DirectMessagesCreatePayload<'a> {
+    username: &'a str,
+}
+
+impl<'a> Endpoint for DirectMessagesCreateEndpoint<'a> {
+    fn method(&self) -> Method {
+        Method::Post
+    }
+
+    fn url(&self) -> String {
+        self.base_url.clone() + DIRECT_MESSAGES_CREATE_PATH
+    }
+
+    fn payload(&self) -> Result<String> {
+        let payload = serde_json::to_string(&self.payload).chain_err(|| {
+            ErrorKind::InvalidJSON("Could not serialize direct messages create payload".to_string())
+        })?;
+        Ok(payload)
+    }
+
+    fn headers(&self) -> Option<Headers> {
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set_raw("X-User-Id", vec![self.user_id.clone().into_bytes()]);
+        headers.set_raw("X-Auth-Token", vec![self.auth_token.clone().into_bytes()]);
+        Some(headers)
+    }
+}
+
+/// V1 endpoints that remove a user from a channel or a private group
+pub struct MembershipEndpoint<'a> {
+    base_url: String,
+    path: &'static str,
+    user_id: String,
+    auth_token: String,
+    payload: MembershipPayload<'a>,
+}
+
+/// Payload of the endpoints that remove a user from a channel or a private group, the user ID is
+/// only needed when another user is kicked
+#[derive(Serialize)]
+pub struct MembershipPayload<'a> {
+    #[serde(rename = "roomId")]
+    room_id: &'a str,
+    #[serde(rename = "userId", skip_serializing_if = "Option::is_none")]
+    user_id: Option<&'a str>,
+}
+
+impl<'a> Endpoint for MembershipEndpoint<'a> {
+    fn method(&self) -> Method {
+        Method::Post
+    }
+
+    fn url(&self) -> String {
+        self.base_url.clone() + self.path
+    }
+
+    fn payload(&self) -> Result<String> {
+        let payload = serde_json::to_string(&self.payload).chain_err(|| {
+            ErrorKind::InvalidJSON("Could not serialize membership payload".to_string())
+        })?;
+        Ok(payload)
+    }
+
+    fn headers(&self) -> Option<Headers> {
+        let mut headers = Headers::new();
+        headers.set(ContentType::json());
+        headers.set_raw("X-User-Id", vec![self.user_id.clone().into_bytes()]);
+        headers.set_raw("X-Auth-Token", vec![self.auth_token.clone().into_bytes()]);
+        Some(headers)
+    }
+}
+
 /// V1 login endpoint
 pub struct LoginEndpoint<'a> {
     base_url: String,
@@ -69,6 +225,8 @@ pub struct LoginEndpoint<'a> {
 pub struct LoginPayload<'a> {
     username: &'a str,
     password: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<&'a str>,
 }
 
 impl<'a> Endpoint for LoginEndpoint<'a> {
@@ -133,11 +291,20 @@ impl<'a> Endpoint for PostChatMessageEndpoint<'a> {
     }
 }
 
+/// Response payload from the Rocket.Chat channels.info endpoint.
+#[derive(Deserialize)]
+pub struct ChannelsInfoResponse {
+    /// The channel
+    pub channel: Channel,
+}
+
 /// Response payload from the Rocket.Chat channels.list endpoint.
 #[derive(Deserialize)]
 pub struct ChannelsListResponse {
     /// A list of channels on the Rocket.Chat server
     pub channels: Vec<Channel>,
+    /// The total number of channels on the Rocket.Chat server
+    pub total: Option<usize>,
 }
 
 /// User credentials.
@@ -151,11 +318,74 @@ pub struct Credentials {
     pub user_id: String,
 }
 
+/// Response payload from the Rocket.Chat im.create endpoint.
+#[derive(Deserialize)]
+pub struct DirectMessagesCreateResponse {
+    /// The direct message channel with the other user
+    pub room: Channel,
+}
+
 /// Response payload from the Rocket.Chat im.list endpoint.
 #[derive(Deserialize)]
 pub struct DirectMessagesListResponse {
     /// A list of direct messages that the user is part of.
     pub ims: Vec<Channel>,
+    /// The total number of direct messages the user is part of
+    pub total: Option<usize>,
+}
+
+/// Response payload from the Rocket.Chat groups.info endpoint.
+#[derive(Deserialize)]
+pub struct GroupsInfoResponse {
+    /// The private group
+    pub group: Channel,
+}
+
+/// Response payload from the Rocket.Chat groups.list endpoint.
+#[derive(Deserialize)]
+pub struct GroupsListResponse {
+    /// A list of private groups that the user is part of
+    pub groups: Vec<Channel>,
+    /// The total number of private groups the user is part of
+    pub total: Option<usize>,
+}
+
+/// A response from an endpoint that returns a list of channels page by page.
+trait ChannelsPage {
+    /// The channels that are on the current page
+    fn channels(self) -> Vec<Channel>;
+    /// The total number of channels, `None` if the server doesn't support pagination
+    fn total(&self) -> Option<usize>;
+}
+
+impl ChannelsPage for ChannelsListResponse {
+    fn channels(self) -> Vec<Channel> {
+        self.channels
+    }
+
+    fn total(&self) -> Option<usize> {
+        self.total
+    }
+}
+
+impl ChannelsPage for DirectMessagesListResponse {
+    fn channels(self) -> Vec<Channel> {
+        self.ims
+    }
+
+    fn total(&self) -> Option<usize> {
+        self.total
+    }
+}
+
+impl ChannelsPage for GroupsListResponse {
+    fn channels(self) -> Vec<Channel> {
+        self.groups
+    }
+
+    fn total(&self) -> Option<usize> {
+        self.total
+    }
 }
 
 #[derive(Deserialize)]
@@ -174,6 +404,20 @@ pub struct MeResponse {
     pub username: String,
 }
 
+/// Response payload from the Rocket.Chat channels.roles and groups.roles endpoints.
+#[derive(Deserialize)]
+pub struct RolesResponse {
+    /// Users that have a role in the room
+    pub roles: Vec<RoomRoles>,
+}
+
+/// Response payload from the Rocket.Chat rooms.info endpoint.
+#[derive(Deserialize)]
+pub struct RoomsInfoResponse {
+    /// The room, which can be a channel, a private group or a direct message
+    pub room: Channel,
+}
+
 /// Response payload from the Rocket.Chat users.info endpoint.
 #[derive(Deserialize)]
 pub struct UsersInfoResponse {
@@ -204,34 +448,201 @@ impl RocketchatApi {
             auth_token: "".to_string(),
         }
     }
-}
 
-impl super::RocketchatApi for RocketchatApi {
-    fn channels_list(&self) -> Result<Vec<Channel>> {
-        debug!(self.logger, "Getting channel list from Rocket.Chat server {}", &self.base_url);
+    /// Get all the pages of a list endpoint and return the channels from all the pages.
+    /// Servers that don't support pagination ignore the parameters and return everything on the
+    /// first page.
+    fn channels_from_all_pages<R>(&self, path: &'static str, endpoint_name: &str) -> Result<Vec<Channel>>
+    where
+        R: ChannelsPage + DeserializeOwned,
+    {
+        let mut channels = Vec::new();
+        let mut offset: usize = 0;
+
+        loop {
+            let offset_param = offset.to_string();
+            let count_param = PAGE_SIZE.to_string();
+            let mut query_params = HashMap::new();
+            query_params.insert("offset", offset_param.as_str());
+            query_params.insert("count", count_param.as_str());
+            let list_endpoint = GetWithAuthEndpoint {
+                base_url: self.base_url.clone(),
+                user_id: self.user_id.clone(),
+                auth_token: self.auth_token.clone(),
+                path: path,
+                query_params: query_params,
+            };
+
+            let (body, status_code) = RestApi::call_rocketchat(&list_endpoint)?;
+            if !status_code.is_success() {
+                return Err(build_error(&list_endpoint.url(), &body, &status_code));
+            }
+
+            let page: R = serde_json::from_str(&body).chain_err(|| {
+                ErrorKind::InvalidJSON(format!(
+                    "Could not deserialize response from Rocket.Chat {} API endpoint: `{}`",
+                    endpoint_name,
+                    body
+                ))
+            })?;
+
+            let total = page.total();
+            let channels_on_page = page.channels();
+            offset += channels_on_page.len();
+            let is_last_page = channels_on_page.is_empty() || offset >= total.unwrap_or(offset);
+            channels.extend(channels_on_page);
+
+            if is_last_page {
+                debug!(self.logger, "Got {} entries from {} on {}", channels.len(), endpoint_name, &self.base_url);
+                return Ok(channels);
+            }
+        }
+    }
 
-        let channels_list_endpoint = GetWithAuthEndpoint {
+    fn change_membership(&self, path: &'static str, room_id: &str, user_id: Option<&str>) -> Result<()> {
+        let membership_endpoint = MembershipEndpoint {
             base_url: self.base_url.clone(),
+            path: path,
             user_id: self.user_id.clone(),
             auth_token: self.auth_token.clone(),
-            path: CHANNELS_LIST_PATH,
-            query_params: HashMap::new(),
+            payload: MembershipPayload {
+                room_id: room_id,
+                user_id: user_id,
+            },
+        };
+
+        let (body, status_code) = RestApi::call_rocketchat(&membership_endpoint)?;
+        if !status_code.is_success() {
+            return Err(build_error(&membership_endpoint.url(), &body, &status_code));
+        }
+
+        Ok(())
+    }
+
+    fn room_info<R>(&self, path: &'static str, endpoint_name: &str, room_id: &str) -> Result<R>
+    where
+        R: DeserializeOwned,
+    {
+        let mut query_params = HashMap::new();
+        query_params.insert("roomId", room_id);
+        let room_info_endpoint = GetWithAuthEndpoint {
+            base_url: self.base_url.clone(),
+            user_id: self.user_id.clone(),
+            auth_token: self.auth_token.clone(),
+            path: path,
+            query_params: query_params,
+        };
+
+        let (body, status_code) = RestApi::call_rocketchat(&room_info_endpoint)?;
+        if !status_code.is_success() {
+            return Err(build_error(&room_info_endpoint.url(), &body, &status_code));
+        }
+
+        serde_json::from_str(&body).chain_err(|| {
+            ErrorKind::InvalidJSON(format!(
+                "Could not deserialize response from Rocket.Chat {} API endpoint: `{}`",
+                endpoint_name,
+                body
+            ))
+        })
+    }
+
+    fn roles(&self, path: &'static str, endpoint_name: &str, room_id: &str) -> Result<Vec<RoomRoles>> {
+        let mut query_params = HashMap::new();
+        query_params.insert("roomId", room_id);
+        let roles_endpoint = GetWithAuthEndpoint {
+            base_url: self.base_url.clone(),
+            user_id: self.user_id.clone(),
+            auth_token: self.auth_token.clone(),
+            path: path,
+            query_params: query_params,
         };
 
-        let (body, status_code) = RestApi::call_rocketchat(&channels_list_endpoint)?;
+        let (body, status_code) = RestApi::call_rocketchat(&roles_endpoint)?;
         if !status_code.is_success() {
-            return Err(build_error(&channels_list_endpoint.url(), &body, &status_code));
+            return Err(build_error(&roles_endpoint.url(), &body, &status_code));
         }
 
-        let channels_list_response: ChannelsListResponse = serde_json::from_str(&body).chain_err(|| {
+        let roles_response: RolesResponse = serde_json::from_str(&body).chain_err(|| {
             ErrorKind::InvalidJSON(format!(
-                "Could not deserialize response from Rocket.Chat channels.list API \
-                                                endpoint: `{}`",
+                "Could not deserialize response from Rocket.Chat {} API endpoint: `{}`",
+                endpoint_name,
                 body
             ))
         })?;
 
-        Ok(channels_list_response.channels)
+        Ok(roles_response.roles)
+    }
+
+    fn users_info_by_param(&self, param: &'static str, value: &str) -> Result<User> {
+        let mut query_params = HashMap::new();
+        query_params.insert(param, value);
+        let users_info_endpoint = GetWithAuthEndpoint {
+            base_url: self.base_url.clone(),
+            user_id: self.user_id.clone(),
+            auth_token: self.auth_token.clone(),
+            path: USERS_INFO_PATH,
+            query_params: query_params,
+        };
+
+        let (body, status_code) = RestApi::call_rocketchat(&users_info_endpoint)?;
+        if !status_code.is_success() {
+            return Err(build_error(&users_info_endpoint.url(), &body, &status_code));
+        }
+
+        let users_info_response: UsersInfoResponse = serde_json::from_str(&body).chain_err(|| {
+            ErrorKind::InvalidJSON(
+                format!("Could not deserialize response from Rocket.Chat users.info API endpoint: `{}`", body),
+            )
+        })?;
+
+        Ok(users_info_response.user)
+    }
+}
+
+impl super::RocketchatApi for RocketchatApi {
+    fn channels_info(&self, room_id: &str) -> Result<Channel> {
+        debug!(self.logger, "Querying channel info for channel {} on Rocket.Chat server {}", room_id, &self.base_url);
+        let channels_info_response: ChannelsInfoResponse = self.room_info(CHANNELS_INFO_PATH, "channels.info", room_id)?;
+        Ok(channels_info_response.channel)
+    }
+
+    fn channels_join(&self, room_id: &str) -> Result<()> {
+        debug!(self.logger, "Joining channel {} on Rocket.Chat server {}", room_id, &self.base_url);
+
+        let channels_join_endpoint = ChannelsJoinEndpoint {
+            base_url: self.base_url.clone(),
+            user_id: self.user_id.clone(),
+            auth_token: self.auth_token.clone(),
+            payload: ChannelsJoinPayload { room_id: room_id },
+        };
+
+        let (body, status_code) = RestApi::call_rocketchat(&channels_join_endpoint)?;
+        if !status_code.is_success() {
+            return Err(build_error(&channels_join_endpoint.url(), &body, &status_code));
+        }
+
+        Ok(())
+    }
+
+    fn channels_kick(&self, room_id: &str, user_id: &str) -> Result<()> {
+        debug!(self.logger, "Kicking user {} from channel {} on Rocket.Chat server {}", user_id, room_id, &self.base_url);
+        self.change_membership(CHANNELS_KICK_PATH, room_id, Some(user_id))
+    }
+
+    fn channels_leave(&self, room_id: &str) -> Result<()> {
+        debug!(self.logger, "Leaving channel {} on Rocket.Chat server {}", room_id, &self.base_url);
+        self.change_membership(CHANNELS_LEAVE_PATH, room_id, None)
+    }
+
+    fn channels_list(&self) -> Result<Vec<Channel>> {
+        debug!(self.logger, "Getting channel list from Rocket.Chat server {}", &self.base_url);
+        self.channels_from_all_pages::<ChannelsListResponse>(CHANNELS_LIST_PATH, "channels.list")
+    }
+
+    fn channels_roles(&self, room_id: &str) -> Result<Vec<RoomRoles>> {
+        debug!(self.logger, "Getting roles for channel {} from Rocket.Chat server {}", room_id, &self.base_url);
+        self.roles(CHANNELS_ROLES_PATH, "channels.roles", room_id)
     }
 
     fn current_username(&self) -> Result<String> {
@@ -257,34 +668,62 @@ impl super::RocketchatApi for RocketchatApi {
         Ok(me_response.username)
     }
 
-    fn direct_messages_list(&self) -> Result<Vec<Channel>> {
-        debug!(self.logger, "Getting direct messages list from Rocket.Chat server {}", &self.base_url);
+    fn direct_messages_create(&self, username: &str) -> Result<Channel> {
+        debug!(self.logger, "Creating direct message channel with user {} on Rocket.Chat server {}", username, &self.base_url);
 
-        let direct_messages_list_endpoint = GetWithAuthEndpoint {
+        let direct_messages_create_endpoint = DirectMessagesCreateEndpoint {
             base_url: self.base_url.clone(),
             user_id: self.user_id.clone(),
             auth_token: self.auth_token.clone(),
-            path: DIRECT_MESSAGES_LIST_PATH,
-            query_params: HashMap::new(),
+            payload: DirectMessagesCreatePayload { username: username },
         };
 
-        let (body, status_code) = RestApi::call_rocketchat(&direct_messages_list_endpoint)?;
+        let (body, status_code) = RestApi::call_rocketchat(&direct_messages_create_endpoint)?;
         if !status_code.is_success() {
-            return Err(build_error(&direct_messages_list_endpoint.url(), &body, &status_code));
+            return Err(build_error(&direct_messages_create_endpoint.url(), &body, &status_code));
         }
 
-        let direct_messages_list_response: DirectMessagesListResponse = serde_json::from_str(&body).chain_err(|| {
-            ErrorKind::InvalidJSON(format!(
-                "Could not deserialize response from Rocket.Chat dm.list API \
-                                                endpoint: `{}`",
-                body
-            ))
+        let direct_messages_create_response: DirectMessagesCreateResponse = serde_json::from_str(&body).chain_err(|| {
+            ErrorKind::InvalidJSON(
+                format!("Could not deserialize response from Rocket.Chat im.create API endpoint: `{}`", body),
+            )
         })?;
 
-        Ok(direct_messages_list_response.ims)
+        Ok(direct_messages_create_response.room)
+    }
+
+    fn direct_messages_list(&self) -> Result<Vec<Channel>> {
+        debug!(self.logger, "Getting direct messages list from Rocket.Chat server {}", &self.base_url);
+        self.channels_from_all_pages::<DirectMessagesListResponse>(DIRECT_MESSAGES_LIST_PATH, "dm.list")
+    }
+
+    fn groups_info(&self, room_id: &str) -> Result<Channel> {
+        debug!(self.logger, "Querying group info for group {} on Rocket.Chat server {}", room_id, &self.base_url);
+        let groups_info_response: GroupsInfoResponse = self.room_info(GROUPS_INFO_PATH, "groups.info", room_id)?;
+        Ok(groups_info_response.group)
+    }
+
+    fn groups_kick(&self, room_id: &str, user_id: &str) -> Result<()> {
+        debug!(self.logger, "Kicking user {} from group {} on Rocket.Chat server {}", user_id, room_id, &self.base_url);
+        self.change_membership(GROUPS_KICK_PATH, room_id, Some(user_id))
+    }
+
+    fn groups_leave(&self, room_id: &str) -> Result<()> {
+        debug!(self.logger, "Leaving group {} on Rocket.Chat server {}", room_id, &self.base_url);
+        self.change_membership(GROUPS_LEAVE_PATH, room_id, None)
+    }
+
+    fn groups_list(&self) -> Result<Vec<Channel>> {
+        debug!(self.logger, "Getting private groups list from Rocket.Chat server {}", &self.base_url);
+        self.channels_from_all_pages::<GroupsListResponse>(GROUPS_LIST_PATH, "groups.list")
+    }
+
+    fn groups_roles(&self, room_id: &str) -> Result<Vec<RoomRoles>> {
+        debug!(self.logger, "Getting roles for private group {} from Rocket.Chat server {}", room_id, &self.base_url);
+        self.roles(GROUPS_ROLES_PATH, "groups.roles", room_id)
     }
 
-    fn login(&self, username: &str, password: &str) -> Result<(String, String)> {
+    fn login(&self, username: &str, password: &str, totp_code: Option<&str>) -> Result<(String, String)> {
         debug!(self.logger, "Logging in user with username {} on Rocket.Chat server {}", username, &self.base_url);
 
         let login_endpoint = LoginEndpoint {
@@ -292,6 +731,7 @@ impl super::RocketchatApi for RocketchatApi {
             payload: LoginPayload {
                 username: username,
                 password: password,
+                code: totp_code,
             },
         };
 
@@ -306,6 +746,25 @@ impl super::RocketchatApi for RocketchatApi {
         Ok((login_response.data.user_id, login_response.data.auth_token))
     }
 
+    fn logout(&self) -> Result<()> {
+        debug!(self.logger, "Logging out user with user_id {} on Rocket.Chat server {}", self.user_id, &self.base_url);
+
+        let logout_endpoint = GetWithAuthEndpoint {
+            base_url: self.base_url.clone(),
+            user_id: self.user_id.clone(),
+            auth_token: self.auth_token.clone(),
+            path: LOGOUT_PATH,
+            query_params: HashMap::new(),
+        };
+
+        let (body, status_code) = RestApi::call_rocketchat(&logout_endpoint)?;
+        if !status_code.is_success() {
+            return Err(build_error(&logout_endpoint.url(), &body, &status_code));
+        }
+
+        Ok(())
+    }
+
     fn post_chat_message(&self, text: &str, room_id: &str) -> Result<()> {
         debug!(self.logger, "Forwarding message to to Rocket.Chat room {}", room_id);
 
@@ -327,31 +786,20 @@ impl super::RocketchatApi for RocketchatApi {
         Ok(())
     }
 
+    fn rooms_info(&self, room_id: &str) -> Result<Channel> {
+        debug!(self.logger, "Querying room info for room {} on Rocket.Chat server {}", room_id, &self.base_url);
+        let rooms_info_response: RoomsInfoResponse = self.room_info(ROOMS_INFO_PATH, "rooms.info", room_id)?;
+        Ok(rooms_info_response.room)
+    }
+
     fn users_info(&self, username: &str) -> Result<User> {
         debug!(self.logger, "Querying user info for user {} on Rocket.Chat server {}", &username, &self.base_url);
+        self.users_info_by_param("username", username)
+    }
 
-        let mut query_params = HashMap::new();
-        query_params.insert("username", username);
-        let users_info_endpoint = GetWithAuthEndpoint {
-            base_url: self.base_url.clone(),
-            user_id: self.user_id.clone(),
-            auth_token: self.auth_token.clone(),
-            path: USERS_INFO_PATH,
-            query_params: query_params,
-        };
-
-        let (body, status_code) = RestApi::call_rocketchat(&users_info_endpoint)?;
-        if !status_code.is_success() {
-            return Err(build_error(&users_info_endpoint.url(), &body, &status_code));
-        }
-
-        let users_info_response: UsersInfoResponse = serde_json::from_str(&body).chain_err(|| {
-            ErrorKind::InvalidJSON(
-                format!("Could not deserialize response from Rocket.Chat users.info API endpoint: `{}`", body),
-            )
-        })?;
-
-        Ok(users_info_response.user)
+    fn users_info_by_id(&self, rocketchat_user_id: &str) -> Result<User> {
+        debug!(self.logger, "Querying user info for user ID {} on Rocket.Chat server {}", &rocketchat_user_id, &self.base_url);
+        self.users_info_by_param("userId", rocketchat_user_id)
     }
 
     fn with_credentials(mut self: Box<Self>, user_id: String, auth_token: String) -> Box<super::RocketchatApi> {
@@ -378,6 +826,23 @@ fn build_error(endpoint: &str, body: &str, status_code: &StatusCode) -> Error {
         };
 
     if *status_code == StatusCode::Unauthorized {
+        // the error field tells if the login failed because of two-factor authentication
+        match rocketchat_error_resp.error.as_ref().map(|error| error.as_ref()) {
+            Some(TOTP_REQUIRED_ERROR) => {
+                return Error {
+                    error_chain: ErrorKind::TwoFactorAuthenticationCodeRequired.into(),
+                    user_message: Some(t!(["errors", "two_factor_authentication_code_required"])),
+                };
+            }
+            Some(TOTP_INVALID_ERROR) => {
+                return Error {
+                    error_chain: ErrorKind::InvalidTwoFactorAuthenticationCode.into(),
+                    user_message: Some(t!(["errors", "invalid_two_factor_authentication_code"])),
+                };
+            }
+            _ => {}
+        }
+
         return Error {
             error_chain: ErrorKind::AuthenticationFailed(rocketchat_error_resp.message.unwrap_or_default()).into(),
             user_message: Some(t!(["errors", "authentication_failed"])),
@@ -1,4 +1,6 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::env;
 use std::fs::File;
 use std::io::Read;
 use std::net::SocketAddr;
@@ -6,6 +8,7 @@ use std::net::SocketAddr;
 use ruma_identifiers::UserId;
 use serde_yaml;
 
+use db::encryption::{DATABASE_ENCRYPTION_KEY_ENV_VARIABLE, EncryptionKey};
 use errors::*;
 
 /// Configuration for the application service.
@@ -36,6 +39,15 @@ pub struct Config {
     pub ssl_certificate_path: Option<String>,
     /// Path to the SSL key (only needed if SSL is used)
     pub ssl_key_path: Option<String>,
+    /// Base64 encoded 32 byte key that is used to encrypt the Rocket.Chat tokens in the database.
+    /// The environment variable `MATRIX_ROCKETCHAT_DATABASE_ENCRYPTION_KEY` takes precedence. The
+    /// tokens are stored in plaintext if no key is set.
+    #[serde(default)]
+    pub database_encryption_key: Option<String>,
+    /// Power levels that the members of a bridged room get for their roles in the Rocket.Chat
+    /// channel. The power levels have to be lower than 100, which is the power level of the bot.
+    #[serde(default = "default_power_levels")]
+    pub power_levels: HashMap<String, i64>,
 }
 
 impl Config {
@@ -44,15 +56,47 @@ impl Config {
         let mut config_content = String::new();
         let mut config_file = File::open(path).chain_err(|| ErrorKind::ReadFileError(path.to_string()))?;
         config_file.read_to_string(&mut config_content).chain_err(|| ErrorKind::ReadConfigError)?;
-        let config: Config = serde_yaml::from_str(&config_content).chain_err(|| {
+        let mut config: Config = serde_yaml::from_str(&config_content).chain_err(|| {
             ErrorKind::InvalidYAML("Could not serialize config".to_string())
         })?;
+
+        if let Ok(encoded_key) = env::var(DATABASE_ENCRYPTION_KEY_ENV_VARIABLE) {
+            config.database_encryption_key = Some(encoded_key);
+        }
+        // an invalid key is detected when the config is loaded and not when the first token is stored
+        config.database_encryption_key()?;
+
+        // the bot has the power level 100 and cannot grant higher or equal power levels
+        for (role, power_level) in &config.power_levels {
+            if *power_level >= 100 {
+                bail_error!(ErrorKind::InvalidPowerLevel(role.clone(), *power_level));
+            }
+        }
+
         Ok(config)
     }
 
+    /// The key that is used to encrypt the Rocket.Chat tokens in the database, `None` if the
+    /// tokens are stored in plaintext.
+    pub fn database_encryption_key(&self) -> Result<Option<EncryptionKey>> {
+        match self.database_encryption_key {
+            Some(ref encoded_key) => Ok(Some(EncryptionKey::from_base64(encoded_key)?)),
+            None => Ok(None),
+        }
+    }
+
     /// Matrix id of the bot user.
     pub fn matrix_bot_user_id(&self) -> Result<UserId> {
         let user_id = format!("@{}:{}", &self.sender_localpart, &self.hs_domain);
         UserId::try_from(&user_id).chain_err(|| ErrorKind::InvalidUserId(user_id)).map_err(Error::from)
     }
 }
+
+/// Rocket.Chat channel owners and moderators get power levels to kick, ban and redact if no power
+/// levels are configured.
+pub fn default_power_levels() -> HashMap<String, i64> {
+    let mut power_levels = HashMap::new();
+    power_levels.insert("owner".to_string(), 90);
+    power_levels.insert("moderator".to_string(), 50);
+    power_levels
+}
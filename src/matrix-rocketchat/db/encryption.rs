@@ -0,0 +1,250 @@
+use std::fmt;
+
+use base64;
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rand::rand_bytes;
+use openssl::sign::Signer;
+use openssl::symm::{self, Cipher};
+use ruma_identifiers::UserId;
+
+use errors::*;
+use super::schema::{rocketchat_servers, users_on_rocketchat_servers};
+
+/// Environment variable that contains the database encryption key, it takes precedence over the
+/// key in the config file
+pub const DATABASE_ENCRYPTION_KEY_ENV_VARIABLE: &'static str = "MATRIX_ROCKETCHAT_DATABASE_ENCRYPTION_KEY";
+/// Prefix of encrypted values, it's used to distinguish them from plaintext values that were
+/// stored before the tokens were encrypted
+const ENCRYPTED_VALUE_PREFIX: &'static str = "enc:v1:";
+/// Length of the encryption key in bytes
+const KEY_LENGTH: usize = 32;
+/// Length of the nonce in bytes
+const NONCE_LENGTH: usize = 12;
+/// Length of the authentication tag in bytes
+const TAG_LENGTH: usize = 16;
+/// Context that is used to derive the key for the token hashes from the encryption key, so the
+/// same key isn't used for two different algorithms
+const HASH_KEY_CONTEXT: &'static [u8] = b"matrix-rocketchat token hash v1";
+
+/// A key to encrypt the tokens that are stored in the database.
+#[derive(Clone)]
+pub struct EncryptionKey {
+    key: Vec<u8>,
+}
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "EncryptionKey([redacted])")
+    }
+}
+
+impl EncryptionKey {
+    /// Create an `EncryptionKey` from a base64 encoded string, the decoded key has to be
+    /// 32 bytes long.
+    pub fn from_base64(encoded_key: &str) -> Result<EncryptionKey> {
+        let key = base64::decode(encoded_key.trim()).chain_err(|| {
+            ErrorKind::InvalidDatabaseEncryptionKey("the key is not base64 encoded".to_string())
+        })?;
+
+        if key.len() != KEY_LENGTH {
+            let msg = format!("the key has to be {} bytes long, but it is {} bytes long", KEY_LENGTH, key.len());
+            bail_error!(ErrorKind::InvalidDatabaseEncryptionKey(msg));
+        }
+
+        Ok(EncryptionKey { key: key })
+    }
+
+    /// Encrypt a value with AES-256-GCM, the result contains the random nonce and the
+    /// authentication tag and is base64 encoded.
+    pub fn encrypt(&self, value: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LENGTH];
+        rand_bytes(&mut nonce).chain_err(|| ErrorKind::EncryptionError)?;
+
+        let mut tag = [0u8; TAG_LENGTH];
+        let ciphertext = symm::encrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(&nonce), &[], value.as_bytes(), &mut tag)
+            .chain_err(|| ErrorKind::EncryptionError)?;
+
+        let mut encrypted_value = nonce.to_vec();
+        encrypted_value.extend(ciphertext);
+        encrypted_value.extend(tag.iter());
+        Ok(format!("{}{}", ENCRYPTED_VALUE_PREFIX, base64::encode(&encrypted_value)))
+    }
+
+    /// Decrypt a value that was encrypted with `encrypt`. Values without the encryption prefix
+    /// were stored before the tokens were encrypted and are returned unchanged.
+    pub fn decrypt(&self, value: &str) -> Result<String> {
+        if !is_encrypted(value) {
+            return Ok(value.to_string());
+        }
+
+        let encrypted_value =
+            base64::decode(&value[ENCRYPTED_VALUE_PREFIX.len()..]).chain_err(|| ErrorKind::DecryptionError)?;
+        if encrypted_value.len() < NONCE_LENGTH + TAG_LENGTH {
+            bail_error!(ErrorKind::DecryptionError);
+        }
+
+        let (nonce, ciphertext_and_tag) = encrypted_value.split_at(NONCE_LENGTH);
+        let (ciphertext, tag) = ciphertext_and_tag.split_at(ciphertext_and_tag.len() - TAG_LENGTH);
+        let decrypted_value = symm::decrypt_aead(Cipher::aes_256_gcm(), &self.key, Some(nonce), &[], ciphertext, tag)
+            .chain_err(|| ErrorKind::DecryptionError)?;
+        String::from_utf8(decrypted_value).chain_err(|| ErrorKind::DecryptionError).map_err(Error::from)
+    }
+
+    /// A deterministic keyed hash (HMAC-SHA256) of a value. The encrypted values contain a random
+    /// nonce, the hash is used to find a stored value without decrypting all of them.
+    pub fn hash(&self, value: &str) -> Result<String> {
+        let hash_key = hmac_sha256(&self.key, HASH_KEY_CONTEXT)?;
+        let signature = hmac_sha256(&hash_key, value.as_bytes())?;
+        Ok(base64::encode(&signature))
+    }
+}
+
+fn hmac_sha256(key: &[u8], value: &[u8]) -> Result<Vec<u8>> {
+    let pkey = PKey::hmac(key).chain_err(|| ErrorKind::EncryptionError)?;
+    let mut signer = Signer::new(MessageDigest::sha256(), &pkey).chain_err(|| ErrorKind::EncryptionError)?;
+    signer.update(value).chain_err(|| ErrorKind::EncryptionError)?;
+    signer.finish().chain_err(|| ErrorKind::EncryptionError).map_err(Error::from)
+}
+
+/// Encrypt an optional token. The token is returned unchanged if no key is configured.
+pub fn encrypt(encryption_key: Option<&EncryptionKey>, value: &Option<String>) -> Result<Option<String>> {
+    match (encryption_key, value.as_ref()) {
+        (Some(encryption_key), Some(value)) => Ok(Some(encryption_key.encrypt(value)?)),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// Decrypt an optional token. Plaintext tokens are returned unchanged, encrypted tokens can't be
+/// decrypted if no key is configured.
+pub fn decrypt(encryption_key: Option<&EncryptionKey>, value: &Option<String>) -> Result<Option<String>> {
+    match (encryption_key, value.as_ref()) {
+        (Some(encryption_key), Some(value)) => Ok(Some(encryption_key.decrypt(value)?)),
+        (None, Some(value)) if is_encrypted(value) => bail_error!(ErrorKind::DatabaseEncryptionKeyMissing),
+        _ => Ok(value.clone()),
+    }
+}
+
+/// The keyed hash of an optional token, `None` if no key is configured.
+pub fn hash(encryption_key: Option<&EncryptionKey>, value: &Option<String>) -> Result<Option<String>> {
+    match (encryption_key, value.as_ref()) {
+        (Some(encryption_key), Some(value)) => Ok(Some(encryption_key.hash(value)?)),
+        _ => Ok(None),
+    }
+}
+
+/// Returns true if the value was encrypted with `EncryptionKey::encrypt`, and false otherwise.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_VALUE_PREFIX)
+}
+
+/// Returns true if the database contains encrypted tokens. The application service can't
+/// read them without the key.
+pub fn has_encrypted_tokens(connection: &SqliteConnection) -> Result<bool> {
+    let rocketchat_tokens: Vec<Option<String>> = rocketchat_servers::table
+        .select(rocketchat_servers::rocketchat_token)
+        .filter(rocketchat_servers::rocketchat_token.is_not_null())
+        .load(connection)
+        .chain_err(|| ErrorKind::DBSelectError)?;
+    let rocketchat_auth_tokens: Vec<Option<String>> = users_on_rocketchat_servers::table
+        .select(users_on_rocketchat_servers::rocketchat_auth_token)
+        .filter(users_on_rocketchat_servers::rocketchat_auth_token.is_not_null())
+        .load(connection)
+        .chain_err(|| ErrorKind::DBSelectError)?;
+    Ok(rocketchat_tokens.iter().chain(rocketchat_auth_tokens.iter()).any(|token| {
+        token.as_ref().map(|token| is_encrypted(token)).unwrap_or(false)
+    }))
+}
+
+/// Encrypt the tokens that are still stored in plaintext. This migrates databases that were
+/// created before the tokens were encrypted and fails if the encrypted tokens cannot be
+/// decrypted with the given key, so a wrong key is detected when the application service starts.
+pub fn encrypt_plaintext_tokens(connection: &SqliteConnection, encryption_key: &EncryptionKey) -> Result<usize> {
+    update_tokens(connection, encryption_key, |token| {
+        if is_encrypted(token) {
+            encryption_key.decrypt(token)?;
+            return Ok(None);
+        }
+
+        Ok(Some(encryption_key.encrypt(token)?))
+    })
+}
+
+/// Re-encrypt all the tokens with a new key. The tokens that are still stored in plaintext are
+/// encrypted as well. Returns the number of tokens that were updated.
+pub fn reencrypt_tokens(
+    connection: &SqliteConnection,
+    old_encryption_key: Option<&EncryptionKey>,
+    new_encryption_key: &EncryptionKey,
+) -> Result<usize> {
+    update_tokens(connection, new_encryption_key, |token| {
+        let decrypted_token = match old_encryption_key {
+            Some(old_encryption_key) => old_encryption_key.decrypt(token)?,
+            None if is_encrypted(token) => {
+                let msg = "the tokens are encrypted, but no old key was provided".to_string();
+                bail_error!(ErrorKind::InvalidDatabaseEncryptionKey(msg));
+            }
+            None => token.to_string(),
+        };
+        Ok(Some(new_encryption_key.encrypt(&decrypted_token)?))
+    })
+}
+
+/// Update all the stored tokens in a single transaction. The function returns the new value of
+/// the token, or `None` if the token stays the same. The hashes of the Rocket.Chat server tokens
+/// are updated with the given key.
+fn update_tokens<F>(connection: &SqliteConnection, encryption_key: &EncryptionKey, update_token: F) -> Result<usize>
+where
+    F: Fn(&str) -> Result<Option<String>>,
+{
+    connection.transaction(|| {
+        let mut updated_tokens = 0;
+
+        let rocketchat_servers: Vec<(String, Option<String>)> = rocketchat_servers::table
+            .select((rocketchat_servers::id, rocketchat_servers::rocketchat_token))
+            .filter(rocketchat_servers::rocketchat_token.is_not_null())
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        for (id, rocketchat_token) in rocketchat_servers {
+            let rocketchat_token = rocketchat_token.unwrap_or_default();
+            let updated_rocketchat_token = update_token(&rocketchat_token)?;
+            if updated_rocketchat_token.is_some() {
+                updated_tokens += 1;
+            }
+
+            let rocketchat_token = updated_rocketchat_token.unwrap_or(rocketchat_token);
+            let rocketchat_token_hash = encryption_key.hash(&encryption_key.decrypt(&rocketchat_token)?)?;
+            diesel::update(rocketchat_servers::table.find(id))
+                .set((
+                    rocketchat_servers::rocketchat_token.eq(Some(rocketchat_token)),
+                    rocketchat_servers::rocketchat_token_hash.eq(Some(rocketchat_token_hash)),
+                ))
+                .execute(connection)
+                .chain_err(|| ErrorKind::DBUpdateError)?;
+        }
+
+        let users_on_rocketchat_servers: Vec<(UserId, String, Option<String>)> = users_on_rocketchat_servers::table
+            .select((
+                users_on_rocketchat_servers::matrix_user_id,
+                users_on_rocketchat_servers::rocketchat_server_id,
+                users_on_rocketchat_servers::rocketchat_auth_token,
+            ))
+            .filter(users_on_rocketchat_servers::rocketchat_auth_token.is_not_null())
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        for (matrix_user_id, rocketchat_server_id, rocketchat_auth_token) in users_on_rocketchat_servers {
+            if let Some(rocketchat_auth_token) = update_token(&rocketchat_auth_token.unwrap_or_default())? {
+                diesel::update(users_on_rocketchat_servers::table.find((&matrix_user_id, rocketchat_server_id)))
+                    .set(users_on_rocketchat_servers::rocketchat_auth_token.eq(Some(rocketchat_auth_token)))
+                    .execute(connection)
+                    .chain_err(|| ErrorKind::DBUpdateError)?;
+                updated_tokens += 1;
+            }
+        }
+
+        Ok(updated_tokens)
+    })
+}
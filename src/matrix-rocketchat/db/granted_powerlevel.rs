@@ -0,0 +1,75 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use ruma_identifiers::{RoomId, UserId};
+
+use errors::*;
+use super::schema::granted_powerlevels;
+
+/// A power level that the bridge granted a Matrix user in a bridged room for a role in the
+/// Rocket.Chat channel. It's used to revoke the power level when the user loses the role.
+#[derive(Debug, Identifiable, Queryable)]
+#[primary_key(matrix_room_id, matrix_user_id)]
+#[table_name = "granted_powerlevels"]
+pub struct GrantedPowerlevel {
+    /// The bridged Matrix room
+    pub matrix_room_id: RoomId,
+    /// The Matrix user that got the power level
+    pub matrix_user_id: UserId,
+    /// The power level the bridge granted
+    pub powerlevel: i64,
+    /// The power level the user had before
+    pub previous_powerlevel: i64,
+    /// created timestamp
+    pub created_at: String,
+    /// updated timestamp
+    pub updated_at: String,
+}
+
+/// A new `GrantedPowerlevel`, not yet saved.
+#[derive(Insertable)]
+#[table_name = "granted_powerlevels"]
+pub struct NewGrantedPowerlevel {
+    /// The bridged Matrix room
+    pub matrix_room_id: RoomId,
+    /// The Matrix user that got the power level
+    pub matrix_user_id: UserId,
+    /// The power level the bridge granted
+    pub powerlevel: i64,
+    /// The power level the user had before
+    pub previous_powerlevel: i64,
+}
+
+impl GrantedPowerlevel {
+    /// Insert a new `GrantedPowerlevel`, an entry for the same user in the room is replaced.
+    pub fn insert(connection: &SqliteConnection, new_granted_powerlevel: &NewGrantedPowerlevel) -> Result<()> {
+        let key = (&new_granted_powerlevel.matrix_room_id, &new_granted_powerlevel.matrix_user_id);
+        diesel::delete(granted_powerlevels::table.find(key)).execute(connection).chain_err(|| ErrorKind::DBDeleteError)?;
+        diesel::insert(new_granted_powerlevel).into(granted_powerlevels::table).execute(connection).chain_err(
+            || ErrorKind::DBInsertError,
+        )?;
+        Ok(())
+    }
+
+    /// Find the power level that the bridge granted a user in a room. Returns `None`, if the
+    /// bridge didn't grant the user a power level.
+    pub fn find(
+        connection: &SqliteConnection,
+        matrix_room_id: &RoomId,
+        matrix_user_id: &UserId,
+    ) -> Result<Option<GrantedPowerlevel>> {
+        let granted_powerlevels = granted_powerlevels::table
+            .find((matrix_room_id, matrix_user_id))
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(granted_powerlevels.into_iter().next())
+    }
+
+    /// Delete the entry after the power level was revoked.
+    pub fn delete(&self, connection: &SqliteConnection) -> Result<()> {
+        diesel::delete(granted_powerlevels::table.find((&self.matrix_room_id, &self.matrix_user_id)))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use ruma_identifiers::{RoomAliasId, RoomId};
+
+use errors::*;
+use super::schema::linked_rooms;
+
+/// An existing Matrix room that was linked to a Rocket.Chat channel. The bridge takes over the
+/// canonical alias of the room, the previous one is restored when the room is unbridged.
+#[derive(Debug, Identifiable, Queryable)]
+#[primary_key(matrix_room_id)]
+#[table_name = "linked_rooms"]
+pub struct LinkedRoom {
+    /// The linked Matrix room
+    pub matrix_room_id: RoomId,
+    /// The canonical alias the room had before it was linked
+    pub previous_canonical_alias: Option<RoomAliasId>,
+    /// created timestamp
+    pub created_at: String,
+    /// updated timestamp
+    pub updated_at: String,
+}
+
+/// A new `LinkedRoom`, not yet saved.
+#[derive(Insertable)]
+#[table_name = "linked_rooms"]
+pub struct NewLinkedRoom {
+    /// The linked Matrix room
+    pub matrix_room_id: RoomId,
+    /// The canonical alias the room had before it was linked
+    pub previous_canonical_alias: Option<RoomAliasId>,
+}
+
+impl LinkedRoom {
+    /// Insert a new `LinkedRoom`, an entry that is left from an earlier link of the room is
+    /// replaced.
+    pub fn insert(connection: &SqliteConnection, new_linked_room: &NewLinkedRoom) -> Result<()> {
+        diesel::delete(linked_rooms::table.find(&new_linked_room.matrix_room_id)).execute(connection).chain_err(
+            || ErrorKind::DBDeleteError,
+        )?;
+        diesel::insert(new_linked_room).into(linked_rooms::table).execute(connection).chain_err(
+            || ErrorKind::DBInsertError,
+        )?;
+        Ok(())
+    }
+
+    /// Find a `LinkedRoom` by its Matrix room ID. Returns `None`, if the room was not linked.
+    pub fn find_by_matrix_room_id(connection: &SqliteConnection, matrix_room_id: &RoomId) -> Result<Option<LinkedRoom>> {
+        let linked_rooms = linked_rooms::table.find(matrix_room_id).load(connection).chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(linked_rooms.into_iter().next())
+    }
+
+    /// Delete the entry after the room was unbridged.
+    pub fn delete(&self, connection: &SqliteConnection) -> Result<()> {
+        diesel::delete(linked_rooms::table.find(&self.matrix_room_id)).execute(connection).chain_err(
+            || ErrorKind::DBDeleteError,
+        )?;
+        Ok(())
+    }
+}
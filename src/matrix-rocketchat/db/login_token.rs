@@ -0,0 +1,144 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use rand::{OsRng, Rng};
+use ruma_identifiers::{RoomId, UserId};
+
+use errors::*;
+use super::schema::login_tokens;
+
+/// Number of minutes after which a login link expires
+pub const LOGIN_TOKEN_TIMEOUT_MINUTES: i64 = 10;
+/// Number of failed logins after which a login link can't be used anymore
+pub const MAX_FAILED_LOGIN_ATTEMPTS: i32 = 3;
+/// Number of characters of the generated tokens
+const TOKEN_LENGTH: usize = 32;
+
+/// A one-time token that allows a user to login via the login page of the application service.
+#[derive(Debug, Identifiable, Queryable)]
+#[primary_key(token)]
+#[table_name = "login_tokens"]
+pub struct LoginToken {
+    /// The token that is part of the login link
+    pub token: String,
+    /// The Matrix user the login link was created for
+    pub matrix_user_id: UserId,
+    /// The Rocket.Chat server on which the user will be logged in
+    pub rocketchat_server_id: String,
+    /// The admin room in which the login link was requested
+    pub admin_room_id: RoomId,
+    /// Token that is embedded in the login form to protect it against CSRF
+    pub csrf_token: String,
+    /// Time when the token expires in seconds since UNIX_EPOCH
+    pub expires_at: i64,
+    /// created timestamp
+    pub created_at: String,
+    /// updated timestamp
+    pub updated_at: String,
+    /// Number of failed logins with this token
+    pub failed_attempts: i32,
+}
+
+/// A new `LoginToken`, not yet saved.
+#[derive(Insertable)]
+#[table_name = "login_tokens"]
+pub struct NewLoginToken {
+    /// The token that is part of the login link
+    pub token: String,
+    /// The Matrix user the login link was created for
+    pub matrix_user_id: UserId,
+    /// The Rocket.Chat server on which the user will be logged in
+    pub rocketchat_server_id: String,
+    /// The admin room in which the login link was requested
+    pub admin_room_id: RoomId,
+    /// Token that is embedded in the login form to protect it against CSRF
+    pub csrf_token: String,
+    /// Time when the token expires in seconds since UNIX_EPOCH
+    pub expires_at: i64,
+}
+
+impl LoginToken {
+    /// Create a new `LoginToken` with a random token and CSRF token. Existing tokens of the user
+    /// for the same Rocket.Chat server are replaced, so only the latest login link can be used.
+    pub fn create(
+        connection: &SqliteConnection,
+        matrix_user_id: UserId,
+        rocketchat_server_id: String,
+        admin_room_id: RoomId,
+    ) -> Result<LoginToken> {
+        let now = now()?;
+        diesel::delete(login_tokens::table.filter(login_tokens::expires_at.le(now)))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+        diesel::delete(
+            login_tokens::table
+                .filter(login_tokens::matrix_user_id.eq(matrix_user_id.clone()))
+                .filter(login_tokens::rocketchat_server_id.eq(rocketchat_server_id.clone())),
+        ).execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+
+        let new_login_token = NewLoginToken {
+            token: generate_token()?,
+            matrix_user_id: matrix_user_id,
+            rocketchat_server_id: rocketchat_server_id,
+            admin_room_id: admin_room_id,
+            csrf_token: generate_token()?,
+            expires_at: now + LOGIN_TOKEN_TIMEOUT_MINUTES * 60,
+        };
+        diesel::insert(&new_login_token).into(login_tokens::table).execute(connection).chain_err(
+            || ErrorKind::DBInsertError,
+        )?;
+
+        login_tokens::table
+            .find(&new_login_token.token)
+            .first(connection)
+            .chain_err(|| ErrorKind::DBSelectError)
+            .map_err(Error::from)
+    }
+
+    /// Find a `LoginToken` that did not expire yet. Returns `None`, if the token is not found or
+    /// if it is expired.
+    pub fn find_valid(connection: &SqliteConnection, token: &str) -> Result<Option<LoginToken>> {
+        let login_tokens = login_tokens::table
+            .filter(login_tokens::token.eq(token))
+            .filter(login_tokens::expires_at.gt(now()?))
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(login_tokens.into_iter().next())
+    }
+
+    /// Record a failed login with this token. The token is deleted when the maximum number of
+    /// failed logins is reached, so the password can't be guessed with the same login link.
+    /// Returns `true` if the token can still be used.
+    pub fn record_failed_attempt(&self, connection: &SqliteConnection) -> Result<bool> {
+        let failed_attempts = self.failed_attempts + 1;
+        if failed_attempts >= MAX_FAILED_LOGIN_ATTEMPTS {
+            self.delete(connection)?;
+            return Ok(false);
+        }
+
+        diesel::update(login_tokens::table.find(&self.token))
+            .set(login_tokens::failed_attempts.eq(failed_attempts))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBUpdateError)?;
+        Ok(true)
+    }
+
+    /// Delete the token, the login link can't be used anymore.
+    pub fn delete(&self, connection: &SqliteConnection) -> Result<()> {
+        diesel::delete(login_tokens::table.find(&self.token)).execute(connection).chain_err(|| ErrorKind::DBDeleteError)?;
+        Ok(())
+    }
+}
+
+fn generate_token() -> Result<String> {
+    let mut rng = OsRng::new().chain_err(|| ErrorKind::InternalServerError)?;
+    Ok(rng.gen_ascii_chars().take(TOKEN_LENGTH).collect())
+}
+
+pub(super) fn now() -> Result<i64> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).chain_err(|| ErrorKind::InternalServerError)?.as_secs() as i64;
+    Ok(now)
+}
@@ -2,6 +2,16 @@
 
 /// Database connection pool
 pub mod connection_pool;
+/// Encryption of the tokens that are stored in the database
+pub mod encryption;
+/// `GrantedPowerlevel` entry
+pub mod granted_powerlevel;
+/// `LinkedRoom` entry
+pub mod linked_room;
+/// `LoginToken` entry
+pub mod login_token;
+/// `QueuedMessage` entry
+pub mod queued_message;
 /// `RocketchatServer` entry
 pub mod rocketchat_server;
 /// `Room` entry
@@ -14,6 +24,10 @@ pub mod user;
 pub mod user_on_rocketchat_server;
 
 pub use self::connection_pool::ConnectionPool;
+pub use self::granted_powerlevel::{GrantedPowerlevel, NewGrantedPowerlevel};
+pub use self::linked_room::{LinkedRoom, NewLinkedRoom};
+pub use self::login_token::{LOGIN_TOKEN_TIMEOUT_MINUTES, LoginToken, MAX_FAILED_LOGIN_ATTEMPTS, NewLoginToken};
+pub use self::queued_message::{MAX_QUEUED_MESSAGES_PER_USER, NewQueuedMessage, QUEUED_MESSAGE_TIMEOUT_HOURS, QueuedMessage};
 pub use self::rocketchat_server::{NewRocketchatServer, RocketchatServer};
 pub use self::room::Room;
 pub use self::user::{NewUser, User};
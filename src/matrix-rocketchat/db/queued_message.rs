@@ -0,0 +1,125 @@
+use diesel;
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use ruma_identifiers::UserId;
+
+use errors::*;
+use super::login_token::now;
+use super::schema::queued_messages;
+
+/// Number of hours after which a queued message is discarded
+pub const QUEUED_MESSAGE_TIMEOUT_HOURS: i64 = 24;
+/// Number of messages that are queued per user and Rocket.Chat server
+pub const MAX_QUEUED_MESSAGES_PER_USER: usize = 50;
+
+/// A message from Matrix that could not be forwarded to Rocket.Chat, because the credentials of
+/// the user are not valid anymore. It is delivered when the user logs in again.
+#[derive(Debug, Identifiable, Queryable)]
+#[table_name = "queued_messages"]
+pub struct QueuedMessage {
+    /// The unique identifier of the queued message
+    pub id: i32,
+    /// The Matrix user that sent the message
+    pub matrix_user_id: UserId,
+    /// The Rocket.Chat server the message is sent to
+    pub rocketchat_server_id: String,
+    /// The Rocket.Chat channel the message is sent to
+    pub rocketchat_channel_id: String,
+    /// The content of the message
+    pub message: String,
+    /// created timestamp
+    pub created_at: String,
+    /// updated timestamp
+    pub updated_at: String,
+    /// Time when the message is discarded in seconds since UNIX_EPOCH
+    pub expires_at: i64,
+}
+
+/// A new `QueuedMessage`, not yet saved.
+#[derive(Insertable)]
+#[table_name = "queued_messages"]
+pub struct NewQueuedMessage {
+    /// The Matrix user that sent the message
+    pub matrix_user_id: UserId,
+    /// The Rocket.Chat server the message is sent to
+    pub rocketchat_server_id: String,
+    /// The Rocket.Chat channel the message is sent to
+    pub rocketchat_channel_id: String,
+    /// The content of the message
+    pub message: String,
+    /// Time when the message is discarded in seconds since UNIX_EPOCH
+    pub expires_at: i64,
+}
+
+impl QueuedMessage {
+    /// Queue a message until the user logs in again, expired messages are removed. Returns `false`
+    /// if the queue of the user is full and the message was discarded.
+    pub fn queue(
+        connection: &SqliteConnection,
+        matrix_user_id: UserId,
+        rocketchat_server_id: String,
+        rocketchat_channel_id: String,
+        message: String,
+    ) -> Result<bool> {
+        let now = now()?;
+        diesel::delete(queued_messages::table.filter(queued_messages::expires_at.le(now)))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+
+        let queued_message_ids: Vec<i32> = queued_messages::table
+            .filter(queued_messages::matrix_user_id.eq(matrix_user_id.clone()))
+            .filter(queued_messages::rocketchat_server_id.eq(rocketchat_server_id.clone()))
+            .select(queued_messages::id)
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        if queued_message_ids.len() >= MAX_QUEUED_MESSAGES_PER_USER {
+            return Ok(false);
+        }
+
+        let new_queued_message = NewQueuedMessage {
+            matrix_user_id: matrix_user_id,
+            rocketchat_server_id: rocketchat_server_id,
+            rocketchat_channel_id: rocketchat_channel_id,
+            message: message,
+            expires_at: now + QUEUED_MESSAGE_TIMEOUT_HOURS * 60 * 60,
+        };
+        diesel::insert(&new_queued_message).into(queued_messages::table).execute(connection).chain_err(
+            || ErrorKind::DBInsertError,
+        )?;
+        Ok(true)
+    }
+
+    /// Get the queued messages of a user for a Rocket.Chat server that didn't expire in the order
+    /// they were sent.
+    pub fn find_by_user(
+        connection: &SqliteConnection,
+        matrix_user_id: &UserId,
+        rocketchat_server_id: String,
+    ) -> Result<Vec<QueuedMessage>> {
+        let queued_messages = queued_messages::table
+            .filter(queued_messages::matrix_user_id.eq(matrix_user_id))
+            .filter(queued_messages::rocketchat_server_id.eq(rocketchat_server_id))
+            .filter(queued_messages::expires_at.gt(now()?))
+            .order(queued_messages::id.asc())
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(queued_messages)
+    }
+
+    /// Delete the message after it was delivered.
+    pub fn delete(&self, connection: &SqliteConnection) -> Result<()> {
+        diesel::delete(queued_messages::table.find(self.id)).execute(connection).chain_err(|| ErrorKind::DBDeleteError)?;
+        Ok(())
+    }
+
+    /// Delete all queued messages of a user for a Rocket.Chat server.
+    pub fn delete_by_user(connection: &SqliteConnection, matrix_user_id: &UserId, rocketchat_server_id: String) -> Result<()> {
+        diesel::delete(
+            queued_messages::table
+                .filter(queued_messages::matrix_user_id.eq(matrix_user_id))
+                .filter(queued_messages::rocketchat_server_id.eq(rocketchat_server_id)),
+        ).execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+        Ok(())
+    }
+}
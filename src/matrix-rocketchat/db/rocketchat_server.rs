@@ -2,10 +2,12 @@ use diesel;
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
 use iron::typemap::Key;
+use ruma_identifiers::UserId;
 
 use errors::*;
 use super::UserOnRocketchatServer;
-use super::schema::{rocketchat_servers, users_on_rocketchat_servers};
+use super::encryption::{self, EncryptionKey};
+use super::schema::{queued_messages, rocketchat_servers, users_on_rocketchat_servers};
 
 /// A Rocket.Chat server.
 #[derive(Associations, Debug, Identifiable, Queryable)]
@@ -15,12 +17,19 @@ pub struct RocketchatServer {
     pub id: String,
     /// The URL to connect to the Rocket.Chat server
     pub rocketchat_url: String,
-    /// The token to identify requests from the Rocket.Chat server
+    /// The token to identify requests from the Rocket.Chat server, it's encrypted if a database
+    /// encryption key is configured
     pub rocketchat_token: Option<String>,
     /// created timestamp
     pub created_at: String,
     /// updated timestamp
     pub updated_at: String,
+    /// The Matrix user that connected the Rocket.Chat server, `None` for servers that were
+    /// connected before it was stored
+    pub created_by: Option<UserId>,
+    /// Keyed hash of the token to find the server by its token, `None` if no database encryption
+    /// key is configured
+    pub rocketchat_token_hash: Option<String>,
 }
 
 /// A new `Room`, not yet saved.
@@ -33,17 +42,32 @@ pub struct NewRocketchatServer {
     pub rocketchat_url: String,
     /// The token to identify reuqests from the Rocket.Chat server
     pub rocketchat_token: Option<String>,
+    /// The Matrix user that connected the Rocket.Chat server
+    pub created_by: Option<UserId>,
 }
 
 impl RocketchatServer {
-    /// Insert a `RocketchatServer`.
-    pub fn insert(connection: &SqliteConnection, new_rocketchat_server: &NewRocketchatServer) -> Result<RocketchatServer> {
-        diesel::insert(new_rocketchat_server).into(rocketchat_servers::table).execute(connection).chain_err(
-            || ErrorKind::DBInsertError,
-        )?;
+    /// Insert a `RocketchatServer`, the token is encrypted before it's stored if a key is given.
+    pub fn insert(
+        connection: &SqliteConnection,
+        encryption_key: Option<&EncryptionKey>,
+        new_rocketchat_server: &NewRocketchatServer,
+    ) -> Result<RocketchatServer> {
+        let server_without_token = NewRocketchatServer {
+            id: new_rocketchat_server.id.clone(),
+            rocketchat_url: new_rocketchat_server.rocketchat_url.clone(),
+            rocketchat_token: None,
+            created_by: new_rocketchat_server.created_by.clone(),
+        };
+        connection.transaction(|| {
+            diesel::insert(&server_without_token).into(rocketchat_servers::table).execute(connection).chain_err(
+                || ErrorKind::DBInsertError,
+            )?;
 
-        let rocketchat_server = RocketchatServer::find(connection, new_rocketchat_server.rocketchat_url.clone())?;
-        Ok(rocketchat_server)
+            let mut rocketchat_server = RocketchatServer::find(connection, new_rocketchat_server.rocketchat_url.clone())?;
+            rocketchat_server.set_rocketchat_token(connection, encryption_key, new_rocketchat_server.rocketchat_token.clone())?;
+            Ok(rocketchat_server)
+        })
     }
 
     /// Find a `RocketchatServer` by its URL, return an error if the `RocketchatServer` is not
@@ -74,12 +98,28 @@ impl RocketchatServer {
         Ok(rocketchat_servers.into_iter().next())
     }
 
-    /// Find a `RocketchatServer` bit its token.
-    pub fn find_by_token(connection: &SqliteConnection, token: String) -> Result<Option<RocketchatServer>> {
-        let rocketchat_servers = rocketchat_servers::table
-            .filter(rocketchat_servers::rocketchat_token.eq(Some(token)))
-            .load(connection)
-            .chain_err(|| ErrorKind::DBSelectError)?;
+    /// Find a `RocketchatServer` by its token. The encrypted tokens contain a random nonce, so
+    /// they are found by their keyed hash if a key is given.
+    pub fn find_by_token(
+        connection: &SqliteConnection,
+        encryption_key: Option<&EncryptionKey>,
+        token: String,
+    ) -> Result<Option<RocketchatServer>> {
+        let rocketchat_servers: Vec<RocketchatServer> = match encryption_key {
+            Some(encryption_key) => {
+                let rocketchat_token_hash = encryption_key.hash(&token)?;
+                rocketchat_servers::table
+                    .filter(rocketchat_servers::rocketchat_token_hash.eq(rocketchat_token_hash))
+                    .load(connection)
+                    .chain_err(|| ErrorKind::DBSelectError)?
+            }
+            None => {
+                rocketchat_servers::table
+                    .filter(rocketchat_servers::rocketchat_token.eq(token))
+                    .load(connection)
+                    .chain_err(|| ErrorKind::DBSelectError)?
+            }
+        };
         Ok(rocketchat_servers.into_iter().next())
     }
 
@@ -92,6 +132,86 @@ impl RocketchatServer {
         Ok(rocketchat_servers)
     }
 
+    /// Get all the Matrix users (not the virtual users) that are connected to this Rocket.Chat
+    /// server.
+    pub fn matrix_users_on_rocketchat_server(&self, connection: &SqliteConnection) -> Result<Vec<UserOnRocketchatServer>> {
+        let users_on_rocketchat_server: Vec<UserOnRocketchatServer> = users_on_rocketchat_servers::table
+            .filter(users_on_rocketchat_servers::rocketchat_server_id.eq(self.id.clone()))
+            .filter(users_on_rocketchat_servers::is_virtual_user.eq(false))
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(users_on_rocketchat_server)
+    }
+
+    /// The Matrix user that is allowed to change or disconnect the Rocket.Chat server. Servers that
+    /// were connected before the creator was stored belong to the first Matrix user on the server,
+    /// because that user's admin room connected it.
+    pub fn owner(&self, connection: &SqliteConnection) -> Result<Option<UserId>> {
+        if let Some(ref created_by) = self.created_by {
+            return Ok(Some(created_by.clone()));
+        }
+
+        let matrix_user_ids: Vec<UserId> = users_on_rocketchat_servers::table
+            .select(users_on_rocketchat_servers::matrix_user_id)
+            .filter(users_on_rocketchat_servers::rocketchat_server_id.eq(self.id.clone()))
+            .filter(users_on_rocketchat_servers::is_virtual_user.eq(false))
+            .order(users_on_rocketchat_servers::created_at.asc())
+            .limit(1)
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(matrix_user_ids.into_iter().next())
+    }
+
+    /// Update the URL of the Rocket.Chat server.
+    pub fn set_rocketchat_url(&mut self, connection: &SqliteConnection, rocketchat_url: String) -> Result<()> {
+        self.rocketchat_url = rocketchat_url.clone();
+        diesel::update(rocketchat_servers::table.find(self.id.clone()))
+            .set(rocketchat_servers::rocketchat_url.eq(rocketchat_url))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBUpdateError)?;
+        Ok(())
+    }
+
+    /// Update the token that is used to identify requests from the Rocket.Chat server. The token
+    /// is encrypted before it's stored if a key is given.
+    pub fn set_rocketchat_token(
+        &mut self,
+        connection: &SqliteConnection,
+        encryption_key: Option<&EncryptionKey>,
+        rocketchat_token: Option<String>,
+    ) -> Result<()> {
+        self.rocketchat_token = encryption::encrypt(encryption_key, &rocketchat_token)?;
+        self.rocketchat_token_hash = encryption::hash(encryption_key, &rocketchat_token)?;
+        diesel::update(rocketchat_servers::table.find(self.id.clone()))
+            .set((
+                rocketchat_servers::rocketchat_token.eq(self.rocketchat_token.clone()),
+                rocketchat_servers::rocketchat_token_hash.eq(self.rocketchat_token_hash.clone()),
+            ))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBUpdateError)?;
+        Ok(())
+    }
+
+    /// Delete the Rocket.Chat server, the Matrix users that are connected to it and their queued
+    /// messages. The virtual users are kept, because they stay registered on the homeserver and
+    /// cannot be registered again. When a server is connected again with the same ID, the existing
+    /// virtual users are reused instead.
+    pub fn delete(&self, connection: &SqliteConnection) -> Result<()> {
+        diesel::delete(queued_messages::table.filter(queued_messages::rocketchat_server_id.eq(self.id.clone())))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+        diesel::delete(
+            users_on_rocketchat_servers::table
+                .filter(users_on_rocketchat_servers::rocketchat_server_id.eq(self.id.clone()))
+                .filter(users_on_rocketchat_servers::is_virtual_user.eq(false)),
+        ).execute(connection)
+            .chain_err(|| ErrorKind::DBDeleteError)?;
+        diesel::delete(rocketchat_servers::table.find(self.id.clone())).execute(connection).chain_err(
+            || ErrorKind::DBDeleteError,
+        )?;
+        Ok(())
+    }
+
     /// Get all users that are connected to this Rocket.Chat server.
     pub fn logged_in_users_on_rocketchat_server(&self, connection: &SqliteConnection) -> Result<Vec<UserOnRocketchatServer>> {
         let users_on_rocketchat_server: Vec<UserOnRocketchatServer> = users_on_rocketchat_servers::table
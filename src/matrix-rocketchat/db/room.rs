@@ -76,7 +76,8 @@ impl Room {
         Ok(user_ids)
     }
 
-    /// Gets the matrix room ID for a Rocket.Chat channel name and a Rocket.Chat server.
+    /// Gets the matrix room ID for a Rocket.Chat channel or private group name and a Rocket.Chat
+    /// server.
     pub fn matrix_id_from_rocketchat_channel_name(
         config: &Config,
         matrix_api: &MatrixApi,
@@ -84,8 +85,9 @@ impl Room {
         rocketchat_server_id: &str,
         rocketchat_channel_name: String,
     ) -> Result<Option<RoomId>> {
-        let channel_id = rocketchat_api
-            .channels_list()?
+        let mut channels = rocketchat_api.channels_list()?;
+        channels.extend(rocketchat_api.groups_list()?);
+        let channel_id = channels
             .iter()
             .filter_map(|channel| {
                 if channel.name == Some(rocketchat_channel_name.clone()) {
@@ -132,8 +134,17 @@ impl Room {
         let matrix_bot_user_id = config.matrix_bot_user_id()?;
         let matrix_user_ids = Room::user_ids(matrix_api, matrix_room_id.clone(), None)?;
         let bot_user_in_room = matrix_user_ids.iter().any(|id| id == &matrix_bot_user_id);
-        let room_creator = matrix_api.get_room_creator(matrix_room_id)?.to_string();
-        Ok(!room_creator.starts_with(&virtual_user_prefix) && bot_user_in_room)
+        let room_creator = matrix_api.get_room_creator(matrix_room_id.clone())?.to_string();
+        if room_creator.starts_with(&virtual_user_prefix) || !bot_user_in_room {
+            return Ok(false);
+        }
+
+        // rooms that were linked to a Rocket.Chat channel are created by Matrix users, but they
+        // are bridged rooms
+        match matrix_api.get_room_canonical_alias(matrix_room_id)? {
+            Some(matrix_room_alias_id) => Ok(Room::rocketchat_ids_from_room_alias_id(config, &matrix_room_alias_id)?.is_none()),
+            None => Ok(true),
+        }
     }
 
     /// Gets the Rocket.Chat channel id for a room that is bridged to Matrix.
@@ -158,23 +169,6 @@ impl Room {
         }
     }
 
-    /// Find the Matrix user in a direct message room
-    pub fn direct_message_room_matrix_user(
-        config: &Config,
-        matrix_api: &MatrixApi,
-        matrix_room_id: RoomId,
-        sender_id: Option<UserId>,
-    ) -> Result<Option<UserId>> {
-        let user_ids = Room::user_ids(matrix_api, matrix_room_id.clone(), sender_id)?;
-        if user_ids.len() > 2 {
-            bail_error!(ErrorKind::GettingMatrixUserForDirectMessageRoomError);
-        }
-
-        let prefix = format!("@{}", config.sender_localpart);
-        let matrix_user_id = user_ids.into_iter().find(|id| !id.to_string().starts_with(&prefix));
-        Ok(matrix_user_id)
-    }
-
     /// Determine if the bot user has access to a room.
     pub fn is_accessible_by_bot(matrix_api: &MatrixApi, matrix_room_id: RoomId) -> Result<bool> {
         matrix_api.is_room_accessible_by_bot(matrix_room_id)
@@ -197,4 +191,29 @@ impl Room {
             RoomAliasId::try_from(&room_alias_id).chain_err(|| ErrorKind::InvalidRoomAliasId(room_alias_id.clone()))?;
         Ok(room_alias)
     }
+
+    /// Get the Rocket.Chat server ID and the Rocket.Chat channel ID from a room alias that was
+    /// built by `build_room_alias_id`. Returns `None` if the alias is not managed by the application service.
+    pub fn rocketchat_ids_from_room_alias_id(
+        config: &Config,
+        matrix_room_alias_id: &RoomAliasId,
+    ) -> Result<Option<(String, String)>> {
+        let alias_parts: Vec<&str> = matrix_room_alias_id.alias().split('#').collect();
+        if alias_parts.len() != 3 || alias_parts[0] != config.sender_localpart {
+            return Ok(None);
+        }
+
+        let (rocketchat_server_id, rocketchat_channel_id) = (alias_parts[1], alias_parts[2]);
+        if rocketchat_server_id.is_empty() || rocketchat_channel_id.is_empty() {
+            return Ok(None);
+        }
+
+        // the alias has to be on the homeserver the application service belongs to
+        let expected_room_alias_id = Room::build_room_alias_id(config, rocketchat_server_id, rocketchat_channel_id)?;
+        if &expected_room_alias_id != matrix_room_alias_id {
+            return Ok(None);
+        }
+
+        Ok(Some((rocketchat_server_id.to_string(), rocketchat_channel_id.to_string())))
+    }
 }
@@ -17,6 +17,8 @@ table! {
         rocketchat_token -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        created_by -> Nullable<Text>,
+        rocketchat_token_hash -> Nullable<Text>,
     }
 }
 
@@ -30,5 +32,54 @@ table! {
         rocketchat_username -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        credentials_valid -> Bool,
+        admin_room_id -> Nullable<Text>,
+    }
+}
+
+table! {
+    login_tokens (token) {
+        token -> Text,
+        matrix_user_id -> Text,
+        rocketchat_server_id -> Text,
+        admin_room_id -> Text,
+        csrf_token -> Text,
+        expires_at -> BigInt,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        failed_attempts -> Integer,
+    }
+}
+
+table! {
+    queued_messages (id) {
+        id -> Integer,
+        matrix_user_id -> Text,
+        rocketchat_server_id -> Text,
+        rocketchat_channel_id -> Text,
+        message -> Text,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+        expires_at -> BigInt,
+    }
+}
+
+table! {
+    linked_rooms (matrix_room_id) {
+        matrix_room_id -> Text,
+        previous_canonical_alias -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
+    }
+}
+
+table! {
+    granted_powerlevels (matrix_room_id, matrix_user_id) {
+        matrix_room_id -> Text,
+        matrix_user_id -> Text,
+        powerlevel -> BigInt,
+        previous_powerlevel -> BigInt,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
@@ -1,9 +1,10 @@
 use diesel;
 use diesel::prelude::*;
 use diesel::sqlite::SqliteConnection;
-use ruma_identifiers::UserId;
+use ruma_identifiers::{RoomId, UserId};
 
 use errors::*;
+use super::encryption::{self, EncryptionKey};
 use super::schema::users_on_rocketchat_servers;
 use super::User;
 
@@ -20,7 +21,8 @@ pub struct UserOnRocketchatServer {
     pub rocketchat_server_id: String,
     /// The users unique id on the Rocket.Chat server.
     pub rocketchat_user_id: Option<String>,
-    /// The token to identify reuqests from the Rocket.Chat server
+    /// The token to identify reuqests from the Rocket.Chat server, it's encrypted if a database
+    /// encryption key is configured
     pub rocketchat_auth_token: Option<String>,
     /// The username on the Rocket.Chat server
     pub rocketchat_username: Option<String>,
@@ -28,6 +30,11 @@ pub struct UserOnRocketchatServer {
     pub created_at: String,
     /// updated timestamp
     pub updated_at: String,
+    /// Flag to indicate if the Rocket.Chat server still accepts the stored credentials, it's
+    /// false when the auth token expired or was revoked
+    pub credentials_valid: bool,
+    /// The admin room in which the user logged in
+    pub admin_room_id: Option<RoomId>,
 }
 
 /// A new `Room`, not yet saved.
@@ -49,9 +56,11 @@ pub struct NewUserOnRocketchatServer {
 }
 
 impl UserOnRocketchatServer {
-    /// Insert or update a `UserOnRocketchatServer`.
+    /// Insert or update a `UserOnRocketchatServer`, the auth token is encrypted before it's stored
+    /// if a key is given.
     pub fn upsert(
         connection: &SqliteConnection,
+        encryption_key: Option<&EncryptionKey>,
         user_on_rocketchat_server: &NewUserOnRocketchatServer,
     ) -> Result<UserOnRocketchatServer> {
         let users_on_rocketchat_server: Vec<UserOnRocketchatServer> = users_on_rocketchat_servers::table
@@ -63,12 +72,22 @@ impl UserOnRocketchatServer {
             Some(mut existing_user_on_rocketchat_server) => {
                 existing_user_on_rocketchat_server.set_credentials(
                     connection,
+                    encryption_key,
                     user_on_rocketchat_server.rocketchat_user_id.clone(),
                     user_on_rocketchat_server.rocketchat_auth_token.clone(),
                 )?;
             }
             None => {
-                diesel::insert(user_on_rocketchat_server)
+                let rocketchat_auth_token = &user_on_rocketchat_server.rocketchat_auth_token;
+                let encrypted_user_on_rocketchat_server = NewUserOnRocketchatServer {
+                    is_virtual_user: user_on_rocketchat_server.is_virtual_user,
+                    matrix_user_id: user_on_rocketchat_server.matrix_user_id.clone(),
+                    rocketchat_server_id: user_on_rocketchat_server.rocketchat_server_id.clone(),
+                    rocketchat_user_id: user_on_rocketchat_server.rocketchat_user_id.clone(),
+                    rocketchat_auth_token: encryption::encrypt(encryption_key, rocketchat_auth_token)?,
+                    rocketchat_username: user_on_rocketchat_server.rocketchat_username.clone(),
+                };
+                diesel::insert(&encrypted_user_on_rocketchat_server)
                     .into(users_on_rocketchat_servers::table)
                     .execute(connection)
                     .chain_err(|| ErrorKind::DBInsertError)?;
@@ -105,7 +124,7 @@ impl UserOnRocketchatServer {
         rocketchat_user_id: String,
         is_virtual_user: bool,
     ) -> Result<Option<UserOnRocketchatServer>> {
-        let users_on_rocketchat_servers = users_on_rocketchat_servers::table
+        let users_on_rocketchat_servers: Vec<UserOnRocketchatServer> = users_on_rocketchat_servers::table
             .filter(
                 users_on_rocketchat_servers::rocketchat_server_id
                     .eq(rocketchat_server_id)
@@ -117,25 +136,68 @@ impl UserOnRocketchatServer {
         Ok(users_on_rocketchat_servers.into_iter().next())
     }
 
-    /// Update the users credentials.
+    /// Find the virtual user of a Rocket.Chat user by the Rocket.Chat username. Returns `None`,
+    /// if the user doesn't have a virtual user yet.
+    pub fn find_virtual_user_by_rocketchat_username(
+        connection: &SqliteConnection,
+        rocketchat_server_id: String,
+        rocketchat_username: String,
+    ) -> Result<Option<UserOnRocketchatServer>> {
+        let users_on_rocketchat_servers: Vec<UserOnRocketchatServer> = users_on_rocketchat_servers::table
+            .filter(
+                users_on_rocketchat_servers::rocketchat_server_id
+                    .eq(rocketchat_server_id)
+                    .and(users_on_rocketchat_servers::rocketchat_username.eq(rocketchat_username))
+                    .and(users_on_rocketchat_servers::is_virtual_user.eq(true)),
+            )
+            .load(connection)
+            .chain_err(|| ErrorKind::DBSelectError)?;
+        Ok(users_on_rocketchat_servers.into_iter().next())
+    }
+
+    /// Update the users credentials, new credentials are always considered valid. The auth token
+    /// is encrypted before it's stored if a key is given.
     pub fn set_credentials(
         &mut self,
         connection: &SqliteConnection,
+        encryption_key: Option<&EncryptionKey>,
         rocketchat_user_id: Option<String>,
         rocketchat_auth_token: Option<String>,
     ) -> Result<()> {
         self.rocketchat_user_id = rocketchat_user_id.clone();
-        self.rocketchat_auth_token = rocketchat_auth_token.clone();
+        self.rocketchat_auth_token = encryption::encrypt(encryption_key, &rocketchat_auth_token)?;
+        self.credentials_valid = true;
         diesel::update(users_on_rocketchat_servers::table.find((&self.matrix_user_id, self.rocketchat_server_id.clone())))
             .set((
                 users_on_rocketchat_servers::rocketchat_user_id.eq(rocketchat_user_id),
-                users_on_rocketchat_servers::rocketchat_auth_token.eq(rocketchat_auth_token),
+                users_on_rocketchat_servers::rocketchat_auth_token.eq(self.rocketchat_auth_token.clone()),
+                users_on_rocketchat_servers::credentials_valid.eq(true),
             ))
             .execute(connection)
             .chain_err(|| ErrorKind::DBUpdateError)?;
         Ok(())
     }
 
+    /// Mark the stored credentials as invalid, they are not used until the user logs in again.
+    pub fn invalidate_credentials(&mut self, connection: &SqliteConnection) -> Result<()> {
+        self.credentials_valid = false;
+        diesel::update(users_on_rocketchat_servers::table.find((&self.matrix_user_id, self.rocketchat_server_id.clone())))
+            .set(users_on_rocketchat_servers::credentials_valid.eq(false))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBUpdateError)?;
+        Ok(())
+    }
+
+    /// Update the admin room in which the user logged in.
+    pub fn set_admin_room_id(&mut self, connection: &SqliteConnection, admin_room_id: Option<RoomId>) -> Result<()> {
+        self.admin_room_id = admin_room_id.clone();
+        diesel::update(users_on_rocketchat_servers::table.find((&self.matrix_user_id, self.rocketchat_server_id.clone())))
+            .set(users_on_rocketchat_servers::admin_room_id.eq(admin_room_id))
+            .execute(connection)
+            .chain_err(|| ErrorKind::DBUpdateError)?;
+        Ok(())
+    }
+
     /// Update the users Rocket.Chat username.
     pub fn set_rocketchat_username(
         &mut self,
@@ -160,4 +222,16 @@ impl UserOnRocketchatServer {
     pub fn is_logged_in(&self) -> bool {
         self.rocketchat_auth_token.is_some()
     }
+
+    /// Returns true if the user is logged in, but the Rocket.Chat server doesn't accept the
+    /// credentials anymore.
+    pub fn has_invalid_credentials(&self) -> bool {
+        self.is_logged_in() && !self.credentials_valid
+    }
+
+    /// The decrypted auth token, an empty string if the user is not logged in.
+    pub fn decrypted_auth_token(&self, encryption_key: Option<&EncryptionKey>) -> Result<String> {
+        let rocketchat_auth_token = encryption::decrypt(encryption_key, &self.rocketchat_auth_token)?;
+        Ok(rocketchat_auth_token.unwrap_or_default())
+    }
 }
@@ -14,6 +14,7 @@ use ruma_identifiers::{RoomId, UserId};
 use serde_json;
 
 use i18n::*;
+use log::redact_secrets;
 
 macro_rules! simple_error {
     ($e:expr) => {
@@ -91,9 +92,9 @@ error_chain!{
     }
 
     errors {
-        InvalidAccessToken(token: String) {
+        InvalidAccessToken {
             description("The provided access token is not valid")
-            display("Could not process request, the access token `{}` is not valid", token)
+            display("Could not process request, the access token is not valid")
         }
 
         MissingAccessToken {
@@ -101,9 +102,9 @@ error_chain!{
             display("Could not process request, no access token was provided")
         }
 
-        InvalidRocketchatToken(token: String) {
+        InvalidRocketchatToken {
             description("The provided access token is not valid")
-            display("Could not process request, the access token `{}` did not match any bridged Rocket.Chat server", token)
+            display("Could not process request, the access token did not match any bridged Rocket.Chat server")
         }
 
         MissingRocketchatToken {
@@ -151,6 +152,21 @@ error_chain!{
             display("Authentication failed: {}", error_msg)
         }
 
+        TwoFactorAuthenticationCodeRequired {
+            description("Two-factor authentication code required")
+            display("The Rocket.Chat server requires a two-factor authentication code to login")
+        }
+
+        InvalidTwoFactorAuthenticationCode {
+            description("Invalid two-factor authentication code")
+            display("The two-factor authentication code was not accepted by the Rocket.Chat server")
+        }
+
+        NoPendingLogin(matrix_user_id: String) {
+            description("No login is waiting for a two-factor authentication code")
+            display("There is no login of user {} that waits for a two-factor authentication code", matrix_user_id)
+        }
+
         ApiCallFailed(url: String) {
             description("Call to REST API failed")
             display("Could not call REST API endpoint {}", url)
@@ -235,9 +251,76 @@ error_chain!{
             display("Attempt to connect with the Rocket.Chat server ID {}, which is already in use.", rocketchat_server_id)
         }
 
-        RocketchatTokenAlreadyInUse(token: String) {
+        RocketchatTokenAlreadyInUse {
             description("The token is already used by another server")
-            display("The token {} is already in use by another server", token)
+            display("The token is already in use by another server")
+        }
+
+        UserNotLoggedIn(rocketchat_url: String) {
+            description("The user is not logged in on the Rocket.Chat server")
+            display("The user is not logged in on the Rocket.Chat server {}", rocketchat_url)
+        }
+
+        OnlyRocketchatServerCreatorCanChangeServer(matrix_user_id: UserId, rocketchat_url: String) {
+            description("Only the user who connected the Rocket.Chat server can change it")
+            display("User {} cannot change the Rocket.Chat server {}, because it was connected by another user",
+                    matrix_user_id, rocketchat_url)
+        }
+
+        RocketchatServerStillInUse(rocketchat_url: String, users: String) {
+            description("The Rocket.Chat server is still used by other Matrix users")
+            display("The Rocket.Chat server {} cannot be changed, because it is still used by {}", rocketchat_url, users)
+        }
+
+        RocketchatServerHasBridgedRooms(rocketchat_url: String, channel_names: String) {
+            description("The Rocket.Chat server still has bridged rooms")
+            display("The Rocket.Chat server {} cannot be disconnected, because the rooms {} are still bridged",
+                    rocketchat_url, channel_names)
+        }
+
+        RocketchatUrlAlreadyInUse(rocketchat_url: String) {
+            description("The Rocket.Chat URL is already used by another server")
+            display("The Rocket.Chat URL {} is already used by another server", rocketchat_url)
+        }
+
+        InvalidRocketchatServerUpdate(command: String) {
+            description("The update command for the Rocket.Chat server is not valid")
+            display("Invalid update command for the Rocket.Chat server: `{}`", command)
+        }
+
+        UnknownCommand(command: String) {
+            description("The command is not known")
+            display("Unknown command `{}`", command)
+        }
+
+        InvalidCommandUsage(command: String) {
+            description("The command was used with the wrong arguments")
+            display("The command `{}` was used with the wrong arguments", command)
+        }
+
+        InvalidCommandQuoting {
+            description("The command contains a quote that is not closed")
+            display("Could not parse the command, because it contains a quote that is not closed")
+        }
+
+        UnknownRoomAlias(room_alias: String) {
+            description("The room alias does not belong to a Rocket.Chat channel that can be bridged")
+            display("The room alias {} does not belong to a Rocket.Chat channel that can be bridged", room_alias)
+        }
+
+        UnknownVirtualUser(user_id: String) {
+            description("The user ID does not belong to a Rocket.Chat user")
+            display("The user ID {} does not belong to a user on a connected Rocket.Chat server", user_id)
+        }
+
+        RocketchatUserNotFound(username: String) {
+            description("No user with the given username found on the Rocket.Chat server")
+            display("The user {} does not exist on the Rocket.Chat server", username)
+        }
+
+        DirectMessageRoomAlreadyExists(username: String) {
+            description("There already is a room for the direct message channel")
+            display("A room for the direct message channel with {} already exists", username)
         }
 
         RocketchatChannelNotFound(channel_name: String) {
@@ -245,6 +328,11 @@ error_chain!{
             display("The channel {} does not exist on the Rocket.Chat server", channel_name)
         }
 
+        ChannelListPageNotFound(page: String) {
+            description("The requested page of the channel list does not exist")
+            display("The page {} of the channel list does not exist", page)
+        }
+
         RocketchatChannelAlreadyBridged(channel_name: String) {
             description("The channel with the given name is already bridged")
             display("The channel {} is already bridged", channel_name)
@@ -255,14 +343,34 @@ error_chain!{
             display("Bridging the channel {} failed, because the user hasn't joined it on Rocket.Chat", channel_name)
         }
 
+        MatrixRoomNotFound(room: String) {
+            description("No Matrix room with the given ID or alias found")
+            display("The Matrix room {} does not exist", room)
+        }
+
+        BotAlreadyInRoom(room: String) {
+            description("The bot user is already a member of the room that should be linked")
+            display("The room {} cannot be linked, because the bot user is already a member of it", room)
+        }
+
+        InsufficientPowerLevelToLinkRoom(matrix_user_id: UserId, matrix_room_id: RoomId, powerlevel: i64) {
+            description("The user does not have the power level that is needed to link the room")
+            display("The user {} needs the power level {} to link the room {}", matrix_user_id, powerlevel, matrix_room_id)
+        }
+
+        RocketchatMembershipChangeFailed(channel_name: String, error_msg: String) {
+            description("The membership change could not be forwarded to the Rocket.Chat channel")
+            display("Could not change the members of the channel {} on Rocket.Chat: {}", channel_name, error_msg)
+        }
+
         UnbridgeOfNotBridgedRoom(display_name: String) {
             description("Room with the given display name could not be found")
             display("No room with display_name {} found", display_name)
         }
 
-        RoomNotEmpty(display_name: String, users: String) {
-            description("Non virtual users are in the room")
-            display("The room {} has matrix users ({}) in it, cannot unbridge", display_name, users)
+        UnbridgeNotPermitted(channel_name: String, matrix_user_id: UserId) {
+            description("The user is not allowed to unbridge the room")
+            display("The user {} is not allowed to unbridge the channel {}", matrix_user_id, channel_name)
         }
 
         ReadConfigError {
@@ -275,6 +383,16 @@ error_chain!{
             display("Could not start application service")
         }
 
+        MissingSslConfiguration(field: String) {
+            description("A setting that is needed to use SSL is missing")
+            display("`use_ssl` is enabled, but `{}` is not set in the config", field)
+        }
+
+        InvalidSslCertificate(msg: String) {
+            description("The SSL certificate or key is not valid")
+            display("Could not load the SSL certificate: {}", msg)
+        }
+
         DatabaseSetupError {
             description("Error when setting up the database")
             display("Could not setup database")
@@ -285,6 +403,32 @@ error_chain!{
             display("Could not run migrations")
         }
 
+        DatabaseEncryptionKeyMissing {
+            description("The database encryption key is missing")
+            display("No database encryption key found, set `database_encryption_key` in the config or the \
+                    environment variable MATRIX_ROCKETCHAT_DATABASE_ENCRYPTION_KEY")
+        }
+
+        InvalidDatabaseEncryptionKey(msg: String) {
+            description("The database encryption key is not valid")
+            display("The database encryption key is not valid: {}", msg)
+        }
+
+        InvalidPowerLevel(role: String, power_level: i64) {
+            description("The configured power level is not valid")
+            display("The power level {} for the role {} is not valid, it has to be lower than 100", power_level, role)
+        }
+
+        EncryptionError {
+            description("Error when encrypting a value")
+            display("Could not encrypt value")
+        }
+
+        DecryptionError {
+            description("Error when decrypting a value")
+            display("Could not decrypt value, the database encryption key is wrong or the value was modified")
+        }
+
         DBConnectionError {
             description("Error when establishing a connection to the database")
             display("Could not establish database connection")
@@ -295,9 +439,9 @@ error_chain!{
             display("Could not get logger from iron")
         }
 
-        GettingMatrixUserForDirectMessageRoomError {
-            description("Error when getting matrix user for a direct message room")
-            display("Could not get matrix user for direct message room")
+        TooManyUsersInDirectMessageRoom(room_id: RoomId) {
+            description("Too many users in direct message room")
+            display("Room {} has more then two members and cannot be used as direct message room", room_id)
         }
 
         TooManyUsersInAdminRoom(room_id: RoomId) {
@@ -361,13 +505,17 @@ error_chain!{
 impl Error {
     pub fn status_code(&self) -> Status {
         match *self.error_chain {
-            ErrorKind::InvalidAccessToken(_) |
-            ErrorKind::InvalidRocketchatToken(_) => Status::Forbidden,
+            ErrorKind::InvalidAccessToken |
+            ErrorKind::InvalidRocketchatToken => Status::Forbidden,
             ErrorKind::MissingAccessToken |
             ErrorKind::MissingRocketchatToken |
-            ErrorKind::AuthenticationFailed(_) => Status::Unauthorized,
+            ErrorKind::AuthenticationFailed(_) |
+            ErrorKind::TwoFactorAuthenticationCodeRequired |
+            ErrorKind::InvalidTwoFactorAuthenticationCode => Status::Unauthorized,
             ErrorKind::InvalidJSON(_) => Status::UnprocessableEntity,
-            ErrorKind::AdminRoomForRocketchatServerNotFound(_) => Status::NotFound,
+            ErrorKind::AdminRoomForRocketchatServerNotFound(_) |
+            ErrorKind::UnknownRoomAlias(_) |
+            ErrorKind::UnknownVirtualUser(_) => Status::NotFound,
             _ => Status::InternalServerError,
         }
     }
@@ -417,10 +565,10 @@ impl<'a> Modifier<Response> for &'a Error {
     fn modify(self, response: &mut Response) {
         let error_message = match self.user_message {
             Some(ref user_message) => user_message.l(DEFAULT_LANGUAGE),
-            None => format!("{}", self),
+            None => redact_secrets(&format!("{}", self)),
         };
 
-        let causes = self.error_chain.iter().skip(1).map(|e| format!("{}", e)).collect();
+        let causes = self.error_chain.iter().skip(1).map(|e| redact_secrets(&format!("{}", e))).collect();
         let resp = ErrorResponse {
             error: error_message,
             causes: causes,
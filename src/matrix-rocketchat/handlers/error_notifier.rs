@@ -7,6 +7,7 @@ use api::MatrixApi;
 use db::User;
 use config::Config;
 use errors::*;
+use log;
 
 /// Notifies the user about errors
 pub struct ErrorNotifier<'a> {
@@ -24,14 +25,15 @@ impl<'a> ErrorNotifier<'a> {
     /// Send the error message to the user if the error contains a user message. Otherwise just
     /// inform the user that an internal error happened.
     pub fn send_message_to_user(&self, err: &Error, room_id: RoomId, user_id: &UserId) -> Result<()> {
-        let mut msg = format!("{}", err);
-        for err in err.error_chain.iter().skip(1) {
-            msg = msg + " caused by: " + &format!("{}", err);
-        }
+        let matrix_bot_id = self.config.matrix_bot_user_id()?;
+        self.send_message_to_user_as(err, room_id, user_id, matrix_bot_id)
+    }
 
-        debug!(self.logger, "{}", msg);
+    /// Send the error message to the user like `send_message_to_user`, but as another sender than
+    /// the bot user. This is used in rooms that the bot user is not a member of.
+    pub fn send_message_to_user_as(&self, err: &Error, room_id: RoomId, user_id: &UserId, sender_id: UserId) -> Result<()> {
+        log::log_debug(self.logger, err);
 
-        let matrix_bot_id = self.config.matrix_bot_user_id()?;
         let language = match User::find_by_matrix_user_id(self.connection, user_id)? {
             Some(user) => user.language,
             None => DEFAULT_LANGUAGE.to_string(),
@@ -41,10 +43,10 @@ impl<'a> ErrorNotifier<'a> {
             Some(ref user_message) => user_message,
             None => {
                 let user_msg = t!(["defaults", "internal_error"]).l(&language);
-                return self.matrix_api.send_text_message_event(room_id, matrix_bot_id, user_msg);
+                return self.matrix_api.send_text_message_event(room_id, sender_id, user_msg);
             }
         };
 
-        self.matrix_api.send_text_message_event(room_id, matrix_bot_id, user_message.l(&language))
+        self.matrix_api.send_text_message_event(room_id, sender_id, user_message.l(&language))
     }
 }
@@ -0,0 +1,149 @@
+use errors::*;
+use i18n::*;
+
+/// Names of all the commands that can be used in the admin room
+pub const COMMANDS: &'static [&'static str] =
+    &["bridge", "connect", "disconnect", "dm", "help", "link", "list", "login", "logout", "unbridge", "update"];
+
+/// Names of the commands that can contain credentials (passwords or tokens)
+const COMMANDS_WITH_CREDENTIALS: &'static [&'static str] = &["connect", "login", "update"];
+
+/// A command that was sent in the admin room
+#[derive(Debug, PartialEq)]
+pub struct Command {
+    /// Name of the command
+    pub name: String,
+    /// Arguments of the command with quotes and escape characters already removed
+    pub arguments: Vec<String>,
+}
+
+impl Command {
+    /// Parse a message from the admin room into a command. The arguments are separated by
+    /// whitespace, arguments that contain whitespace can be wrapped in `"` or `'`. Within `"` a
+    /// `\` escapes a following `"` or `\`, all the other backslashes are kept as they are.
+    /// Returns `None` if the message doesn't contain a command.
+    pub fn parse(message: &str) -> Result<Option<Command>> {
+        let mut tokens = Command::tokenize(message)?.into_iter();
+        let command = tokens.next().map(|name| {
+            Command {
+                name: name,
+                arguments: tokens.collect(),
+            }
+        });
+
+        Ok(command)
+    }
+
+    /// Checks if a command with the given name exists.
+    pub fn exists(name: &str) -> bool {
+        COMMANDS.contains(&name)
+    }
+
+    /// Checks if a message that could not be parsed might contain credentials. This is the case
+    /// when a command that accepts credentials is used with more than one argument.
+    pub fn might_contain_credentials(message: &str) -> bool {
+        let mut words = message.split_whitespace();
+        match words.next() {
+            Some(name) => COMMANDS_WITH_CREDENTIALS.contains(&name) && words.count() > 1,
+            None => false,
+        }
+    }
+
+    /// Checks if the command contains credentials (a password or a token), which must not stay
+    /// in the room history.
+    pub fn contains_credentials(&self) -> bool {
+        match self.name.as_ref() {
+            "connect" | "login" => self.arguments.len() > 1,
+            "update" => self.optional_argument(0) == Some("token") && self.arguments.len() > 1,
+            _ => false,
+        }
+    }
+
+    /// Get the argument at the given position, a usage error is returned if the argument is missing.
+    pub fn argument(&self, index: usize) -> Result<&str> {
+        match self.arguments.get(index) {
+            Some(argument) => Ok(argument),
+            None => Err(self.usage_error()),
+        }
+    }
+
+    /// Get the argument at the given position, if there is one.
+    pub fn optional_argument(&self, index: usize) -> Option<&str> {
+        self.arguments.get(index).map(|argument| argument.as_ref())
+    }
+
+    /// Get all the arguments starting at the given position joined by a space, a usage error is
+    /// returned if there are no arguments.
+    pub fn remaining_arguments(&self, index: usize) -> Result<String> {
+        if self.arguments.len() <= index {
+            return Err(self.usage_error());
+        }
+
+        Ok(self.arguments[index..].join(" "))
+    }
+
+    /// Returns a usage error if the command has more arguments than it accepts.
+    pub fn max_arguments(&self, max: usize) -> Result<()> {
+        if self.arguments.len() > max {
+            return Err(self.usage_error());
+        }
+
+        Ok(())
+    }
+
+    /// Error that tells the user how to get the help for the command.
+    pub fn usage_error(&self) -> Error {
+        user_error!(
+            ErrorKind::InvalidCommandUsage(self.name.clone()),
+            t!(["errors", "invalid_command_usage"]).with_vars(vec![("command", self.name.clone())])
+        )
+    }
+
+    fn tokenize(message: &str) -> Result<Vec<String>> {
+        let mut tokens = Vec::new();
+        let mut token = String::new();
+        let mut in_token = false;
+        let mut in_quotes = false;
+        let mut chars = message.chars().peekable();
+
+        // only double quotes group arguments, single quotes are kept because they are common in
+        // passwords and names
+        while let Some(c) = chars.next() {
+            if in_quotes {
+                if c == '"' {
+                    in_quotes = false;
+                } else if c == '\\' {
+                    // only quotes and backslashes can be escaped, other backslashes are kept
+                    match chars.peek() {
+                        Some(&'"') | Some(&'\\') => token.push(chars.next().unwrap_or(c)),
+                        _ => token.push(c),
+                    }
+                } else {
+                    token.push(c);
+                }
+            } else if c == '"' {
+                in_quotes = true;
+                in_token = true;
+            } else if c.is_whitespace() {
+                if in_token {
+                    tokens.push(token.clone());
+                    token.clear();
+                    in_token = false;
+                }
+            } else {
+                token.push(c);
+                in_token = true;
+            }
+        }
+
+        if in_quotes {
+            bail_error!(ErrorKind::InvalidCommandQuoting, t!(["errors", "invalid_command_quoting"]));
+        }
+
+        if in_token {
+            tokens.push(token);
+        }
+
+        Ok(tokens)
+    }
+}
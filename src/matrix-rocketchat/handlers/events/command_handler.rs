@@ -1,19 +1,29 @@
+use std::convert::TryFrom;
+
 use diesel::Connection;
 use diesel::sqlite::SqliteConnection;
+use ruma_events::EventType;
 use ruma_events::room::message::MessageEvent;
 use ruma_events::room::message::MessageEventContent;
-use ruma_identifiers::{RoomId, UserId};
+use ruma_identifiers::{RoomAliasId, RoomId, UserId};
 use slog::Logger;
 
 use MAX_ROCKETCHAT_SERVER_ID_LENGTH;
 use api::{MatrixApi, RocketchatApi};
 use api::rocketchat::Channel;
 use config::Config;
-use db::{NewRocketchatServer, NewUserOnRocketchatServer, RocketchatServer, Room, User, UserOnRocketchatServer};
+use db::{LOGIN_TOKEN_TIMEOUT_MINUTES, LoginToken, NewRocketchatServer, NewUserOnRocketchatServer, QueuedMessage,
+         RocketchatServer, Room, User, UserOnRocketchatServer};
 use errors::*;
-use handlers::rocketchat::{Credentials, Login};
-use handlers::events::RoomHandler;
+use handlers::rocketchat::{AccessTokenCredentials, Credentials, DirectMessageHandler, Login, VirtualUserHandler};
+use handlers::events::{Command, PENDING_ROOM_LINK_TIMEOUT_MINUTES, RoomHandler, RoomLink};
 use i18n::*;
+use log;
+
+/// Argument of the bridge command to bridge all the channels the user is a member of
+const BRIDGE_ALL_FLAG: &'static str = "--all";
+/// Number of channels that are shown on one page when listing the channels in the admin room
+const CHANNELS_PER_PAGE: usize = 25;
 
 /// Handles command messages from the admin room
 pub struct CommandHandler<'a> {
@@ -49,42 +59,102 @@ impl<'a> CommandHandler<'a> {
             }
         };
 
-        if message.starts_with("connect") {
-            debug!(self.logger, "Received connect command: {}", message);
+        let command = match Command::parse(&message) {
+            Ok(Some(command)) => command,
+            Ok(None) => {
+                debug!(self.logger, "Skipping event, the message doesn't contain a command");
+                return Ok(());
+            }
+            Err(err) => {
+                if Command::might_contain_credentials(&message) {
+                    self.redact_credentials(event)?;
+                }
+                return Err(err);
+            }
+        };
 
-            self.connect(event, &message)?;
-        } else if message == "help" {
-            debug!(self.logger, "Received help command");
+        debug!(self.logger, "Received {} command", command.name);
 
-            self.help(event)?;
-        } else if message.starts_with("login") {
-            debug!(self.logger, "Received login command");
+        // the credentials are removed before the command is executed, so that they are also
+        // removed from the room history when the command fails
+        if command.contains_credentials() {
+            self.redact_credentials(event)?;
+        }
 
-            let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
-            self.login(event, &rocketchat_server, &message)?;
-        } else if message == "list" {
-            debug!(self.logger, "Received list command");
+        match command.name.as_ref() {
+            "connect" => self.connect(event, &command)?,
+            "help" => self.help(event, &command)?,
+            "login" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.login(event, &rocketchat_server, &command)?;
+            }
+            "logout" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.logout(event, &rocketchat_server, &command)?;
+            }
+            "disconnect" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.disconnect(event, &rocketchat_server, &command)?;
+            }
+            "update" => {
+                let mut rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.update_rocketchat_server(event, &mut rocketchat_server, &command)?;
+            }
+            "list" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.list_channels(event, &rocketchat_server, &command)?;
+            }
+            "bridge" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.bridge(event, &rocketchat_server, &command)?;
+            }
+            "link" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.link(event, &rocketchat_server, &command)?;
+            }
+            "unbridge" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.unbridge(event, &rocketchat_server, &command)?;
+            }
+            "dm" => {
+                let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
+                self.direct_message(event, &rocketchat_server, &command)?;
+            }
+            _ => {
+                bail_error!(
+                    ErrorKind::UnknownCommand(command.name.clone()),
+                    t!(["errors", "unknown_command"]).with_vars(vec![("command", command.name.clone())])
+                );
+            }
+        }
 
-            let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
-            self.list_channels(event, &rocketchat_server)?;
-        } else if message.starts_with("bridge") {
-            debug!(self.logger, "Received bridge command");
+        Ok(())
+    }
 
-            let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
-            self.bridge(event, &rocketchat_server, &message)?;
-        } else if message.starts_with("unbridge") {
-            debug!(self.logger, "Received unbridge command");
+    fn redact_credentials(&self, event: &MessageEvent) -> Result<()> {
+        let user = User::find(self.connection, &event.user_id)?;
+        let reason = t!(["defaults", "credentials_redaction_reason"]).l(&user.language);
 
-            let rocketchat_server = self.get_rocketchat_server(matrix_room_id)?;
-            self.unbridge(event, &rocketchat_server, &message)?;
-        } else {
-            debug!(self.logger, "Skipping event, don't know how to handle command `{}`", message);
-        }
+        let message = match self.matrix_api.redact_event(event.room_id.clone(), event.event_id.clone(), Some(reason)) {
+            Ok(()) => t!(["admin_room", "credentials_redacted"]),
+            Err(err) => {
+                // the bot user needs the permission to redact events of other users in the admin room
+                info!(self.logger, "Could not redact message {} that contains credentials", event.event_id);
+                log::log_info(self.logger, &err);
+                t!(["admin_room", "credentials_not_redacted"])
+            }
+        };
 
-        Ok(())
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))
     }
 
-    fn connect(&self, event: &MessageEvent, message: &str) -> Result<()> {
+    fn connect(&self, event: &MessageEvent, command: &Command) -> Result<()> {
+        let rocketchat_url = command.argument(0)?;
+        let token = command.optional_argument(1);
+        let rocketchat_id = command.optional_argument(2).unwrap_or_default();
+        command.max_arguments(3)?;
+
         self.connection
             .transaction(|| {
                 if Room::is_connected(self.connection, self.matrix_api, event.room_id.clone())? {
@@ -94,16 +164,12 @@ impl<'a> CommandHandler<'a> {
                     );
                 }
 
-                let mut command = message.split_whitespace().collect::<Vec<&str>>().into_iter();
-                let rocketchat_url = command.by_ref().nth(1).unwrap_or_default();
-
                 debug!(self.logger, "Connecting to Rocket.Chat server {}", rocketchat_url);
 
-                let rocketchat_server = match command.by_ref().next() {
+                let rocketchat_server = match token {
                     Some(token) => {
-                        let rocketchat_id = command.by_ref().next().unwrap_or_default().to_string();
                         self.connect_new_rocktechat_server(
-                            rocketchat_id,
+                            rocketchat_id.to_string(),
                             rocketchat_url.to_string(),
                             token.to_string(),
                             &event.user_id,
@@ -121,7 +187,8 @@ impl<'a> CommandHandler<'a> {
                     rocketchat_username: None,
                 };
 
-                UserOnRocketchatServer::upsert(self.connection, &new_user_on_rocketchat_server)?;
+                let encryption_key = self.config.database_encryption_key()?;
+                UserOnRocketchatServer::upsert(self.connection, encryption_key.as_ref(), &new_user_on_rocketchat_server)?;
                 self.matrix_api.set_room_topic(event.room_id.clone(), rocketchat_url.to_string())?;
 
                 let user = User::find(self.connection, &event.user_id)?;
@@ -187,10 +254,11 @@ impl<'a> CommandHandler<'a> {
             }
         }
 
-        if RocketchatServer::find_by_token(self.connection, token.clone())?.is_some() {
+        let encryption_key = self.config.database_encryption_key()?;
+        if RocketchatServer::find_by_token(self.connection, encryption_key.as_ref(), token.clone())?.is_some() {
             bail_error!(
-                ErrorKind::RocketchatTokenAlreadyInUse(token.clone()),
-                t!(["errors", "token_already_in_use"]).with_vars(vec![("token", token)])
+                ErrorKind::RocketchatTokenAlreadyInUse,
+                t!(["errors", "token_already_in_use"])
             );
         }
 
@@ -201,93 +269,481 @@ impl<'a> CommandHandler<'a> {
             id: rocketchat_server_id,
             rocketchat_url: rocketchat_url,
             rocketchat_token: Some(token),
+            created_by: Some(matrix_user_id.clone()),
         };
 
-        RocketchatServer::insert(self.connection, &new_rocketchat_server)
+        RocketchatServer::insert(self.connection, encryption_key.as_ref(), &new_rocketchat_server)
+    }
+
+    fn disconnect(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        command.max_arguments(0)?;
+        self.validate_rocketchat_server_change(&event.user_id, rocketchat_server)?;
+        self.validate_no_other_users_on_rocketchat_server(&event.user_id, rocketchat_server)?;
+
+        let user_on_rocketchat_server =
+            UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
+        if !user_on_rocketchat_server.is_logged_in() {
+            bail_error!(
+                ErrorKind::UserNotLoggedIn(rocketchat_server.rocketchat_url.clone()),
+                t!(["errors", "not_logged_in"]).with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())])
+            );
+        }
+
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+
+        let bridged_channel_names = self.bridged_channel_names(rocketchat_server, rocketchat_api.as_ref())?;
+        if !bridged_channel_names.is_empty() {
+            let channel_names = bridged_channel_names.join(", ");
+            bail_error!(
+                ErrorKind::RocketchatServerHasBridgedRooms(rocketchat_server.rocketchat_url.clone(), channel_names.clone()),
+                t!(["errors", "rocketchat_server_has_bridged_rooms"]).with_vars(vec![
+                    ("rocketchat_url", rocketchat_server.rocketchat_url.clone()),
+                    ("channel_names", channel_names),
+                ])
+            );
+        }
+
+        let user = user_on_rocketchat_server.user(self.connection)?;
+        self.connection.transaction(|| {
+            rocketchat_server.delete(self.connection)?;
+            // the admin room topic contains the URL of the Rocket.Chat server the room is connected to
+            self.matrix_api.set_room_topic(event.room_id.clone(), "".to_string())
+        })?;
+
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let message = t!(["admin_room", "rocketchat_server_disconnected"])
+            .with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())]);
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
+
+        Ok(info!(self.logger, "Successfully disconnected Rocket.Chat server {}", &rocketchat_server.rocketchat_url))
     }
 
-    fn help(&self, event: &MessageEvent) -> Result<()> {
+    fn update_rocketchat_server(
+        &self,
+        event: &MessageEvent,
+        rocketchat_server: &mut RocketchatServer,
+        command: &Command,
+    ) -> Result<()> {
+        let field = command.argument(0)?;
+        let value = command.argument(1)?;
+        command.max_arguments(2)?;
+
+        self.validate_rocketchat_server_change(&event.user_id, rocketchat_server)?;
+
+        let message = match field {
+            "url" => {
+                self.update_rocketchat_server_url(event, rocketchat_server, value.to_string())?;
+                t!(["admin_room", "rocketchat_server_url_updated"])
+            }
+            "token" => {
+                self.update_rocketchat_server_token(rocketchat_server, value.to_string())?;
+                t!(["admin_room", "rocketchat_server_token_updated"])
+            }
+            _ => {
+                bail_error!(
+                    ErrorKind::InvalidRocketchatServerUpdate(field.to_string()),
+                    t!(["errors", "invalid_rocketchat_server_update"])
+                );
+            }
+        };
+
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
         let user = User::find(self.connection, &event.user_id)?;
+        let message = message.with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())]);
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
 
-        let help_message = CommandHandler::build_help_message(
-            self.connection,
-            self.matrix_api,
-            self.config.as_url.clone(),
-            event.room_id.clone(),
-            &user,
-        )?;
+        Ok(info!(self.logger, "Successfully updated Rocket.Chat server {}", &rocketchat_server.id))
+    }
+
+    fn update_rocketchat_server_url(
+        &self,
+        event: &MessageEvent,
+        rocketchat_server: &mut RocketchatServer,
+        rocketchat_url: String,
+    ) -> Result<()> {
+        // the admin rooms of other users would still point to the old URL
+        self.validate_no_other_users_on_rocketchat_server(&event.user_id, rocketchat_server)?;
+
+        if RocketchatServer::find_by_url(self.connection, rocketchat_url.clone())?.is_some() {
+            bail_error!(
+                ErrorKind::RocketchatUrlAlreadyInUse(rocketchat_url.clone()),
+                t!(["errors", "rocketchat_url_already_in_use"]).with_vars(vec![("rocketchat_url", rocketchat_url)])
+            );
+        }
+
+        // see if we can reach the server and if the server has a supported API version
+        RocketchatApi::new(rocketchat_url.clone(), self.logger.clone())?;
+
+        self.connection.transaction(|| {
+            rocketchat_server.set_rocketchat_url(self.connection, rocketchat_url.clone())?;
+            self.matrix_api.set_room_topic(event.room_id.clone(), rocketchat_url.clone())
+        })
+    }
+
+    fn update_rocketchat_server_token(&self, rocketchat_server: &mut RocketchatServer, token: String) -> Result<()> {
+        let encryption_key = self.config.database_encryption_key()?;
+        if RocketchatServer::find_by_token(self.connection, encryption_key.as_ref(), token.clone())?.is_some() {
+            bail_error!(
+                ErrorKind::RocketchatTokenAlreadyInUse,
+                t!(["errors", "token_already_in_use"])
+            );
+        }
+
+        rocketchat_server.set_rocketchat_token(self.connection, encryption_key.as_ref(), Some(token))
+    }
+
+    fn validate_rocketchat_server_change(&self, matrix_user_id: &UserId, rocketchat_server: &RocketchatServer) -> Result<()> {
+        if rocketchat_server.owner(self.connection)?.as_ref() != Some(matrix_user_id) {
+            bail_error!(
+                ErrorKind::OnlyRocketchatServerCreatorCanChangeServer(
+                    matrix_user_id.clone(),
+                    rocketchat_server.rocketchat_url.clone(),
+                ),
+                t!(["errors", "only_rocketchat_server_creator_can_change_server"])
+                    .with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())])
+            );
+        }
+
+        Ok(())
+    }
+
+    fn validate_no_other_users_on_rocketchat_server(
+        &self,
+        matrix_user_id: &UserId,
+        rocketchat_server: &RocketchatServer,
+    ) -> Result<()> {
+        let other_users = rocketchat_server
+            .matrix_users_on_rocketchat_server(self.connection)?
+            .into_iter()
+            .filter(|user| &user.matrix_user_id != matrix_user_id)
+            .map(|user| user.matrix_user_id.to_string())
+            .collect::<Vec<String>>();
+        if !other_users.is_empty() {
+            let users = other_users.join(", ");
+            bail_error!(
+                ErrorKind::RocketchatServerStillInUse(rocketchat_server.rocketchat_url.clone(), users.clone()),
+                t!(["errors", "rocketchat_server_still_in_use"]).with_vars(vec![
+                    ("rocketchat_url", rocketchat_server.rocketchat_url.clone()),
+                    ("users", users),
+                ])
+            );
+        }
+
+        Ok(())
+    }
+
+    fn bridged_channel_names(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_api: &RocketchatApi,
+    ) -> Result<Vec<String>> {
+        let mut channels = rocketchat_api.channels_list()?;
+        channels.extend(rocketchat_api.groups_list()?);
+
+        let mut channel_names = Vec::new();
+        for channel in channels {
+            if Room::matrix_id_from_rocketchat_channel_id(self.config, self.matrix_api, &rocketchat_server.id, &channel.id)?
+                .is_some()
+            {
+                channel_names.push(channel.name.unwrap_or(channel.id));
+            }
+        }
+
+        for direct_message in rocketchat_api.direct_messages_list()? {
+            let channel_id = format!("{}#dm", direct_message.id);
+            if Room::matrix_id_from_rocketchat_channel_id(self.config, self.matrix_api, &rocketchat_server.id, &channel_id)?
+                .is_some()
+            {
+                channel_names.push(direct_message.id);
+            }
+        }
+
+        Ok(channel_names)
+    }
+
+    fn help(&self, event: &MessageEvent, command: &Command) -> Result<()> {
+        command.max_arguments(1)?;
+        let user = User::find(self.connection, &event.user_id)?;
+
+        let help_message = match command.optional_argument(0) {
+            Some(command_name) => {
+                if !Command::exists(command_name) {
+                    bail_error!(
+                        ErrorKind::UnknownCommand(command_name.to_string()),
+                        t!(["errors", "unknown_command"]).with_vars(vec![("command", command_name.to_string())])
+                    );
+                }
+
+                t!(["commands", command_name]).l(&user.language)
+            }
+            None => {
+                CommandHandler::build_help_message(
+                    self.connection,
+                    self.matrix_api,
+                    self.config.as_url.clone(),
+                    event.room_id.clone(),
+                    &user,
+                )?
+            }
+        };
         let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
         self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, help_message)?;
 
         Ok(info!(self.logger, "Successfully executed help command for user {}", user.matrix_user_id))
     }
 
-    fn login(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, message: &str) -> Result<()> {
-        let mut command = message.split_whitespace().collect::<Vec<&str>>().into_iter();
-        let username = command.by_ref().nth(1).unwrap_or_default();
-        let password = command.by_ref().fold("".to_string(), |acc, x| acc + x);
+    fn login(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        let login = Login {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+
+        if command.arguments.is_empty() {
+            return self.send_login_link(event, rocketchat_server);
+        }
+
+        // users that have no password on the Rocket.Chat server (e.g. because they use SSO) can
+        // login with a personal access token
+        if command.optional_argument(0) == Some("--token") {
+            let rocketchat_user_id = command.argument(1)?;
+            let access_token = command.argument(2)?;
+            command.max_arguments(3)?;
+
+            let credentials = AccessTokenCredentials {
+                matrix_user_id: event.user_id.clone(),
+                rocketchat_user_id: rocketchat_user_id.to_string(),
+                access_token: access_token.to_string(),
+                rocketchat_url: rocketchat_server.rocketchat_url.clone(),
+            };
+            return login.call_with_access_token(&credentials, rocketchat_server, Some(event.room_id.clone()));
+        }
+
+        // the second step of the login when two-factor authentication is enabled for the user
+        if command.optional_argument(0) == Some("--code") {
+            let totp_code = command.argument(1)?;
+            command.max_arguments(2)?;
+            return login.call_with_totp_code(&event.user_id, totp_code, rocketchat_server, Some(event.room_id.clone()));
+        }
+
+        let username = command.argument(0)?;
+        let password = command.argument(1)?;
+        command.max_arguments(2)?;
 
         let credentials = Credentials {
             matrix_user_id: event.user_id.clone(),
             rocketchat_username: username.to_string(),
             password: password.to_string(),
             rocketchat_url: rocketchat_server.rocketchat_url.clone(),
-        };
-        let login = Login {
-            config: self.config,
-            connection: self.connection,
-            logger: self.logger,
-            matrix_api: self.matrix_api,
+            totp_code: None,
         };
         login.call(&credentials, rocketchat_server, Some(event.room_id.clone()))
     }
 
-    fn list_channels(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer) -> Result<()> {
+    fn send_login_link(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer) -> Result<()> {
+        let login_token =
+            LoginToken::create(self.connection, event.user_id.clone(), rocketchat_server.id.clone(), event.room_id.clone())?;
+        let login_url = format!("{}/rocketchat/login/{}", self.config.as_url, login_token.token);
+
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let user = User::find(self.connection, &event.user_id)?;
+        let message = t!(["admin_room", "login_link"]).with_vars(vec![
+            ("login_url", login_url),
+            ("rocketchat_url", rocketchat_server.rocketchat_url.clone()),
+            ("timeout", LOGIN_TOKEN_TIMEOUT_MINUTES.to_string()),
+        ]);
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
+
+        Ok(info!(self.logger, "Sent login link to user {} for Rocket.Chat server {}", event.user_id, rocketchat_server.id))
+    }
+
+    fn logout(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        command.max_arguments(0)?;
+        let mut user_on_rocketchat_server =
+            UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
+        if !user_on_rocketchat_server.is_logged_in() {
+            bail_error!(
+                ErrorKind::UserNotLoggedIn(rocketchat_server.rocketchat_url.clone()),
+                t!(["errors", "not_logged_in"]).with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())])
+            );
+        }
+
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+
+        // the token is already invalid when the authentication fails, so it only has to be removed
+        if let Err(err) = rocketchat_api.logout() {
+            match *err.error_chain {
+                ErrorKind::AuthenticationFailed(_) => {
+                    info!(self.logger, "Auth token of user {} was already invalid", &event.user_id);
+                }
+                _ => return Err(err),
+            }
+        }
+
+        self.connection.transaction(|| {
+            user_on_rocketchat_server.set_credentials(self.connection, None, None, None)?;
+            QueuedMessage::delete_by_user(self.connection, &event.user_id, rocketchat_server.id.clone())
+        })?;
+
+        // The user stays in the bridged rooms to still receive the messages from Rocket.Chat, the
+        // rooms are left explicitly by the user or when unbridging them.
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let user = user_on_rocketchat_server.user(self.connection)?;
+        let message = t!(["admin_room", "logout_successful"])
+            .with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())]);
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
+
+        Ok(info!(
+            self.logger,
+            "Successfully executed logout command for user {} on Rocket.Chat server {}",
+            &event.user_id,
+            &rocketchat_server.rocketchat_url
+        ))
+    }
+
+    fn list_channels(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
         let user = User::find(self.connection, &event.user_id)?;
 
+        let (filter, page) = match (command.optional_argument(0), command.arguments.len()) {
+            (Some("page"), 2) => (None, Some(command.argument(1)?)),
+            (None, _) => (None, None),
+            _ => (Some(command.remaining_arguments(0)?.to_lowercase()), None),
+        };
+
         let user_on_rocketchat_server =
             UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
         let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
             .with_credentials(
                 user_on_rocketchat_server.rocketchat_user_id.unwrap_or_default(),
-                user_on_rocketchat_server.rocketchat_auth_token.unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
             );
-        let channels = rocketchat_api.channels_list()?;
+        let mut channels = rocketchat_api.channels_list()?;
+        channels.extend(rocketchat_api.groups_list()?);
+
+        if let Some(ref filter) = filter {
+            channels.retain(|channel| {
+                channel.name.clone().unwrap_or_else(|| channel.id.clone()).to_lowercase().contains(filter)
+            });
+        }
+        channels.sort_by(|a, b| a.name.cmp(&b.name));
+
+        let total_pages = (channels.len() + CHANNELS_PER_PAGE - 1) / CHANNELS_PER_PAGE;
+        let page = match page {
+            Some(page) => {
+                match page.parse::<usize>() {
+                    Ok(page) if page > 0 && page <= total_pages => page,
+                    _ => {
+                        bail_error!(
+                            ErrorKind::ChannelListPageNotFound(page.to_string()),
+                            t!(["errors", "channel_list_page_not_found"]).with_vars(vec![
+                                ("page", page.to_string()),
+                                ("total_pages", total_pages.to_string()),
+                            ])
+                        );
+                    }
+                }
+            }
+            None => 1,
+        };
+        let channels_on_page = channels.into_iter().skip((page - 1) * CHANNELS_PER_PAGE).take(CHANNELS_PER_PAGE).collect();
 
         let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
-        let channels_list = self.build_channels_list(&rocketchat_server.id, &event.user_id, channels)?;
-        let message = t!(["admin_room", "list_channels"]).with_vars(vec![("channel_list", channels_list)]);
+        let channels_list = if total_pages == 0 {
+            t!(["admin_room", "no_channels_found"]).l(&user.language)
+        } else {
+            self.build_channels_list(&rocketchat_server.id, &event.user_id, channels_on_page)?
+        };
+        let page_info = if total_pages > 1 {
+            t!(["admin_room", "list_channels_page"])
+                .with_vars(vec![("page", page.to_string()), ("total_pages", total_pages.to_string())])
+                .l(&user.language)
+        } else {
+            "".to_string()
+        };
+        let message =
+            t!(["admin_room", "list_channels"]).with_vars(vec![("channel_list", channels_list), ("page_info", page_info)]);
         self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
 
         Ok(info!(self.logger, "Successfully listed channels for Rocket.Chat server {}", &rocketchat_server.rocketchat_url))
     }
 
-    fn bridge(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, message: &str) -> Result<()> {
-        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+    fn bridge(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        let channel_name = command.argument(0)?;
+        command.max_arguments(1)?;
+
         let user_on_rocketchat_server =
             UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
         let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
             .with_credentials(
                 user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
-                user_on_rocketchat_server.rocketchat_auth_token.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
             );
 
-        let channels = rocketchat_api.channels_list()?;
+        if channel_name == BRIDGE_ALL_FLAG {
+            return self.bridge_all(event, rocketchat_server, &user_on_rocketchat_server, rocketchat_api.as_ref());
+        }
 
-        let mut command = message.split_whitespace().collect::<Vec<&str>>().into_iter();
-        let channel_name = command.by_ref().nth(1).unwrap_or_default();
+        let channel = self.find_channel(rocketchat_api.as_ref(), &user_on_rocketchat_server, channel_name)?;
+        let matrix_room_id = self.bridge_channel(&event.user_id, rocketchat_server, rocketchat_api.as_ref(), &channel)?;
 
-        let channel = match channels.iter().find(|channel| channel.name.clone().unwrap_or_default() == channel_name) {
-            Some(channel) => channel,
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let user = user_on_rocketchat_server.user(self.connection)?;
+        let message =
+            t!(["admin_room", "room_successfully_bridged"]).with_vars(vec![
+                (
+                    "channel_name",
+                    channel.name.clone().unwrap_or_else(|| channel.id.clone())
+                ),
+            ]);
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
+
+        Ok(info!(self.logger, "Successfully bridged room {} to {}", &channel.id, &matrix_room_id))
+    }
+
+    // Public channels that the user hasn't joined yet are joined, private groups cannot be joined,
+    // the user has to be invited on the Rocket.Chat server.
+    fn find_channel(
+        &self,
+        rocketchat_api: &RocketchatApi,
+        user_on_rocketchat_server: &UserOnRocketchatServer,
+        channel_name: &str,
+    ) -> Result<Channel> {
+        let username = user_on_rocketchat_server.rocketchat_username.clone().unwrap_or_default();
+        let channels = rocketchat_api.channels_list()?;
+        let channel = match channels.into_iter().find(|channel| channel.name.clone().unwrap_or_default() == channel_name) {
+            Some(mut channel) => {
+                if !channel.usernames.iter().any(|u| u == &username) {
+                    debug!(self.logger, "User {} is not a member of the channel {}, joining it", &username, channel_name);
+                    rocketchat_api.channels_join(&channel.id)?;
+                    channel.usernames.push(username.clone());
+                }
+                channel
+            }
             None => {
-                bail_error!(
-                    ErrorKind::RocketchatChannelNotFound(channel_name.to_string()),
-                    t!(["errors", "rocketchat_channel_not_found"]).with_vars(vec![("channel_name", channel_name.to_string())])
-                );
+                let groups = rocketchat_api.groups_list()?;
+                match groups.into_iter().find(|group| group.name.clone().unwrap_or_default() == channel_name) {
+                    // the group list doesn't contain the members of a group on all Rocket.Chat versions
+                    Some(group) => rocketchat_api.groups_info(&group.id)?,
+                    None => {
+                        bail_error!(
+                            ErrorKind::RocketchatChannelNotFound(channel_name.to_string()),
+                            t!(["errors", "rocketchat_channel_not_found"])
+                                .with_vars(vec![("channel_name", channel_name.to_string())])
+                        );
+                    }
+                }
             }
         };
 
-        let username = user_on_rocketchat_server.rocketchat_username.clone().unwrap_or_default();
         if !channel.usernames.iter().any(|u| u == &username) {
             bail_error!(
                 ErrorKind::RocketchatJoinFirst(channel_name.to_string()),
@@ -295,6 +751,103 @@ impl<'a> CommandHandler<'a> {
             );
         }
 
+        Ok(channel)
+    }
+
+    fn bridge_all(
+        &self,
+        event: &MessageEvent,
+        rocketchat_server: &RocketchatServer,
+        user_on_rocketchat_server: &UserOnRocketchatServer,
+        rocketchat_api: &RocketchatApi,
+    ) -> Result<()> {
+        let username = user_on_rocketchat_server.rocketchat_username.clone().unwrap_or_default();
+        let mut channels: Vec<Channel> = rocketchat_api
+            .channels_list()?
+            .into_iter()
+            .filter(|channel| channel.usernames.iter().any(|u| u == &username))
+            .collect();
+
+        // a channel that cannot be bridged doesn't stop the other channels from being bridged, the
+        // user gets a summary of the bridged and the failed channels
+        let mut bridged_channel_names = Vec::new();
+        let mut failed_channel_names = Vec::new();
+        for group in rocketchat_api.groups_list()? {
+            match rocketchat_api.groups_info(&group.id) {
+                Ok(group) => channels.push(group),
+                Err(err) => {
+                    log::log_error(self.logger, &err);
+                    failed_channel_names.push(group.name.unwrap_or(group.id));
+                }
+            }
+        }
+
+        for channel in channels {
+            let channel_name = channel.name.clone().unwrap_or_else(|| channel.id.clone());
+            match self.bridge_channel_if_not_bridged(&event.user_id, rocketchat_server, rocketchat_api, &channel) {
+                Ok(true) => bridged_channel_names.push(channel_name),
+                Ok(false) => {}
+                Err(err) => {
+                    log::log_error(self.logger, &err);
+                    failed_channel_names.push(channel_name);
+                }
+            }
+        }
+
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let user = user_on_rocketchat_server.user(self.connection)?;
+        if !bridged_channel_names.is_empty() || failed_channel_names.is_empty() {
+            let message = if bridged_channel_names.is_empty() {
+                t!(["admin_room", "no_rooms_to_bridge"])
+            } else {
+                t!(["admin_room", "rooms_successfully_bridged"])
+                    .with_vars(vec![("channel_names", bridged_channel_names.join(", "))])
+            };
+            let body = message.l(&user.language);
+            self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id.clone(), body)?;
+        }
+
+        if !failed_channel_names.is_empty() {
+            let message =
+                t!(["admin_room", "rooms_not_bridged"]).with_vars(vec![("channel_names", failed_channel_names.join(", "))]);
+            self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
+        }
+
+        Ok(info!(
+            self.logger,
+            "Bridged {} rooms for user {}, {} rooms could not be bridged",
+            bridged_channel_names.len(),
+            &event.user_id,
+            failed_channel_names.len()
+        ))
+    }
+
+    // Returns false if the channel is already bridged for the user.
+    fn bridge_channel_if_not_bridged(
+        &self,
+        matrix_user_id: &UserId,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_api: &RocketchatApi,
+        channel: &Channel,
+    ) -> Result<bool> {
+        if Room::is_bridged_for_user(self.config, self.matrix_api, &rocketchat_server.id, &channel.id, matrix_user_id)? {
+            debug!(self.logger, "Skipping channel {}, because it is already bridged", &channel.id);
+            return Ok(false);
+        }
+
+        self.bridge_channel(matrix_user_id, rocketchat_server, rocketchat_api, channel)?;
+        Ok(true)
+    }
+
+    fn bridge_channel(
+        &self,
+        matrix_user_id: &UserId,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_api: &RocketchatApi,
+        channel: &Channel,
+    ) -> Result<RoomId> {
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let channel_name = channel.name.clone().unwrap_or_else(|| channel.id.clone());
         let room_handler = RoomHandler::new(self.config, self.connection, self.logger, self.matrix_api);
         let matrix_room_id = match Room::matrix_id_from_rocketchat_channel_id(
             self.config,
@@ -303,7 +856,13 @@ impl<'a> CommandHandler<'a> {
             &channel.id,
         )? {
             Some(matrix_room_id) => {
-                room_handler.bridge_existing_room(matrix_room_id.clone(), event.user_id.clone(), channel_name.to_string())?;
+                room_handler.bridge_existing_room(matrix_room_id.clone(), matrix_user_id.clone(), channel_name)?;
+                room_handler.synchronize_virtual_users(
+                    rocketchat_api,
+                    channel,
+                    rocketchat_server.id.clone(),
+                    matrix_room_id.clone(),
+                )?;
                 matrix_room_id
             }
             None => {
@@ -311,38 +870,106 @@ impl<'a> CommandHandler<'a> {
                     rocketchat_api,
                     rocketchat_server,
                     channel,
-                    bot_matrix_user_id.clone(),
-                    event.user_id.clone(),
+                    bot_matrix_user_id,
+                    matrix_user_id.clone(),
                 )?
             }
         };
 
         let matrix_room_alias_id = Room::build_room_alias_id(self.config, &rocketchat_server.id, &channel.id)?;
-        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), Some(matrix_room_alias_id))?;
+        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), Some(matrix_room_alias_id), None)?;
+
+        Ok(matrix_room_id)
+    }
+
+    fn link(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        let channel_name = command.argument(0)?;
+        let room = command.argument(1)?;
+        command.max_arguments(2)?;
+
+        let matrix_room_id = self.matrix_room_id_from_argument(room)?;
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        // the bot user only stays in admin rooms and bridged rooms
+        if Room::is_accessible_by_bot(self.matrix_api, matrix_room_id.clone())? &&
+            Room::user_ids(self.matrix_api, matrix_room_id.clone(), None)?.contains(&bot_matrix_user_id)
+        {
+            bail_error!(
+                ErrorKind::BotAlreadyInRoom(room.to_string()),
+                t!(["errors", "bot_already_in_room"]).with_vars(vec![("room", room.to_string())])
+            );
+        }
+
+        let user_on_rocketchat_server =
+            UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+        let channel = self.find_channel(rocketchat_api.as_ref(), &user_on_rocketchat_server, channel_name)?;
+
+        if Room::matrix_id_from_rocketchat_channel_id(self.config, self.matrix_api, &rocketchat_server.id, &channel.id)?
+            .is_some()
+        {
+            bail_error!(
+                ErrorKind::RocketchatChannelAlreadyBridged(channel_name.to_string()),
+                t!(["errors", "rocketchat_channel_already_bridged"]).with_vars(vec![("channel_name", channel_name.to_string())])
+            );
+        }
+
+        // the bot user cannot read the power levels of the room before it joined it, the room is
+        // linked as soon as the bot user joins the room
+        let room_link = RoomLink {
+            admin_room_id: event.room_id.clone(),
+            matrix_user_id: event.user_id.clone(),
+            rocketchat_server_id: rocketchat_server.id.clone(),
+            channel: channel,
+        };
+        RoomHandler::add_pending_room_link(matrix_room_id.clone(), room_link);
 
         let user = user_on_rocketchat_server.user(self.connection)?;
-        let message =
-            t!(["admin_room", "room_successfully_bridged"]).with_vars(vec![
-                (
-                    "channel_name",
-                    channel.name.clone().unwrap_or_else(|| channel.id.clone())
-                ),
-            ]);
+        let message = t!(["admin_room", "room_link_pending"]).with_vars(vec![
+            ("bot_user_id", bot_matrix_user_id.to_string()),
+            ("channel_name", channel_name.to_string()),
+            ("room", room.to_string()),
+            ("timeout", PENDING_ROOM_LINK_TIMEOUT_MINUTES.to_string()),
+        ]);
         self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
 
-        Ok(info!(self.logger, "Successfully bridged room {} to {}", &channel.id, &matrix_room_id))
+        Ok(info!(self.logger, "Room {} waits for the bot user to be linked to {}", matrix_room_id, channel_name))
     }
 
-    fn unbridge(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, message: &str) -> Result<()> {
-        let mut command = message.split_whitespace().collect::<Vec<&str>>().into_iter();
-        let channel_name = command.nth(1).unwrap_or_default().to_string();
+    fn matrix_room_id_from_argument(&self, room: &str) -> Result<RoomId> {
+        let matrix_room_id = if room.starts_with('#') {
+            match RoomAliasId::try_from(room) {
+                Ok(matrix_room_alias_id) => self.matrix_api.get_room_alias(matrix_room_alias_id)?,
+                Err(_) => None,
+            }
+        } else {
+            RoomId::try_from(room).ok()
+        };
+
+        match matrix_room_id {
+            Some(matrix_room_id) => Ok(matrix_room_id),
+            None => {
+                Err(user_error!(
+                    ErrorKind::MatrixRoomNotFound(room.to_string()),
+                    t!(["errors", "matrix_room_not_found"]).with_vars(vec![("room", room.to_string())])
+                ))
+            }
+        }
+    }
+
+    fn unbridge(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        let channel_name = command.argument(0)?.to_string();
+        command.max_arguments(1)?;
 
         let user_on_rocketchat_server =
             UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
         let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
             .with_credentials(
                 user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
-                user_on_rocketchat_server.rocketchat_auth_token.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
             );
 
         let matrix_room_id = match Room::matrix_id_from_rocketchat_channel_name(
@@ -361,23 +988,30 @@ impl<'a> CommandHandler<'a> {
             }
         };
 
-        let virtual_user_prefix = format!("@{}", self.config.sender_localpart);
-        let user_ids: Vec<UserId> = Room::user_ids(self.matrix_api, matrix_room_id.clone(), None)?
-            .into_iter()
-            .filter(|id| !id.to_string().starts_with(&virtual_user_prefix))
-            .collect();
-        if !user_ids.is_empty() {
-            let user_ids = user_ids.iter().map(|id| id.to_string()).collect::<Vec<String>>().join(", ");
+        let rocketchat_channel_id = Room::rocketchat_channel_id(self.matrix_api, matrix_room_id.clone())?.unwrap_or_default();
+        let rocketchat_user_id = user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default();
+        if !self.is_allowed_to_unbridge(
+            rocketchat_api.as_ref(),
+            &rocketchat_channel_id,
+            &rocketchat_user_id,
+            matrix_room_id.clone(),
+            &event.user_id,
+        )?
+        {
             bail_error!(
-                ErrorKind::RoomNotEmpty(channel_name.to_string(), user_ids.clone()),
-                t!(["errors", "room_not_empty"]).with_vars(vec![("channel_name", channel_name), ("users", user_ids)])
+                ErrorKind::UnbridgeNotPermitted(channel_name.clone(), event.user_id.clone()),
+                t!(["errors", "unbridge_not_permitted"]).with_vars(vec![("channel_name", channel_name)])
             );
         }
 
-        let rocketchat_channel_id = Room::rocketchat_channel_id(self.matrix_api, matrix_room_id.clone())?.unwrap_or_default();
-        let room_alias_id = Room::build_room_alias_id(self.config, &rocketchat_server.id, &rocketchat_channel_id)?;
-        self.matrix_api.put_canonical_room_alias(event.room_id.clone(), None)?;
-        self.matrix_api.delete_room_alias(room_alias_id)?;
+        let room_handler = RoomHandler::new(self.config, self.connection, self.logger, self.matrix_api);
+        room_handler.unbridge_room(
+            matrix_room_id,
+            &event.user_id,
+            &rocketchat_server.id,
+            &rocketchat_channel_id,
+            channel_name.clone(),
+        )?;
 
         let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
         let user = User::find(self.connection, &event.user_id)?;
@@ -387,6 +1021,108 @@ impl<'a> CommandHandler<'a> {
         Ok(info!(self.logger, "Successfully unbridged room {}", channel_name.clone()))
     }
 
+    // Unbridging hands a room that was created by the bridge over to the user, so only members of
+    // the room that can already change its power levels or that own the Rocket.Chat channel are
+    // allowed to do it.
+    fn is_allowed_to_unbridge(
+        &self,
+        rocketchat_api: &RocketchatApi,
+        rocketchat_channel_id: &str,
+        rocketchat_user_id: &str,
+        matrix_room_id: RoomId,
+        matrix_user_id: &UserId,
+    ) -> Result<bool> {
+        let user_ids = Room::user_ids(self.matrix_api, matrix_room_id.clone(), None)?;
+        if !user_ids.iter().any(|id| id == matrix_user_id) {
+            return Ok(false);
+        }
+
+        let power_levels = self.matrix_api.get_room_powerlevels(matrix_room_id)?;
+        if power_levels.user_powerlevel(matrix_user_id) >= power_levels.state_event_powerlevel(EventType::RoomPowerLevels) {
+            return Ok(true);
+        }
+
+        // the roles of a private group can only be read with the groups endpoint
+        let room_roles = match rocketchat_api.channels_roles(rocketchat_channel_id) {
+            Ok(room_roles) => room_roles,
+            Err(_) => rocketchat_api.groups_roles(rocketchat_channel_id)?,
+        };
+        let is_owner = room_roles.iter().any(|room_roles| {
+            room_roles.user.id == rocketchat_user_id && room_roles.roles.iter().any(|role| role == "owner")
+        });
+
+        Ok(is_owner)
+    }
+
+    fn direct_message(&self, event: &MessageEvent, rocketchat_server: &RocketchatServer, command: &Command) -> Result<()> {
+        command.max_arguments(1)?;
+        let username = command.argument(0)?;
+
+        let user_on_rocketchat_server =
+            UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
+        if !user_on_rocketchat_server.is_logged_in() {
+            bail_error!(
+                ErrorKind::UserNotLoggedIn(rocketchat_server.rocketchat_url.clone()),
+                t!(["errors", "not_logged_in"]).with_vars(vec![("rocketchat_url", rocketchat_server.rocketchat_url.clone())])
+            );
+        }
+
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+
+        let rocketchat_user = match rocketchat_api.users_info(username) {
+            Ok(rocketchat_user) => rocketchat_user,
+            Err(err) => {
+                log::log_info(self.logger, &err);
+                bail_error!(
+                    ErrorKind::RocketchatUserNotFound(username.to_string()),
+                    t!(["errors", "rocketchat_user_not_found"]).with_vars(vec![("rocketchat_username", username.to_string())])
+                );
+            }
+        };
+
+        let virtual_user_handler = VirtualUserHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+        let virtual_user = virtual_user_handler.find_or_register(
+            rocketchat_server.id.clone(),
+            rocketchat_user.id.clone(),
+            rocketchat_user.username.clone(),
+        )?;
+        let direct_message_channel = rocketchat_api.direct_messages_create(&rocketchat_user.username)?;
+        DirectMessageHandler::cache_participants(&rocketchat_server.rocketchat_url, &direct_message_channel);
+
+        let user = user_on_rocketchat_server.user(self.connection)?;
+        let room_display_name_suffix = t!(["defaults", "direct_message_room_display_name_suffix"]).l(&user.language);
+        let room_display_name = format!("{} {}", rocketchat_user.username, room_display_name_suffix);
+        let room_handler = RoomHandler::new(self.config, self.connection, self.logger, self.matrix_api);
+        let matrix_room_id = room_handler.bridge_direct_message_room(
+            rocketchat_server.id.clone(),
+            &direct_message_channel.id,
+            virtual_user.matrix_user_id,
+            event.user_id.clone(),
+            room_display_name,
+        )?;
+
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let message = t!(["admin_room", "direct_message_room_ready"])
+            .with_vars(vec![("rocketchat_username", rocketchat_user.username.clone())]);
+        self.matrix_api.send_text_message_event(event.room_id.clone(), bot_matrix_user_id, message.l(&user.language))?;
+
+        Ok(info!(
+            self.logger,
+            "Successfully bridged direct message channel {} to room {}",
+            direct_message_channel.id,
+            matrix_room_id
+        ))
+    }
+
     fn get_existing_rocketchat_server(&self, rocketchat_url: String) -> Result<RocketchatServer> {
         let rocketchat_server: RocketchatServer = match RocketchatServer::find_by_url(self.connection, rocketchat_url)? {
             Some(rocketchat_server) => rocketchat_server,
@@ -4,11 +4,15 @@ use ruma_identifiers::RoomId;
 use slog::Logger;
 
 use api::{MatrixApi, RocketchatApi};
-use db::{Room, UserOnRocketchatServer};
+use config::Config;
+use db::{MAX_QUEUED_MESSAGES_PER_USER, QUEUED_MESSAGE_TIMEOUT_HOURS, QueuedMessage, RocketchatServer, Room,
+         UserOnRocketchatServer};
 use errors::*;
+use i18n::*;
 
 /// Forwards messages
 pub struct Forwarder<'a> {
+    config: &'a Config,
     connection: &'a SqliteConnection,
     logger: &'a Logger,
     matrix_api: &'a MatrixApi,
@@ -16,8 +20,14 @@ pub struct Forwarder<'a> {
 
 impl<'a> Forwarder<'a> {
     /// Create a new `Forwarder`.
-    pub fn new(connection: &'a SqliteConnection, logger: &'a Logger, matrix_api: &'a MatrixApi) -> Forwarder<'a> {
+    pub fn new(
+        config: &'a Config,
+        connection: &'a SqliteConnection,
+        logger: &'a Logger,
+        matrix_api: &'a MatrixApi,
+    ) -> Forwarder<'a> {
         Forwarder {
+            config: config,
             connection: connection,
             logger: logger,
             matrix_api: matrix_api,
@@ -28,8 +38,8 @@ impl<'a> Forwarder<'a> {
     pub fn process(&self, event: &MessageEvent, matrix_room_id: RoomId, rocketchat_channel_id: String) -> Result<()> {
         match Room::rocketchat_server(self.connection, self.matrix_api, matrix_room_id.clone())? {
             Some(rocketchat_server) => {
-                let user_on_rocketchat_server =
-                    UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id)?;
+                let mut user_on_rocketchat_server =
+                    UserOnRocketchatServer::find(self.connection, &event.user_id, rocketchat_server.id.clone())?;
 
                 if user_on_rocketchat_server.is_virtual_user {
                     debug!(self.logger, "Skipping event, because it was sent by a virtual user");
@@ -38,12 +48,31 @@ impl<'a> Forwarder<'a> {
 
                 match event.content {
                     MessageEventContent::Text(ref text_content) => {
-                        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url, self.logger.clone())?
+                        // the user was already asked to login again, the message is delivered after the login
+                        if user_on_rocketchat_server.has_invalid_credentials() {
+                            return self.queue_message(&user_on_rocketchat_server, &text_content.body, rocketchat_channel_id);
+                        }
+
+                        let encryption_key = self.config.database_encryption_key()?;
+                        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
                             .with_credentials(
                                 user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
-                                user_on_rocketchat_server.rocketchat_auth_token.clone().unwrap_or_default(),
+                                user_on_rocketchat_server.decrypted_auth_token(encryption_key.as_ref())?,
                             );
-                        rocketchat_api.post_chat_message(&text_content.body, &rocketchat_channel_id)?;
+
+                        if let Err(err) = rocketchat_api.post_chat_message(&text_content.body, &rocketchat_channel_id) {
+                            match *err.error_chain {
+                                ErrorKind::AuthenticationFailed(_) if user_on_rocketchat_server.is_logged_in() => {
+                                    self.queue_message(&user_on_rocketchat_server, &text_content.body, rocketchat_channel_id)?;
+                                    return self.handle_invalid_credentials(
+                                        &mut user_on_rocketchat_server,
+                                        &rocketchat_server,
+                                        matrix_room_id,
+                                    );
+                                }
+                                _ => return Err(err),
+                            }
+                        }
                     }
                     _ => info!(self.logger, "Forwarding the type {} is not implemented.", event.event_type),
                 }
@@ -55,4 +84,60 @@ impl<'a> Forwarder<'a> {
 
         Ok(())
     }
+
+    fn queue_message(
+        &self,
+        user_on_rocketchat_server: &UserOnRocketchatServer,
+        message: &str,
+        rocketchat_channel_id: String,
+    ) -> Result<()> {
+        let is_queued = QueuedMessage::queue(
+            self.connection,
+            user_on_rocketchat_server.matrix_user_id.clone(),
+            user_on_rocketchat_server.rocketchat_server_id.clone(),
+            rocketchat_channel_id,
+            message.to_string(),
+        )?;
+
+        if !is_queued {
+            return Ok(info!(
+                self.logger,
+                "Discarded message of user {}, because too many messages are queued",
+                user_on_rocketchat_server.matrix_user_id
+            ));
+        }
+
+        Ok(debug!(
+            self.logger,
+            "Queued message of user {}, because the credentials are invalid",
+            user_on_rocketchat_server.matrix_user_id
+        ))
+    }
+
+    fn handle_invalid_credentials(
+        &self,
+        user_on_rocketchat_server: &mut UserOnRocketchatServer,
+        rocketchat_server: &RocketchatServer,
+        matrix_room_id: RoomId,
+    ) -> Result<()> {
+        user_on_rocketchat_server.invalidate_credentials(self.connection)?;
+        info!(
+            self.logger,
+            "The credentials of user {} were rejected by the Rocket.Chat server {}",
+            user_on_rocketchat_server.matrix_user_id,
+            rocketchat_server.rocketchat_url
+        );
+
+        // users that logged in via the REST API don't have an admin room stored, they get the
+        // message in the room they were writing in
+        let room_id = user_on_rocketchat_server.admin_room_id.clone().unwrap_or(matrix_room_id);
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let user = user_on_rocketchat_server.user(self.connection)?;
+        let message = t!(["admin_room", "credentials_expired"]).with_vars(vec![
+            ("rocketchat_url", rocketchat_server.rocketchat_url.clone()),
+            ("max_messages", MAX_QUEUED_MESSAGES_PER_USER.to_string()),
+            ("timeout", QUEUED_MESSAGE_TIMEOUT_HOURS.to_string()),
+        ]);
+        self.matrix_api.send_text_message_event(room_id, bot_matrix_user_id, message.l(&user.language))
+    }
 }
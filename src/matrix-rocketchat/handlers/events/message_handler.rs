@@ -44,7 +44,7 @@ impl<'a> MessageHandler<'a> {
         if Room::is_admin_room(self.matrix_api.as_ref(), self.config, matrix_room_id.clone())? {
             CommandHandler::new(self.config, self.connection, self.logger, matrix_api).process(event, matrix_room_id)?;
         } else if let Some(channel_id) = Room::rocketchat_channel_id(matrix_api, matrix_room_id.clone())? {
-            Forwarder::new(self.connection, self.logger, matrix_api).process(event, matrix_room_id, channel_id)?;
+            Forwarder::new(self.config, self.connection, self.logger, matrix_api).process(event, matrix_room_id, channel_id)?;
         } else {
             debug!(self.logger, "Skipping event, because the room {} is not bridged", matrix_room_id);
         }
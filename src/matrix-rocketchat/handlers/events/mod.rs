@@ -1,5 +1,7 @@
 //! Event handlers
 
+/// Parses commands from the admin room
+pub mod command;
 /// Handles commands from the admin room
 pub mod command_handler;
 /// Event dispatcher
@@ -11,8 +13,9 @@ pub mod message_handler;
 /// Handles room events
 pub mod room_handler;
 
+pub use self::command::Command;
 pub use self::command_handler::CommandHandler;
 pub use self::event_dispatcher::EventDispatcher;
 pub use self::forwarder::Forwarder;
 pub use self::message_handler::MessageHandler;
-pub use self::room_handler::RoomHandler;
+pub use self::room_handler::{PENDING_ROOM_LINK_TIMEOUT_MINUTES, RoomHandler, RoomLink};
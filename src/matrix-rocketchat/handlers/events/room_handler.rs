@@ -1,9 +1,15 @@
+use std::cmp;
 use std::collections::HashMap;
 use std::convert::TryFrom;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
 
 use diesel::sqlite::SqliteConnection;
 use diesel::Connection;
 use iron::url::Host;
+use ruma_events::EventType;
+use ruma_events::room::history_visibility::HistoryVisibility;
+use ruma_events::room::join_rules::JoinRule;
 use ruma_events::room::member::{MemberEvent, MembershipState};
 use ruma_identifiers::{RoomId, UserId};
 use slog::Logger;
@@ -11,15 +17,44 @@ use slog::Logger;
 use api::{MatrixApi, RocketchatApi};
 use api::rocketchat::Channel;
 use config::Config;
-use db::{RocketchatServer, Room, User};
+use db::{GrantedPowerlevel, LinkedRoom, NewGrantedPowerlevel, NewLinkedRoom, RocketchatServer, Room, User,
+         UserOnRocketchatServer};
 use errors::*;
 use handlers::ErrorNotifier;
-use handlers::rocketchat::VirtualUserHandler;
+use handlers::rocketchat::{DirectMessageHandler, VirtualUserHandler};
 use i18n::*;
 use log;
 use serde_json::{self, Value};
 use super::CommandHandler;
 
+/// Number of minutes a room link waits for the bot user to be invited into the room before it is
+/// discarded
+pub const PENDING_ROOM_LINK_TIMEOUT_MINUTES: u64 = 10;
+
+lazy_static! {
+    /// Links of existing Matrix rooms to Rocket.Chat channels that wait for the bot user to be
+    /// invited into the room, indexed by the Matrix room ID.
+    static ref PENDING_ROOM_LINKS: Mutex<HashMap<RoomId, PendingRoomLink>> = Mutex::new(HashMap::new());
+}
+
+/// A link of an existing Matrix room to a Rocket.Chat channel
+pub struct RoomLink {
+    /// The admin room of the user who links the room
+    pub admin_room_id: RoomId,
+    /// The user who links the room
+    pub matrix_user_id: UserId,
+    /// The Rocket.Chat server the channel belongs to
+    pub rocketchat_server_id: String,
+    /// The channel the room is linked to
+    pub channel: Channel,
+}
+
+/// A room link that waits for the bot user to be invited into the room
+struct PendingRoomLink {
+    room_link: RoomLink,
+    started_at: Instant,
+}
+
 /// Handles room events
 pub struct RoomHandler<'a> {
     config: &'a Config,
@@ -57,6 +92,14 @@ impl<'a> RoomHandler<'a> {
 
                 self.handle_bot_invite(event.room_id.clone(), matrix_bot_user_id)?;
             }
+            MembershipState::Invite if self.is_bridge_user(&state_key) && !self.is_bridge_user(&event.user_id) => {
+                debug!(self.logger, "Virtual user {} got invite for room {} from {}", &state_key, event.room_id, event.user_id);
+
+                self.handle_virtual_user_invite(event.room_id.clone(), state_key, event.user_id.clone())?;
+            }
+            MembershipState::Join if is_profile_change(event) => {
+                debug!(self.logger, "Skipping profile change of user {} in room {}", &state_key, &event.room_id);
+            }
             MembershipState::Join if addressed_to_matrix_bot => {
                 debug!(self.logger, "Received join event for bot user {} and room {}", matrix_bot_user_id, event.room_id);
 
@@ -76,12 +119,12 @@ impl<'a> RoomHandler<'a> {
             MembershipState::Join => {
                 debug!(self.logger, "Received join event for user {} and room {}", &state_key, &event.room_id);
 
-                self.handle_user_join(event.room_id.clone())?;
+                self.handle_user_join(event.room_id.clone(), &state_key)?;
             }
             MembershipState::Leave if !addressed_to_matrix_bot => {
-                debug!(self.logger, "User {} left room {}", event.user_id, event.room_id);
+                debug!(self.logger, "User {} left room {}", state_key, event.room_id);
 
-                self.handle_user_leave(event.room_id.clone())?;
+                self.handle_user_leave(event.room_id.clone(), &state_key, &event.user_id)?;
             }
             _ => {
                 let msg = format!(
@@ -100,7 +143,7 @@ impl<'a> RoomHandler<'a> {
     /// homeserver and manages the rooms virtual users.
     pub fn bridge_new_room(
         &self,
-        rocketchat_api: Box<RocketchatApi>,
+        rocketchat_api: &RocketchatApi,
         rocketchat_server: &RocketchatServer,
         channel: &Channel,
         room_creator_id: UserId,
@@ -111,15 +154,109 @@ impl<'a> RoomHandler<'a> {
             channel.id.clone(),
             rocketchat_server.id.clone(),
             room_creator_id,
-            invited_user_id,
+            Some(invited_user_id),
             channel.name.clone(),
         )?;
         let matrix_room_alias_id = Room::build_room_alias_id(self.config, &rocketchat_server.id, &channel.id)?;
-        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), Some(matrix_room_alias_id))?;
-        self.add_virtual_users_to_room(rocketchat_api, channel, rocketchat_server.id.clone(), matrix_room_id.clone())?;
+        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), Some(matrix_room_alias_id), None)?;
+        self.synchronize_virtual_users(rocketchat_api, channel, rocketchat_server.id.clone(), matrix_room_id.clone())?;
         Ok(matrix_room_id)
     }
 
+    /// Creates a public room for a Rocket.Chat channel when a user tries to join the room alias
+    /// of a channel that is not bridged yet.
+    pub fn bridge_room_for_alias(
+        &self,
+        rocketchat_api: &RocketchatApi,
+        rocketchat_server: &RocketchatServer,
+        channel: &Channel,
+    ) -> Result<RoomId> {
+        debug!(self.logger, "Briding room for alias, Rocket.Chat channel: {}", channel.name.clone().unwrap_or_default());
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let matrix_room_id =
+            self.create_room(channel.id.clone(), rocketchat_server.id.clone(), bot_matrix_user_id, None, channel.name.clone())?;
+        // nobody is invited, the users join the room via the alias. Users that join without being
+        // logged in are kicked, they must not be able to read the history before they are kicked.
+        self.matrix_api.set_room_history_visibility(matrix_room_id.clone(), HistoryVisibility::Joined)?;
+        self.matrix_api.set_room_join_rule(matrix_room_id.clone(), JoinRule::Public)?;
+        let matrix_room_alias_id = Room::build_room_alias_id(self.config, &rocketchat_server.id, &channel.id)?;
+        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), Some(matrix_room_alias_id), None)?;
+        self.synchronize_virtual_users(rocketchat_api, channel, rocketchat_server.id.clone(), matrix_room_id.clone())?;
+        Ok(matrix_room_id)
+    }
+
+    /// Bridges a Rocket.Chat direct message channel for a Matrix user. The room is created by the
+    /// virtual user of the other participant if it doesn't exist yet, otherwise the Matrix user
+    /// is invited to the existing room.
+    pub fn bridge_direct_message_room(
+        &self,
+        rocketchat_server_id: String,
+        rocketchat_channel_id: &str,
+        virtual_user_id: UserId,
+        matrix_user_id: UserId,
+        room_display_name: String,
+    ) -> Result<RoomId> {
+        let dm_channel_id = format!("{}#dm", rocketchat_channel_id);
+        match Room::matrix_id_from_rocketchat_channel_id(self.config, self.matrix_api, &rocketchat_server_id, &dm_channel_id)? {
+            Some(matrix_room_id) => {
+                let virtual_user_handler = VirtualUserHandler {
+                    config: self.config,
+                    connection: self.connection,
+                    logger: self.logger,
+                    matrix_api: self.matrix_api,
+                };
+                virtual_user_handler.add_to_room(matrix_user_id, virtual_user_id, matrix_room_id.clone())?;
+                Ok(matrix_room_id)
+            }
+            None => {
+                self.create_direct_message_room(
+                    rocketchat_server_id,
+                    rocketchat_channel_id,
+                    virtual_user_id,
+                    matrix_user_id,
+                    room_display_name,
+                )
+            }
+        }
+    }
+
+    /// Creates the room for a Rocket.Chat direct message channel. The room is created by the
+    /// virtual user of the other participant and the Matrix user is invited to it.
+    pub fn create_direct_message_room(
+        &self,
+        rocketchat_server_id: String,
+        rocketchat_channel_id: &str,
+        virtual_user_id: UserId,
+        matrix_user_id: UserId,
+        room_display_name: String,
+    ) -> Result<RoomId> {
+        let dm_channel_id = format!("{}#dm", rocketchat_channel_id);
+        let matrix_room_id = self.create_room(
+            dm_channel_id,
+            rocketchat_server_id,
+            virtual_user_id.clone(),
+            Some(matrix_user_id),
+            Some(room_display_name),
+        )?;
+
+        self.invite_bot_into_direct_message_room(matrix_room_id.clone(), virtual_user_id)?;
+        debug!(self.logger, "Direct message room {} successfully created", &matrix_room_id);
+
+        Ok(matrix_room_id)
+    }
+
+    /// Stores a room link until the bot user is invited into the room, the room is linked when
+    /// the bot user joins it.
+    pub fn add_pending_room_link(matrix_room_id: RoomId, room_link: RoomLink) {
+        let mut pending_room_links = PENDING_ROOM_LINKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        remove_expired_pending_room_links(&mut pending_room_links);
+        let pending_room_link = PendingRoomLink {
+            room_link: room_link,
+            started_at: Instant::now(),
+        };
+        pending_room_links.insert(matrix_room_id, pending_room_link);
+    }
+
     /// Bridges a room that is already bridged (for other users) for a new user.
     pub fn bridge_existing_room(
         &self,
@@ -139,6 +276,102 @@ impl<'a> RoomHandler<'a> {
         self.matrix_api.invite(matrix_room_id, matrix_user_id, bot_matrix_user_id)
     }
 
+    /// Unbridges a room from a Rocket.Chat channel. The Matrix users stay in the room, but the
+    /// bot user and the virtual users give up their power levels and leave it. Rooms that were
+    /// created by the bridge cannot be joined anymore and are handed over to the Matrix user that
+    /// unbridged them, linked rooms are only detached and get back their previous canonical alias.
+    pub fn unbridge_room(
+        &self,
+        matrix_room_id: RoomId,
+        matrix_user_id: &UserId,
+        rocketchat_server_id: &str,
+        rocketchat_channel_id: &str,
+        rocketchat_channel_name: String,
+    ) -> Result<()> {
+        debug!(self.logger, "Unbridging room {}, Rocket.Chat channel: {}", matrix_room_id, rocketchat_channel_name);
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let room_creator_id = self.matrix_api.get_room_creator(matrix_room_id.clone())?;
+
+        let virtual_user_handler = VirtualUserHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+        let mut virtual_user_ids = Vec::new();
+        for user_id in Room::user_ids(self.matrix_api, matrix_room_id.clone(), None)? {
+            if virtual_user_handler.rocketchat_ids_from_matrix_user_id(&user_id)?.is_some() {
+                virtual_user_ids.push(user_id);
+            }
+        }
+
+        // messages from Matrix are forwarded based on the canonical alias, messages from Rocket.Chat
+        // based on the alias, the canonical alias is restored if the alias cannot be deleted so that
+        // the room is never bridged in one direction only
+        let matrix_room_alias_id = Room::build_room_alias_id(self.config, rocketchat_server_id, rocketchat_channel_id)?;
+        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), None, None)?;
+        if let Err(err) = self.matrix_api.delete_room_alias(matrix_room_alias_id.clone()) {
+            let matrix_room_alias_id = Some(matrix_room_alias_id);
+            if let Err(err) = self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), matrix_room_alias_id, None) {
+                log::log_error(self.logger, &err);
+            }
+
+            return Err(err);
+        }
+
+        // the room is detached and the command cannot be repeated without the alias, so the rest of
+        // the cleanup is done as far as possible and errors are only logged
+        let log_error = |result: Result<()>| {
+            if let Err(err) = result {
+                log::log_error(self.logger, &err);
+            }
+        };
+
+        let notice = t!(["defaults", "room_unbridged_notice"]).with_vars(vec![("channel_name", rocketchat_channel_name)]);
+        log_error(self.matrix_api.send_text_message_event(
+            matrix_room_id.clone(),
+            bot_matrix_user_id.clone(),
+            notice.l(DEFAULT_LANGUAGE),
+        ));
+        log_error(self.restore_canonical_alias(matrix_room_id.clone()));
+
+        let mut user_powerlevels: HashMap<UserId, i64> = virtual_user_ids.iter().map(|id| (id.clone(), 0)).collect();
+        user_powerlevels.insert(bot_matrix_user_id.clone(), 0);
+
+        // the bot user is the only user that can manage a room that was created by the bridge, so
+        // the room is handed over to the user that unbridged it and closed for new users
+        if room_creator_id == bot_matrix_user_id {
+            log_error(self.matrix_api.set_room_join_rule(matrix_room_id.clone(), JoinRule::Invite));
+            user_powerlevels.insert(matrix_user_id.clone(), 100);
+        }
+
+        log_error(self.matrix_api.set_user_powerlevels(matrix_room_id.clone(), bot_matrix_user_id.clone(), user_powerlevels));
+
+        // the bot user leaves first, otherwise a linked room without an alias would be treated as
+        // an admin room when the virtual users leave
+        log_error(self.leave_and_forget_room(matrix_room_id.clone(), bot_matrix_user_id));
+        for virtual_user_id in virtual_user_ids {
+            log_error(self.matrix_api.leave_room(matrix_room_id.clone(), virtual_user_id));
+        }
+
+        Ok(info!(self.logger, "Successfully unbridged room {}", matrix_room_id))
+    }
+
+    // a linked room gets back the canonical alias it had before it was linked
+    fn restore_canonical_alias(&self, matrix_room_id: RoomId) -> Result<()> {
+        let linked_room = match LinkedRoom::find_by_matrix_room_id(self.connection, &matrix_room_id)? {
+            Some(linked_room) => linked_room,
+            None => return Ok(()),
+        };
+
+        if linked_room.previous_canonical_alias.is_some() {
+            let previous_canonical_alias = linked_room.previous_canonical_alias.clone();
+            self.matrix_api.put_canonical_room_alias(matrix_room_id, previous_canonical_alias, None)?;
+        }
+
+        linked_room.delete(self.connection)
+    }
+
     fn handle_bot_invite(&self, matrix_room_id: RoomId, invited_user_id: UserId) -> Result<()> {
         if !self.config.accept_remote_invites && self.is_remote_invite(&matrix_room_id)? {
             info!(
@@ -156,6 +389,19 @@ impl<'a> RoomHandler<'a> {
     }
 
     fn handle_bot_join(&self, matrix_room_id: RoomId, matrix_bot_user_id: UserId, inviter_id: Option<UserId>) -> Result<()> {
+        // leave direct message room, the bot only joined it to be able to read the room members.
+        // This is checked first, because direct message rooms that were started on Matrix are
+        // created by the Matrix user and would otherwise be mistaken for an admin room.
+        if Room::is_direct_message_room(self.matrix_api, matrix_room_id.clone())? {
+            return self.matrix_api.leave_room(matrix_room_id, matrix_bot_user_id);
+        }
+
+        // rooms that are linked are created by Matrix users and would otherwise be mistaken for an
+        // admin room as well
+        if let Some(room_link) = take_pending_room_link(&matrix_room_id) {
+            return self.handle_room_link(matrix_room_id, matrix_bot_user_id, &room_link);
+        }
+
         let is_admin_room = match Room::is_admin_room(self.matrix_api, self.config, matrix_room_id.clone()) {
             Ok(is_admin_room) => is_admin_room,
             Err(err) => {
@@ -172,14 +418,221 @@ impl<'a> RoomHandler<'a> {
             self.setup_admin_room(matrix_room_id.clone(), matrix_bot_user_id.clone(), inviter_id)?;
         }
 
-        // leave direct message room, the bot only joined it to be able to read the room members
-        if Room::is_direct_message_room(self.matrix_api, matrix_room_id.clone())? {
-            self.matrix_api.leave_room(matrix_room_id.clone(), matrix_bot_user_id)?;
+        Ok(())
+    }
+
+    fn handle_room_link(&self, matrix_room_id: RoomId, matrix_bot_user_id: UserId, room_link: &RoomLink) -> Result<()> {
+        let channel_name = room_link.channel.name.clone().unwrap_or_else(|| room_link.channel.id.clone());
+        if let Err(err) = self.link_room(matrix_room_id.clone(), &matrix_bot_user_id, room_link) {
+            info!(self.logger, "Could not link room {} to channel {}, bot is leaving", matrix_room_id, channel_name);
+            let error_notifier = ErrorNotifier {
+                config: self.config,
+                connection: self.connection,
+                logger: self.logger,
+                matrix_api: self.matrix_api,
+            };
+            if let Err(err) =
+                error_notifier.send_message_to_user(&err, room_link.admin_room_id.clone(), &room_link.matrix_user_id)
+            {
+                log::log_error(self.logger, &err);
+            }
+
+            return self.leave_and_forget_room(matrix_room_id, matrix_bot_user_id);
+        }
+
+        let user = User::find(self.connection, &room_link.matrix_user_id)?;
+        let message = t!(["admin_room", "room_successfully_linked"])
+            .with_vars(vec![("room", matrix_room_id.to_string()), ("channel_name", channel_name.clone())]);
+        let admin_room_id = room_link.admin_room_id.clone();
+        self.matrix_api.send_text_message_event(admin_room_id, matrix_bot_user_id, message.l(&user.language))?;
+
+        Ok(info!(self.logger, "Successfully linked room {} to channel {}", matrix_room_id, channel_name))
+    }
+
+    // The room gets the same alias as a room that was created by the bridge. The user who links the
+    // room and the bot user need the power level to change the power levels of the room, because
+    // the bot user manages the power levels of the channel members. The canonical alias the room
+    // had before is stored, so that it can be restored when the room is unbridged.
+    fn link_room(&self, matrix_room_id: RoomId, matrix_bot_user_id: &UserId, room_link: &RoomLink) -> Result<()> {
+        debug!(self.logger, "Linking room {} to channel {}", matrix_room_id, room_link.channel.id);
+
+        let power_levels = self.matrix_api.get_room_powerlevels(matrix_room_id.clone())?;
+        let required_powerlevel = power_levels.state_event_powerlevel(EventType::RoomPowerLevels);
+        for &(matrix_user_id, translation_key) in &[
+            (&room_link.matrix_user_id, "insufficient_power_level_to_link_room"),
+            (matrix_bot_user_id, "bot_insufficient_power_level_to_link_room"),
+        ]
+        {
+            if power_levels.user_powerlevel(matrix_user_id) < required_powerlevel {
+                bail_error!(
+                    ErrorKind::InsufficientPowerLevelToLinkRoom(
+                        matrix_user_id.clone(),
+                        matrix_room_id.clone(),
+                        required_powerlevel,
+                    ),
+                    t!(["errors", translation_key]).with_vars(vec![
+                        ("matrix_user_id", matrix_user_id.to_string()),
+                        ("powerlevel", required_powerlevel.to_string()),
+                        ("room", matrix_room_id.to_string()),
+                    ])
+                );
+            }
+        }
+
+        let rocketchat_server = match RocketchatServer::find_by_id(self.connection, &room_link.rocketchat_server_id)? {
+            Some(rocketchat_server) => rocketchat_server,
+            None => {
+                let admin_room_id = room_link.admin_room_id.to_string();
+                bail_error!(ErrorKind::RoomNotConnected(admin_room_id), t!(["errors", "room_not_connected"]));
+            }
+        };
+        let user_on_rocketchat_server =
+            UserOnRocketchatServer::find(self.connection, &room_link.matrix_user_id, rocketchat_server.id.clone())?;
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+
+        let new_linked_room = NewLinkedRoom {
+            matrix_room_id: matrix_room_id.clone(),
+            previous_canonical_alias: self.matrix_api.get_room_canonical_alias(matrix_room_id.clone())?,
+        };
+        LinkedRoom::insert(self.connection, &new_linked_room)?;
+
+        let matrix_room_alias_id = Room::build_room_alias_id(self.config, &rocketchat_server.id, &room_link.channel.id)?;
+        self.matrix_api.put_room_alias(matrix_room_id.clone(), matrix_room_alias_id.clone())?;
+        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), Some(matrix_room_alias_id), None)?;
+        self.synchronize_virtual_users(rocketchat_api.as_ref(), &room_link.channel, rocketchat_server.id, matrix_room_id)
+    }
+
+    fn handle_virtual_user_invite(&self, matrix_room_id: RoomId, invited_user_id: UserId, inviter_id: UserId) -> Result<()> {
+        let virtual_user_handler = VirtualUserHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+
+        let (rocketchat_server, rocketchat_user_id) =
+            match virtual_user_handler.rocketchat_ids_from_matrix_user_id(&invited_user_id)? {
+                Some(rocketchat_ids) => rocketchat_ids,
+                None => {
+                    info!(self.logger, "Ignoring invite for {}, because the Rocket.Chat server is unknown", invited_user_id);
+                    return Ok(());
+                }
+            };
+
+        // the invite is accepted first, so that the virtual user can tell the inviter why the
+        // direct message could not be started
+        self.matrix_api.join(matrix_room_id.clone(), invited_user_id.clone())?;
+
+        if let Err(err) = self.setup_direct_message_room(
+            &virtual_user_handler,
+            &rocketchat_server,
+            &rocketchat_user_id,
+            matrix_room_id.clone(),
+            invited_user_id.clone(),
+            &inviter_id,
+        )
+        {
+            info!(self.logger, "Could not start direct message in room {}, {} is leaving", matrix_room_id, invited_user_id);
+            let error_notifier = ErrorNotifier {
+                config: self.config,
+                connection: self.connection,
+                logger: self.logger,
+                matrix_api: self.matrix_api,
+            };
+            if let Err(err) =
+                error_notifier.send_message_to_user_as(&err, matrix_room_id.clone(), &inviter_id, invited_user_id.clone())
+            {
+                log::log_error(self.logger, &err);
+            }
+
+            self.matrix_api.leave_room(matrix_room_id, invited_user_id)?;
         }
 
         Ok(())
     }
 
+    fn setup_direct_message_room(
+        &self,
+        virtual_user_handler: &VirtualUserHandler,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_user_id: &str,
+        matrix_room_id: RoomId,
+        virtual_user_id: UserId,
+        inviter_id: &UserId,
+    ) -> Result<()> {
+        debug!(self.logger, "Setting up direct message room {} for user {}", matrix_room_id, inviter_id);
+
+        let logged_in_users = rocketchat_server.logged_in_users_on_rocketchat_server(self.connection)?;
+        let user_on_rocketchat_server = match logged_in_users.into_iter().find(|user| &user.matrix_user_id == inviter_id) {
+            Some(user_on_rocketchat_server) => user_on_rocketchat_server,
+            None => {
+                let rocketchat_url = rocketchat_server.rocketchat_url.clone();
+                bail_error!(
+                    ErrorKind::UserNotLoggedIn(rocketchat_url.clone()),
+                    t!(["errors", "not_logged_in"]).with_vars(vec![("rocketchat_url", rocketchat_url)])
+                );
+            }
+        };
+
+        if Room::user_ids(self.matrix_api, matrix_room_id.clone(), Some(virtual_user_id.clone()))?.len() > 2 {
+            bail_error!(
+                ErrorKind::TooManyUsersInDirectMessageRoom(matrix_room_id),
+                t!(["errors", "too_many_members_in_direct_message_room"])
+            );
+        }
+
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+        let rocketchat_user = rocketchat_api.users_info_by_id(rocketchat_user_id)?;
+        virtual_user_handler.find_or_register(
+            rocketchat_server.id.clone(),
+            rocketchat_user.id.clone(),
+            rocketchat_user.username.clone(),
+        )?;
+        let direct_message_channel = rocketchat_api.direct_messages_create(&rocketchat_user.username)?;
+        DirectMessageHandler::cache_participants(&rocketchat_server.rocketchat_url, &direct_message_channel);
+
+        let dm_channel_id = format!("{}#dm", direct_message_channel.id);
+        let matrix_room_alias_id = Room::build_room_alias_id(self.config, &rocketchat_server.id, &dm_channel_id)?;
+        if let Some(existing_matrix_room_id) = self.matrix_api.get_room_alias(matrix_room_alias_id.clone())? {
+            // there can only be one room per direct message channel, the user gets an invite to the
+            // existing room instead
+            virtual_user_handler.add_to_room(inviter_id.clone(), virtual_user_id, existing_matrix_room_id)?;
+            bail_error!(
+                ErrorKind::DirectMessageRoomAlreadyExists(rocketchat_user.username.clone()),
+                t!(["errors", "direct_message_room_already_exists"])
+                    .with_vars(vec![("rocketchat_username", rocketchat_user.username)])
+            );
+        }
+
+        self.matrix_api.put_room_alias(matrix_room_id.clone(), matrix_room_alias_id.clone())?;
+        // the canonical alias is set by the virtual user, because the bot user is not in the room
+        let matrix_room_alias_id = Some(matrix_room_alias_id);
+        self.matrix_api.put_canonical_room_alias(matrix_room_id.clone(), matrix_room_alias_id, Some(virtual_user_id.clone()))?;
+        self.invite_bot_into_direct_message_room(matrix_room_id.clone(), virtual_user_id)?;
+
+        Ok(info!(
+            self.logger,
+            "Successfully bridged direct message channel {} to room {}",
+            direct_message_channel.id,
+            matrix_room_id
+        ))
+    }
+
+    // invite the bot user into the direct message room to be able to read the room members
+    // the bot will leave as soon as the AS gets the join event
+    fn invite_bot_into_direct_message_room(&self, matrix_room_id: RoomId, virtual_user_id: UserId) -> Result<()> {
+        let matrix_bot_user_id = self.config.matrix_bot_user_id()?;
+        self.matrix_api.invite(matrix_room_id, matrix_bot_user_id, virtual_user_id)
+    }
+
     fn setup_admin_room(&self, matrix_room_id: RoomId, matrix_bot_user_id: UserId, inviter_id: Option<UserId>) -> Result<()> {
         debug!(self.logger, "Setting up a new admin room with id {}", matrix_room_id);
 
@@ -222,7 +675,7 @@ impl<'a> RoomHandler<'a> {
         })
     }
 
-    fn handle_user_join(&self, matrix_room_id: RoomId) -> Result<()> {
+    fn handle_user_join(&self, matrix_room_id: RoomId, matrix_user_id: &UserId) -> Result<()> {
         if Room::is_admin_room(self.matrix_api, self.config, matrix_room_id.clone())? &&
             !self.is_private_room(matrix_room_id.clone())?
         {
@@ -231,20 +684,217 @@ impl<'a> RoomHandler<'a> {
             let body = t!(["errors", "other_user_joined"]).l(&admin_room_language);
             let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
             self.matrix_api.send_text_message_event(matrix_room_id.clone(), bot_matrix_user_id.clone(), body)?;
-            self.leave_and_forget_room(matrix_room_id, bot_matrix_user_id)?;
+            return self.leave_and_forget_room(matrix_room_id, bot_matrix_user_id);
         }
-        Ok(())
+
+        if self.kick_user_without_login(matrix_room_id.clone(), matrix_user_id)? {
+            return Ok(());
+        }
+
+        self.forward_membership_change(matrix_room_id, MembershipState::Join, matrix_user_id, matrix_user_id)
     }
 
-    fn handle_user_leave(&self, matrix_room_id: RoomId) -> Result<()> {
+    // Rooms that the bridge created for a room alias can be joined by anyone, users that are not
+    // logged in on the Rocket.Chat server would see the messages of the channel without being
+    // members of it, so they are kicked. Linked rooms are managed by their creator and the bot
+    // user and the virtual users are always allowed to join.
+    fn kick_user_without_login(&self, matrix_room_id: RoomId, matrix_user_id: &UserId) -> Result<bool> {
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        if matrix_user_id == &bot_matrix_user_id {
+            return Ok(false);
+        }
+
+        let rocketchat_server = match self.bridged_rocketchat_ids(matrix_room_id.clone())? {
+            Some((rocketchat_server, _)) => rocketchat_server,
+            None => return Ok(false),
+        };
+
+        if self.matrix_api.get_room_creator(matrix_room_id.clone())? != bot_matrix_user_id {
+            return Ok(false);
+        }
+
+        let virtual_user_handler = VirtualUserHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+        if virtual_user_handler.rocketchat_ids_from_matrix_user_id(matrix_user_id)?.is_some() {
+            return Ok(false);
+        }
+
+        let logged_in_users = rocketchat_server.logged_in_users_on_rocketchat_server(self.connection)?;
+        if logged_in_users.iter().any(|user| &user.matrix_user_id == matrix_user_id) {
+            return Ok(false);
+        }
+
+        info!(
+            self.logger,
+            "Kicking {} from room {}, the user is not logged in on {}",
+            matrix_user_id,
+            matrix_room_id,
+            rocketchat_server.id
+        );
+        let reason = t!(["defaults", "not_logged_in_kick_reason"]).l(DEFAULT_LANGUAGE);
+        self.matrix_api.kick_user(matrix_room_id, matrix_user_id.clone(), Some(reason))?;
+        Ok(true)
+    }
+
+    fn handle_user_leave(&self, matrix_room_id: RoomId, matrix_user_id: &UserId, sender_id: &UserId) -> Result<()> {
         if Room::is_admin_room(self.matrix_api, self.config, matrix_room_id.clone())? {
             let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
             return self.leave_and_forget_room(matrix_room_id, bot_matrix_user_id);
         }
 
+        self.forward_membership_change(matrix_room_id, MembershipState::Leave, matrix_user_id, sender_id)
+    }
+
+    // Joins, leaves and kicks in a bridged room are forwarded to the Rocket.Chat channel with the
+    // credentials of the user who changed the membership. Changes by users that are not logged
+    // in, like the bot user, are not forwarded. Errors that the user can act on are sent to the
+    // admin room of the user, other errors are only logged, because the membership already
+    // changed on Matrix.
+    fn forward_membership_change(
+        &self,
+        matrix_room_id: RoomId,
+        membership: MembershipState,
+        matrix_user_id: &UserId,
+        sender_id: &UserId,
+    ) -> Result<()> {
+        let (rocketchat_server, rocketchat_channel_id) = match self.bridged_rocketchat_ids(matrix_room_id.clone())? {
+            Some(rocketchat_ids) => rocketchat_ids,
+            None => return Ok(()),
+        };
+
+        let logged_in_users = rocketchat_server.logged_in_users_on_rocketchat_server(self.connection)?;
+        let sender = match logged_in_users.iter().find(|user| &user.matrix_user_id == sender_id) {
+            Some(sender) => sender,
+            None => {
+                debug!(
+                    self.logger,
+                    "Not forwarding membership change in room {}, {} is not logged in",
+                    matrix_room_id,
+                    sender_id
+                );
+                return Ok(());
+            }
+        };
+
+        let virtual_user_handler = VirtualUserHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+        let logged_in_user = logged_in_users.iter().find(|user| &user.matrix_user_id == matrix_user_id);
+        let rocketchat_user_id =
+            match (logged_in_user, virtual_user_handler.rocketchat_ids_from_matrix_user_id(matrix_user_id)?) {
+                (Some(logged_in_user), _) => logged_in_user.rocketchat_user_id.clone().unwrap_or_default(),
+                (None, Some((ref server, ref rocketchat_user_id))) if server.id == rocketchat_server.id => {
+                    rocketchat_user_id.clone()
+                }
+                _ => {
+                    debug!(self.logger, "Not forwarding membership change of {}, Rocket.Chat user not found", matrix_user_id);
+                    return Ok(());
+                }
+            };
+
+        if let Err(err) = self.change_rocketchat_membership(
+            &rocketchat_server,
+            &rocketchat_channel_id,
+            membership,
+            sender,
+            matrix_user_id,
+            &rocketchat_user_id,
+        )
+        {
+            match sender.admin_room_id.clone() {
+                Some(admin_room_id) if err.user_message.is_some() => {
+                    let error_notifier = ErrorNotifier {
+                        config: self.config,
+                        connection: self.connection,
+                        logger: self.logger,
+                        matrix_api: self.matrix_api,
+                    };
+                    error_notifier.send_message_to_user(&err, admin_room_id, sender_id)?;
+                }
+                _ => log::log_info(self.logger, &err),
+            }
+        }
+
         Ok(())
     }
 
+    fn change_rocketchat_membership(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_channel_id: &str,
+        membership: MembershipState,
+        sender: &UserOnRocketchatServer,
+        matrix_user_id: &UserId,
+        rocketchat_user_id: &str,
+    ) -> Result<()> {
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                sender.rocketchat_user_id.clone().unwrap_or_default(),
+                sender.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+
+        // private groups are only visible for their members
+        let (channel, is_group) = match rocketchat_api.channels_info(rocketchat_channel_id) {
+            Ok(channel) => (channel, false),
+            Err(_) => match rocketchat_api.groups_info(rocketchat_channel_id) {
+                Ok(group) => (group, true),
+                Err(err) => {
+                    debug!(self.logger, "Not forwarding membership change, channel {} not found", rocketchat_channel_id);
+                    log::log_debug(self.logger, &err);
+                    return Ok(());
+                }
+            },
+        };
+        let channel_name = channel.name.clone().unwrap_or_else(|| channel.id.clone());
+
+        let is_sender = sender.rocketchat_user_id.clone().unwrap_or_default() == rocketchat_user_id;
+        let (result, translation_key) = match membership {
+            MembershipState::Join => {
+                // members of a private group don't have to join it and members of a channel
+                // don't have to join it again
+                let username = sender.rocketchat_username.clone().unwrap_or_default();
+                if is_group || channel.usernames.contains(&username) {
+                    return Ok(());
+                }
+                (rocketchat_api.channels_join(rocketchat_channel_id), "rocketchat_join_failed")
+            }
+            _ if is_sender && is_group => {
+                (rocketchat_api.groups_leave(rocketchat_channel_id), "rocketchat_leave_failed")
+            }
+            _ if is_sender => (rocketchat_api.channels_leave(rocketchat_channel_id), "rocketchat_leave_failed"),
+            _ if is_group => {
+                (rocketchat_api.groups_kick(rocketchat_channel_id, rocketchat_user_id), "rocketchat_kick_failed")
+            }
+            _ => (rocketchat_api.channels_kick(rocketchat_channel_id, rocketchat_user_id), "rocketchat_kick_failed"),
+        };
+
+        if let Err(err) = result {
+            // errors returned by the Rocket.Chat server, like missing permissions, are passed on
+            // to the user
+            if let ErrorKind::RocketchatError(ref error_msg) = *err.error_chain {
+                bail_error!(
+                    ErrorKind::RocketchatMembershipChangeFailed(channel_name.clone(), error_msg.clone()),
+                    t!(["errors", translation_key]).with_vars(vec![
+                        ("channel_name", channel_name),
+                        ("error_msg", error_msg.clone()),
+                        ("matrix_user_id", matrix_user_id.to_string()),
+                    ])
+                );
+            }
+
+            return Err(err);
+        }
+
+        Ok(info!(self.logger, "Forwarded membership change of {} to channel {}", matrix_user_id, rocketchat_channel_id))
+    }
+
     fn leave_and_forget_room(&self, matrix_room_id: RoomId, matrix_user_id: UserId) -> Result<()> {
         self.matrix_api.leave_room(matrix_room_id.clone(), matrix_user_id)?;
         self.matrix_api.forget_room(matrix_room_id)
@@ -277,6 +927,30 @@ impl<'a> RoomHandler<'a> {
         Ok(())
     }
 
+    fn bridged_rocketchat_ids(&self, matrix_room_id: RoomId) -> Result<Option<(RocketchatServer, String)>> {
+        let matrix_room_alias_id = match self.matrix_api.get_room_canonical_alias(matrix_room_id)? {
+            Some(matrix_room_alias_id) => matrix_room_alias_id,
+            None => return Ok(None),
+        };
+        let (rocketchat_server_id, rocketchat_channel_id) =
+            match Room::rocketchat_ids_from_room_alias_id(self.config, &matrix_room_alias_id)? {
+                Some(rocketchat_ids) => rocketchat_ids,
+                None => return Ok(None),
+            };
+        let rocketchat_server = RocketchatServer::find_by_id(self.connection, &rocketchat_server_id)?;
+        Ok(rocketchat_server.map(|rocketchat_server| (rocketchat_server, rocketchat_channel_id)))
+    }
+
+    // the bot user or a virtual user, Matrix users whose name starts with the same localpart are
+    // not part of the bridge
+    fn is_bridge_user(&self, matrix_user_id: &UserId) -> bool {
+        let user_id = matrix_user_id.to_string();
+        let bot_user_id = format!("@{}:{}", self.config.sender_localpart, self.config.hs_domain);
+        let virtual_user_prefix = format!("@{}_", self.config.sender_localpart);
+        let hs_domain_suffix = format!(":{}", self.config.hs_domain);
+        user_id == bot_user_id || (user_id.starts_with(&virtual_user_prefix) && user_id.ends_with(&hs_domain_suffix))
+    }
+
     fn is_private_room(&self, matrix_room_id: RoomId) -> Result<bool> {
         Ok(Room::user_ids(self.matrix_api, matrix_room_id, None)?.len() <= 2)
     }
@@ -287,7 +961,7 @@ impl<'a> RoomHandler<'a> {
         rocketchat_channel_id: String,
         rocketchat_server_id: String,
         room_creator_id: UserId,
-        invited_user_id: UserId,
+        invited_user_id: Option<UserId>,
         room_display_name: Option<String>,
     ) -> Result<RoomId> {
         let matrix_room_alias_id = Room::build_room_alias_name(self.config, &rocketchat_server_id, &rocketchat_channel_id);
@@ -296,21 +970,26 @@ impl<'a> RoomHandler<'a> {
         debug!(self.logger, "Successfully created room, matrix_room_id is {}", &matrix_room_id);
         self.matrix_api.set_default_powerlevels(matrix_room_id.clone(), room_creator_id.clone())?;
         debug!(self.logger, "Successfully set powerlevels for room {}", &matrix_room_id);
-        self.matrix_api.invite(matrix_room_id.clone(), invited_user_id.clone(), room_creator_id.clone())?;
-        debug!(self.logger, "{} successfully invited {} into room {}", &room_creator_id, &invited_user_id, &matrix_room_id);
+        if let Some(invited_user_id) = invited_user_id {
+            self.matrix_api.invite(matrix_room_id.clone(), invited_user_id.clone(), room_creator_id.clone())?;
+            debug!(self.logger, "{} successfully invited {} into room {}", &room_creator_id, &invited_user_id, &matrix_room_id);
+        }
 
         Ok(matrix_room_id)
     }
 
-    /// Add all users that are in a Rocket.Chat room to the Matrix room.
-    pub fn add_virtual_users_to_room(
+    /// Synchronizes the virtual users in a room with the members of the Rocket.Chat channel.
+    /// Members that are missing are added to the room and virtual users of Rocket.Chat users that
+    /// left the channel are kicked. The power levels of the users are updated to match their roles
+    /// in the channel.
+    pub fn synchronize_virtual_users(
         &self,
-        rocketchat_api: Box<RocketchatApi>,
+        rocketchat_api: &RocketchatApi,
         channel: &Channel,
         rocketchat_server_id: String,
         matrix_room_id: RoomId,
     ) -> Result<()> {
-        debug!(self.logger, "Starting to add virtual users to room {}", matrix_room_id);
+        debug!(self.logger, "Starting to synchronize the virtual users in room {}", matrix_room_id);
 
         let virtual_user_handler = VirtualUserHandler {
             config: self.config,
@@ -322,10 +1001,23 @@ impl<'a> RoomHandler<'a> {
         //TODO: Check if a max number of users per channel has to be defined to avoid problems when
         //there are several thousand users in a channel.
         let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let mut rocketchat_user_ids = Vec::new();
         for username in &channel.usernames {
-            let rocketchat_user = rocketchat_api.users_info(username)?;
-            let user_on_rocketchat_server =
-                virtual_user_handler.find_or_register(rocketchat_server_id.clone(), rocketchat_user.id, username.to_string())?;
+            // the channel only contains the usernames, the Rocket.Chat user is only looked up for
+            // members that don't have a virtual user yet
+            let user_on_rocketchat_server = match UserOnRocketchatServer::find_virtual_user_by_rocketchat_username(
+                self.connection,
+                rocketchat_server_id.clone(),
+                username.to_string(),
+            )? {
+                Some(user_on_rocketchat_server) => user_on_rocketchat_server,
+                None => {
+                    let rocketchat_user_id = rocketchat_api.users_info(username)?.id;
+                    let username = username.to_string();
+                    virtual_user_handler.find_or_register(rocketchat_server_id.clone(), rocketchat_user_id, username)?
+                }
+            };
+            rocketchat_user_ids.push(user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default());
             virtual_user_handler.add_to_room(
                 user_on_rocketchat_server.matrix_user_id,
                 bot_matrix_user_id.clone(),
@@ -333,9 +1025,144 @@ impl<'a> RoomHandler<'a> {
             )?;
         }
 
-        debug!(self.logger, "Successfully added {} virtual users to room {}", channel.usernames.len(), matrix_room_id);
+        let reason = t!(["defaults", "left_rocketchat_channel_kick_reason"]).l(DEFAULT_LANGUAGE);
+        let mut kicked_users = 0;
+        for matrix_user_id in Room::user_ids(self.matrix_api, matrix_room_id.clone(), Some(bot_matrix_user_id))? {
+            let rocketchat_user_id = match virtual_user_handler.rocketchat_ids_from_matrix_user_id(&matrix_user_id)? {
+                Some((ref rocketchat_server, ref rocketchat_user_id)) if rocketchat_server.id == rocketchat_server_id => {
+                    rocketchat_user_id.clone()
+                }
+                _ => continue,
+            };
 
-        Ok(())
+            if !rocketchat_user_ids.iter().any(|id| id == &rocketchat_user_id) {
+                info!(self.logger, "Kicking virtual user {} from room {}", matrix_user_id, matrix_room_id);
+                self.matrix_api.kick_user(matrix_room_id.clone(), matrix_user_id, Some(reason.clone()))?;
+                kicked_users += 1;
+            }
+        }
+
+        debug!(
+            self.logger,
+            "Successfully synchronized room {}, {} members, {} virtual users kicked",
+            matrix_room_id,
+            channel.usernames.len(),
+            kicked_users
+        );
+
+        self.synchronize_powerlevels(rocketchat_api, channel, rocketchat_server_id, matrix_room_id)
+    }
+
+    // Rocket.Chat users get the power level that is configured for their roles in the channel.
+    // Logged in users get it for their Matrix user, all the other users for their virtual user.
+    fn synchronize_powerlevels(
+        &self,
+        rocketchat_api: &RocketchatApi,
+        channel: &Channel,
+        rocketchat_server_id: String,
+        matrix_room_id: RoomId,
+    ) -> Result<()> {
+        let rocketchat_server = match RocketchatServer::find_by_id(self.connection, &rocketchat_server_id)? {
+            Some(rocketchat_server) => rocketchat_server,
+            None => return Ok(()),
+        };
+
+        let room_roles = if channel.channel_type == "p" {
+            rocketchat_api.groups_roles(&channel.id)?
+        } else {
+            rocketchat_api.channels_roles(&channel.id)?
+        };
+        let mut rocketchat_powerlevels = HashMap::new();
+        for room_roles in room_roles {
+            let powerlevels = room_roles.roles.iter().filter_map(|role| self.config.power_levels.get(role));
+            rocketchat_powerlevels.insert(room_roles.user.id, powerlevels.max().cloned().unwrap_or_default());
+        }
+
+        let virtual_user_handler = VirtualUserHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+            matrix_api: self.matrix_api,
+        };
+        let logged_in_users = rocketchat_server.logged_in_users_on_rocketchat_server(self.connection)?;
+        let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
+        let room_powerlevels = self.matrix_api.get_room_powerlevels(matrix_room_id.clone())?;
+        // the bot user can have a power level below 100 in a linked room, the users get lower power
+        // levels than the bot user, otherwise it could not change them anymore
+        let max_powerlevel = room_powerlevels.user_powerlevel(&bot_matrix_user_id) - 1;
+        self.connection.transaction(|| {
+            let mut user_powerlevels = HashMap::new();
+            for matrix_user_id in Room::user_ids(self.matrix_api, matrix_room_id.clone(), Some(bot_matrix_user_id.clone()))? {
+                let logged_in_user = logged_in_users.iter().find(|user| user.matrix_user_id == matrix_user_id);
+                let current_powerlevel = room_powerlevels.user_powerlevel(&matrix_user_id);
+                let powerlevel =
+                    match (logged_in_user, virtual_user_handler.rocketchat_ids_from_matrix_user_id(&matrix_user_id)?) {
+                        (Some(logged_in_user), _) => {
+                            let rocketchat_user_id = logged_in_user.rocketchat_user_id.clone().unwrap_or_default();
+                            let powerlevel = rocketchat_powerlevels.get(&rocketchat_user_id).cloned().unwrap_or_default();
+                            let powerlevel = cmp::min(powerlevel, max_powerlevel);
+                            self.matrix_user_powerlevel(&matrix_room_id, &matrix_user_id, current_powerlevel, powerlevel)?
+                        }
+                        (None, Some((ref server, ref rocketchat_user_id))) if server.id == rocketchat_server.id => {
+                            let powerlevel = rocketchat_powerlevels.get(rocketchat_user_id).cloned().unwrap_or_default();
+                            Some(cmp::min(powerlevel, max_powerlevel))
+                        }
+                        // users that logged out lose the power levels that the bridge granted them
+                        _ => self.matrix_user_powerlevel(&matrix_room_id, &matrix_user_id, current_powerlevel, 0)?,
+                    };
+
+                if let Some(powerlevel) = powerlevel {
+                    user_powerlevels.insert(matrix_user_id, powerlevel);
+                }
+            }
+
+            self.matrix_api.set_user_powerlevels(matrix_room_id.clone(), bot_matrix_user_id.clone(), user_powerlevels)
+        })
+    }
+
+    // Returns the new power level of a Matrix user or `None` if it stays the same. The power levels
+    // of Matrix users are only raised above the level they had before, because it might have been
+    // granted on Matrix. The levels the bridge granted are stored to revoke them when the user
+    // loses the role, unless the power level was changed on Matrix in the meantime.
+    fn matrix_user_powerlevel(
+        &self,
+        matrix_room_id: &RoomId,
+        matrix_user_id: &UserId,
+        current_powerlevel: i64,
+        rocketchat_powerlevel: i64,
+    ) -> Result<Option<i64>> {
+        let granted_powerlevel = GrantedPowerlevel::find(self.connection, matrix_room_id, matrix_user_id)?;
+        let previous_powerlevel = match granted_powerlevel {
+            Some(ref granted_powerlevel) if granted_powerlevel.powerlevel == current_powerlevel => {
+                granted_powerlevel.previous_powerlevel
+            }
+            Some(ref granted_powerlevel) => {
+                granted_powerlevel.delete(self.connection)?;
+                current_powerlevel
+            }
+            None => current_powerlevel,
+        };
+
+        let powerlevel = cmp::max(rocketchat_powerlevel, previous_powerlevel);
+        if powerlevel == current_powerlevel {
+            return Ok(None);
+        }
+
+        if powerlevel == previous_powerlevel {
+            if let Some(granted_powerlevel) = granted_powerlevel {
+                granted_powerlevel.delete(self.connection)?;
+            }
+        } else {
+            let new_granted_powerlevel = NewGrantedPowerlevel {
+                matrix_room_id: matrix_room_id.clone(),
+                matrix_user_id: matrix_user_id.clone(),
+                powerlevel: powerlevel,
+                previous_powerlevel: previous_powerlevel,
+            };
+            GrantedPowerlevel::insert(self.connection, &new_granted_powerlevel)?;
+        }
+
+        Ok(Some(powerlevel))
     }
 
     fn handle_admin_room_setup_error(&self, err: &Error, matrix_room_id: RoomId, matrix_bot_user_id: UserId) {
@@ -354,3 +1181,27 @@ impl<'a> RoomHandler<'a> {
         }
     }
 }
+
+fn take_pending_room_link(matrix_room_id: &RoomId) -> Option<RoomLink> {
+    let mut pending_room_links = PENDING_ROOM_LINKS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    remove_expired_pending_room_links(&mut pending_room_links);
+    pending_room_links.remove(matrix_room_id).map(|pending_room_link| pending_room_link.room_link)
+}
+
+fn remove_expired_pending_room_links(pending_room_links: &mut HashMap<RoomId, PendingRoomLink>) {
+    let timeout = Duration::from_secs(PENDING_ROOM_LINK_TIMEOUT_MINUTES * 60);
+    pending_room_links.retain(|_, pending_room_link| pending_room_link.started_at.elapsed() < timeout);
+}
+
+// Changes of the display name or the avatar are sent as join events of users that already joined
+// the room.
+fn is_profile_change(event: &MemberEvent) -> bool {
+    if let Some(ref prev_content) = event.prev_content {
+        return prev_content.membership == MembershipState::Join;
+    }
+
+    let unsigned: HashMap<String, Value> =
+        serde_json::from_value(event.unsigned.clone().unwrap_or_default()).unwrap_or_default();
+    let previous_membership = unsigned.get("prev_content").and_then(|prev_content| prev_content.get("membership"));
+    previous_membership.and_then(|membership| membership.as_str()) == Some("join")
+}
@@ -0,0 +1,265 @@
+use std::io::Read;
+
+use diesel::sqlite::SqliteConnection;
+use iron::{Handler, status};
+use iron::headers::ContentType;
+use iron::method::Method;
+use iron::prelude::*;
+use iron::request::Body;
+use iron::status::Status;
+use router::Router;
+use slog::Logger;
+use url::form_urlencoded;
+
+use api::MatrixApi;
+use config::Config;
+use db::{ConnectionPool, LoginToken, RocketchatServer, User};
+use errors::*;
+use handlers::rocketchat::{Credentials, Login};
+use i18n::*;
+use log::{self, IronLogger};
+
+/// Layout of the login page, `${title}` and `${body}` are replaced when the page is rendered
+const LOGIN_PAGE_LAYOUT: &'static str = include_str!("../../../../assets/login_page.html");
+
+/// `LoginPage` serves a login form that is reached through a one-time login link, which is posted
+/// in the admin room when the user sends `login` without any arguments.
+pub struct LoginPage {
+    /// Application service configuration
+    pub config: Config,
+    /// Matrix REST API
+    pub matrix_api: Box<MatrixApi>,
+}
+
+/// Fields of the submitted login form
+#[derive(Default)]
+struct LoginForm {
+    csrf_token: String,
+    username: String,
+    password: String,
+    totp_code: Option<String>,
+}
+
+impl Handler for LoginPage {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        let logger = IronLogger::from_request(request)?;
+        let connection = ConnectionPool::from_request(request)?;
+
+        let token = request.extensions.get::<Router>().and_then(|params| params.find("token")).unwrap_or("").to_string();
+        let login_token = match LoginToken::find_valid(&connection, &token)? {
+            Some(login_token) => login_token,
+            None => {
+                debug!(logger, "Login page was requested with an invalid or expired token");
+                return Ok(render_message(status::NotFound, t!(["handlers", "login_page_invalid_link"]), DEFAULT_LANGUAGE));
+            }
+        };
+        let rocketchat_server = match RocketchatServer::find_by_id(&connection, &login_token.rocketchat_server_id)? {
+            Some(rocketchat_server) => rocketchat_server,
+            None => {
+                login_token.delete(&connection)?;
+                return Ok(render_message(status::NotFound, t!(["handlers", "login_page_invalid_link"]), DEFAULT_LANGUAGE));
+            }
+        };
+        let user = User::find(&connection, &login_token.matrix_user_id)?;
+
+        match request.method {
+            Method::Post => {
+                let login_form = deserialize_login_form(&mut request.body)?;
+                self.login(&connection, &logger, &login_token, &rocketchat_server, &user, login_form)
+            }
+            _ => Ok(render_form(status::Ok, &login_token, &rocketchat_server, &user, None)),
+        }
+    }
+}
+
+impl LoginPage {
+    fn login(
+        &self,
+        connection: &SqliteConnection,
+        logger: &Logger,
+        login_token: &LoginToken,
+        rocketchat_server: &RocketchatServer,
+        user: &User,
+        login_form: LoginForm,
+    ) -> IronResult<Response> {
+        // the CSRF token is only known to the page that was served for the login link, so a form
+        // on another site cannot submit credentials on behalf of the user
+        if !constant_time_eq(&login_form.csrf_token, &login_token.csrf_token) {
+            info!(logger, "Rejected login form of user {} with an invalid CSRF token", login_token.matrix_user_id);
+            return Ok(render_message(status::Forbidden, t!(["handlers", "login_page_invalid_csrf_token"]), &user.language));
+        }
+
+        let login = Login {
+            config: &self.config,
+            connection: connection,
+            logger: logger,
+            matrix_api: self.matrix_api.as_ref(),
+        };
+        let admin_room_id = Some(login_token.admin_room_id.clone());
+
+        // the second step of a login with two-factor authentication only submits the code, the
+        // credentials of the first step are kept by the pending login
+        let is_totp_step = login_form.username.is_empty() && login_form.totp_code.is_some();
+        let result = match (is_totp_step, login_form.totp_code) {
+            (true, Some(totp_code)) => {
+                login.call_with_totp_code(&login_token.matrix_user_id, &totp_code, rocketchat_server, admin_room_id)
+            }
+            (_, totp_code) => {
+                let credentials = Credentials {
+                    matrix_user_id: login_token.matrix_user_id.clone(),
+                    rocketchat_username: login_form.username,
+                    password: login_form.password,
+                    rocketchat_url: rocketchat_server.rocketchat_url.clone(),
+                    totp_code: totp_code,
+                };
+                login.call(&credentials, rocketchat_server, admin_room_id)
+            }
+        };
+
+        if let Err(err) = result {
+            if let ErrorKind::TwoFactorAuthenticationCodeRequired = *err.error_chain {
+                debug!(logger, "Asking user {} for the two-factor authentication code", login_token.matrix_user_id);
+                return Ok(render_totp_form(status::Ok, login_token, user, None));
+            }
+
+            log::log_error(logger, &err);
+            if !login_token.record_failed_attempt(connection)? {
+                info!(logger, "Login link of user {} deleted after too many failed logins", login_token.matrix_user_id);
+                let message = t!(["handlers", "login_page_too_many_failed_attempts"]);
+                return Ok(render_message(err.status_code(), message, &user.language));
+            }
+
+            let error_message = match err.user_message {
+                Some(ref user_message) => user_message.l(&user.language),
+                None => t!(["defaults", "internal_error"]).l(&user.language),
+            };
+            return match *err.error_chain {
+                // the pending login is kept when the code is invalid, so the user can try another code
+                ErrorKind::InvalidTwoFactorAuthenticationCode if is_totp_step => {
+                    Ok(render_totp_form(err.status_code(), login_token, user, Some(error_message)))
+                }
+                _ => Ok(render_form(err.status_code(), login_token, rocketchat_server, user, Some(error_message))),
+            };
+        }
+
+        login_token.delete(connection)?;
+        Ok(render_message(status::Ok, t!(["handlers", "rocketchat_login_successful"]), &user.language))
+    }
+}
+
+fn render_form(
+    status: Status,
+    login_token: &LoginToken,
+    rocketchat_server: &RocketchatServer,
+    user: &User,
+    error_message: Option<String>,
+) -> Response {
+    let language = &user.language;
+    let error = match error_message {
+        Some(error_message) => format!(r#"<p class="error">{}</p>"#, escape_html(&error_message)),
+        None => "".to_string(),
+    };
+    let description = t!(["handlers", "login_page_description"]).with_vars(vec![
+        ("matrix_user_id", login_token.matrix_user_id.to_string()),
+        ("rocketchat_url", rocketchat_server.rocketchat_url.clone()),
+    ]);
+    let body = format!(
+        r#"<p>{description}</p>
+    {error}
+    <form method="post">
+      <input type="hidden" name="csrf_token" value="{csrf_token}">
+      <label for="username">{username}</label>
+      <input type="text" id="username" name="username" autocomplete="username" required autofocus>
+      <label for="password">{password}</label>
+      <input type="password" id="password" name="password" autocomplete="current-password" required>
+      <label for="totp_code">{totp_code}</label>
+      <input type="text" id="totp_code" name="totp_code" autocomplete="one-time-code">
+      <input type="submit" value="{submit}">
+    </form>"#,
+        description = escape_html(&description.l(language)),
+        error = error,
+        csrf_token = escape_html(&login_token.csrf_token),
+        username = escape_html(&t!(["handlers", "login_page_username"]).l(language)),
+        password = escape_html(&t!(["handlers", "login_page_password"]).l(language)),
+        totp_code = escape_html(&t!(["handlers", "login_page_totp_code"]).l(language)),
+        submit = escape_html(&t!(["handlers", "login_page_submit"]).l(language)),
+    );
+
+    render_page(status, body, language)
+}
+
+fn render_totp_form(status: Status, login_token: &LoginToken, user: &User, error_message: Option<String>) -> Response {
+    let language = &user.language;
+    let error = match error_message {
+        Some(error_message) => format!(r#"<p class="error">{}</p>"#, escape_html(&error_message)),
+        None => "".to_string(),
+    };
+    let body = format!(
+        r#"<p>{description}</p>
+    {error}
+    <form method="post">
+      <input type="hidden" name="csrf_token" value="{csrf_token}">
+      <label for="totp_code">{totp_code}</label>
+      <input type="text" id="totp_code" name="totp_code" autocomplete="one-time-code" required autofocus>
+      <input type="submit" value="{submit}">
+    </form>"#,
+        description = escape_html(&t!(["handlers", "login_page_totp_code_required"]).l(language)),
+        error = error,
+        csrf_token = escape_html(&login_token.csrf_token),
+        totp_code = escape_html(&t!(["handlers", "login_page_code"]).l(language)),
+        submit = escape_html(&t!(["handlers", "login_page_submit"]).l(language)),
+    );
+
+    render_page(status, body, language)
+}
+
+fn render_message(status: Status, message: I18n, language: &str) -> Response {
+    let body = format!("<p>{}</p>", escape_html(&message.l(language)));
+    render_page(status, body, language)
+}
+
+fn render_page(status: Status, body: String, language: &str) -> Response {
+    let title = escape_html(&t!(["handlers", "login_page_title"]).l(language));
+    let page = LOGIN_PAGE_LAYOUT.replace("${title}", &title).replace("${body}", &body);
+
+    let mut response = Response::with((status, page));
+    response.headers.set(ContentType::html());
+    // the page contains a form for credentials, it must not be cached or embedded into other sites
+    response.headers.set_raw("Cache-Control", vec![b"no-store".to_vec()]);
+    response.headers.set_raw("X-Frame-Options", vec![b"DENY".to_vec()]);
+    response
+}
+
+fn deserialize_login_form(body: &mut Body) -> Result<LoginForm> {
+    let mut payload = String::new();
+    body.read_to_string(&mut payload).chain_err(|| ErrorKind::InternalServerError)?;
+
+    let mut login_form = LoginForm::default();
+    for (key, value) in form_urlencoded::parse(payload.as_bytes()) {
+        match key.as_ref() {
+            "csrf_token" => login_form.csrf_token = value.into_owned(),
+            "username" => login_form.username = value.into_owned(),
+            "password" => login_form.password = value.into_owned(),
+            "totp_code" if !value.trim().is_empty() => login_form.totp_code = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+
+    Ok(login_form)
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+fn constant_time_eq(a: &str, b: &str) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+
+    a.bytes().zip(b.bytes()).fold(0, |result, (x, y)| result | (x ^ y)) == 0
+}
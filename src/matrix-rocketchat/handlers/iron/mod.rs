@@ -1,15 +1,24 @@
 //! Iron handlers
 
+/// Login form that is reached through a one-time login link
+pub mod login_page;
 /// Process requests from the Rocket.Chat server
 pub mod rocketchat;
 /// Process login request for Rocket.Chat
 pub mod rocketchat_login;
+/// Processes room alias queries from the Matrix homeserver
+pub mod rooms;
 /// Processes requests from the Matrix homeserver
 pub mod transactions;
+/// Processes user queries from the Matrix homeserver
+pub mod users;
 /// Sends a welcome message to the caller
 pub mod welcome;
 
+pub use self::login_page::LoginPage;
 pub use self::rocketchat::Rocketchat;
 pub use self::rocketchat_login::RocketchatLogin;
+pub use self::rooms::Rooms;
 pub use self::transactions::Transactions;
+pub use self::users::Users;
 pub use self::welcome::Welcome;
@@ -26,7 +26,7 @@ impl Rocketchat {
             matrix_api: matrix_api,
         };
         let mut chain = Chain::new(rocketchat);
-        chain.link_before(RocketchatToken {});
+        chain.link_before(RocketchatToken { config: config });
 
         chain
     }
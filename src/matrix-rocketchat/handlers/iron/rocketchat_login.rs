@@ -10,8 +10,8 @@ use api::MatrixApi;
 use config::Config;
 use db::{ConnectionPool, RocketchatServer};
 use errors::*;
-use handlers::rocketchat::{Credentials, Login};
-use log::IronLogger;
+use handlers::rocketchat::{AccessTokenCredentials, Credentials, Login};
+use log::{IronLogger, redact_secrets};
 
 /// `RocketchatLogin` is an endpoint that allows a user to login to Rocket.Chat via REST API.
 pub struct RocketchatLogin {
@@ -21,32 +21,55 @@ pub struct RocketchatLogin {
     pub matrix_api: Box<MatrixApi>,
 }
 
+/// The login endpoint accepts a username and password or a personal access token.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum LoginRequest {
+    /// Login with a username and a password
+    Password(Credentials),
+    /// Login with a personal access token
+    AccessToken(AccessTokenCredentials),
+}
+
+impl LoginRequest {
+    fn rocketchat_url(&self) -> &str {
+        match *self {
+            LoginRequest::Password(ref credentials) => &credentials.rocketchat_url,
+            LoginRequest::AccessToken(ref credentials) => &credentials.rocketchat_url,
+        }
+    }
+}
+
 impl Handler for RocketchatLogin {
     fn handle(&self, request: &mut Request) -> IronResult<Response> {
         let logger = IronLogger::from_request(request)?;
         debug!(logger, "Received login command via REST API");
 
         let connection = ConnectionPool::from_request(request)?;
-        let credentials = deserialize_credentials(&mut request.body)?;
+        let login_request = deserialize_login_request(&mut request.body)?;
         let login = Login {
             config: &self.config,
             connection: &connection,
             logger: &logger,
             matrix_api: self.matrix_api.as_ref(),
         };
-        let rocketchat_server = match RocketchatServer::find_by_url(&connection, credentials.rocketchat_url.clone())? {
+        let rocketchat_url = login_request.rocketchat_url().to_string();
+        let rocketchat_server = match RocketchatServer::find_by_url(&connection, rocketchat_url.clone())? {
             Some(rocketchat_server) => rocketchat_server,
             None => {
                 return Err(user_error!(
-                    ErrorKind::AdminRoomForRocketchatServerNotFound(credentials.rocketchat_url.clone()),
-                    t!(["errors", "rocketchat_server_not_found"]).with_vars(vec![
-                        ("rocketchat_url", credentials.rocketchat_url.clone()),
-                    ])
+                    ErrorKind::AdminRoomForRocketchatServerNotFound(rocketchat_url.clone()),
+                    t!(["errors", "rocketchat_server_not_found"]).with_vars(vec![("rocketchat_url", rocketchat_url)])
                 ))?;
             }
         };
 
-        if let Err(err) = login.call(&credentials, &rocketchat_server, None) {
+        let result = match login_request {
+            LoginRequest::Password(ref credentials) => login.call(credentials, &rocketchat_server, None),
+            LoginRequest::AccessToken(ref credentials) => login.call_with_access_token(credentials, &rocketchat_server, None),
+        };
+
+        if let Err(err) = result {
             return Err(err)?;
         }
 
@@ -55,10 +78,10 @@ impl Handler for RocketchatLogin {
 }
 
 
-fn deserialize_credentials(body: &mut Body) -> Result<Credentials> {
+fn deserialize_login_request(body: &mut Body) -> Result<LoginRequest> {
     let mut payload = String::new();
     body.read_to_string(&mut payload).chain_err(|| ErrorKind::InternalServerError)?;
     serde_json::from_str(&payload)
-        .chain_err(|| ErrorKind::InvalidJSON(format!("Could not deserialize login request: `{}`", payload)))
+        .chain_err(|| ErrorKind::InvalidJSON(format!("Could not deserialize login request: `{}`", redact_secrets(&payload))))
         .map_err(Error::from)
 }
@@ -0,0 +1,104 @@
+use std::convert::TryFrom;
+
+use diesel::sqlite::SqliteConnection;
+use iron::{Handler, status};
+use iron::prelude::*;
+use router::Router;
+use ruma_identifiers::RoomAliasId;
+use slog::Logger;
+use url::percent_encoding::percent_decode;
+
+use api::{MatrixApi, RocketchatApi};
+use config::Config;
+use db::{ConnectionPool, RocketchatServer, Room};
+use errors::*;
+use handlers::events::RoomHandler;
+use log::{self, IronLogger};
+use middleware::AccessToken;
+
+/// Rooms is an endpoint of the application service API which is called by the homeserver to
+/// query the existence of a room alias in the namespace of the application service. The room is
+/// created on demand, so a Rocket.Chat channel can be joined via its alias.
+pub struct Rooms {
+    config: Config,
+    matrix_api: Box<MatrixApi>,
+}
+
+impl Rooms {
+    /// Rooms endpoint with middleware
+    pub fn chain(config: Config, matrix_api: Box<MatrixApi>) -> Chain {
+        let rooms = Rooms {
+            config: config.clone(),
+            matrix_api: matrix_api,
+        };
+        let mut chain = Chain::new(rooms);
+        chain.link_before(AccessToken { config: config });
+
+        chain
+    }
+}
+
+impl Handler for Rooms {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        let logger = IronLogger::from_request(request)?;
+        let connection = ConnectionPool::from_request(request)?;
+
+        let url_room_alias = request.extensions.get::<Router>().and_then(|params| params.find("room_alias")).unwrap_or("");
+        let room_alias = percent_decode(url_room_alias.as_bytes()).decode_utf8_lossy().to_string();
+        debug!(logger, "Homeserver queried room alias {}", room_alias);
+
+        if let Err(err) = self.bridge_room_for_alias(&connection, &logger, &room_alias) {
+            log::log_info(&logger, &err);
+            return Err(err)?;
+        }
+
+        Ok(Response::with((status::Ok, "{}".to_string())))
+    }
+}
+
+impl Rooms {
+    fn bridge_room_for_alias(&self, connection: &SqliteConnection, logger: &Logger, room_alias: &str) -> Result<()> {
+        let matrix_room_alias_id = match RoomAliasId::try_from(room_alias) {
+            Ok(matrix_room_alias_id) => matrix_room_alias_id,
+            Err(_) => bail_error!(ErrorKind::UnknownRoomAlias(room_alias.to_string())),
+        };
+
+        let (rocketchat_server_id, rocketchat_channel_id) =
+            match Room::rocketchat_ids_from_room_alias_id(&self.config, &matrix_room_alias_id)? {
+                Some(rocketchat_ids) => rocketchat_ids,
+                None => bail_error!(ErrorKind::UnknownRoomAlias(room_alias.to_string())),
+            };
+
+        let rocketchat_server = match RocketchatServer::find_by_id(connection, &rocketchat_server_id)? {
+            Some(rocketchat_server) => rocketchat_server,
+            None => bail_error!(ErrorKind::UnknownRoomAlias(room_alias.to_string())),
+        };
+
+        // the homeserver doesn't tell who is querying the alias, so the channel is looked up with
+        // the credentials of a user that is logged in on the Rocket.Chat server. Only the public
+        // channels are bridged, which every user on the Rocket.Chat server can see.
+        let user_on_rocketchat_server =
+            match rocketchat_server.logged_in_users_on_rocketchat_server(connection)?.into_iter().next() {
+                Some(user_on_rocketchat_server) => user_on_rocketchat_server,
+                None => bail_error!(ErrorKind::UnknownRoomAlias(room_alias.to_string())),
+            };
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), logger.clone())?.with_credentials(
+            user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+            user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+        );
+
+        // only public channels can be joined via alias, private groups need an invite on the Rocket.Chat server
+        let channel = match rocketchat_api.channels_info(&rocketchat_channel_id) {
+            Ok(channel) => channel,
+            Err(err) => {
+                log::log_info(logger, &err);
+                bail_error!(ErrorKind::UnknownRoomAlias(room_alias.to_string()));
+            }
+        };
+
+        let room_handler = RoomHandler::new(&self.config, connection, logger, self.matrix_api.as_ref());
+        let matrix_room_id = room_handler.bridge_room_for_alias(rocketchat_api.as_ref(), &rocketchat_server, &channel)?;
+
+        Ok(info!(logger, "Successfully bridged room {} to {} for alias {}", &channel.id, &matrix_room_id, room_alias))
+    }
+}
@@ -0,0 +1,102 @@
+use std::convert::TryFrom;
+
+use diesel::sqlite::SqliteConnection;
+use iron::{Handler, status};
+use iron::prelude::*;
+use router::Router;
+use ruma_identifiers::UserId;
+use slog::Logger;
+use url::percent_encoding::percent_decode;
+
+use api::{MatrixApi, RocketchatApi};
+use config::Config;
+use db::ConnectionPool;
+use errors::*;
+use handlers::rocketchat::VirtualUserHandler;
+use log::{self, IronLogger};
+use middleware::AccessToken;
+
+/// Users is an endpoint of the application service API which is called by the homeserver to
+/// query the existence of a user in the namespace of the application service. Virtual users are
+/// registered on demand, so Rocket.Chat users can be invited before they sent a message.
+pub struct Users {
+    config: Config,
+    matrix_api: Box<MatrixApi>,
+}
+
+impl Users {
+    /// Users endpoint with middleware
+    pub fn chain(config: Config, matrix_api: Box<MatrixApi>) -> Chain {
+        let users = Users {
+            config: config.clone(),
+            matrix_api: matrix_api,
+        };
+        let mut chain = Chain::new(users);
+        chain.link_before(AccessToken { config: config });
+
+        chain
+    }
+}
+
+impl Handler for Users {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        let logger = IronLogger::from_request(request)?;
+        let connection = ConnectionPool::from_request(request)?;
+
+        let url_user_id = request.extensions.get::<Router>().and_then(|params| params.find("user_id")).unwrap_or("");
+        let user_id = percent_decode(url_user_id.as_bytes()).decode_utf8_lossy().to_string();
+        debug!(logger, "Homeserver queried user {}", user_id);
+
+        if let Err(err) = self.register_virtual_user(&connection, &logger, &user_id) {
+            log::log_info(&logger, &err);
+            return Err(err)?;
+        }
+
+        Ok(Response::with((status::Ok, "{}".to_string())))
+    }
+}
+
+impl Users {
+    fn register_virtual_user(&self, connection: &SqliteConnection, logger: &Logger, user_id: &str) -> Result<()> {
+        let matrix_user_id = match UserId::try_from(user_id) {
+            Ok(matrix_user_id) => matrix_user_id,
+            Err(_) => bail_error!(ErrorKind::UnknownVirtualUser(user_id.to_string())),
+        };
+
+        let virtual_user_handler = VirtualUserHandler {
+            config: &self.config,
+            connection: connection,
+            logger: logger,
+            matrix_api: self.matrix_api.as_ref(),
+        };
+        let (rocketchat_server, rocketchat_user_id) =
+            match virtual_user_handler.rocketchat_ids_from_matrix_user_id(&matrix_user_id)? {
+                Some(rocketchat_ids) => rocketchat_ids,
+                None => bail_error!(ErrorKind::UnknownVirtualUser(user_id.to_string())),
+            };
+
+        // the user is looked up with the credentials of a user that is logged in on the Rocket.Chat server
+        let user_on_rocketchat_server =
+            match rocketchat_server.logged_in_users_on_rocketchat_server(connection)?.into_iter().next() {
+                Some(user_on_rocketchat_server) => user_on_rocketchat_server,
+                None => bail_error!(ErrorKind::UnknownVirtualUser(user_id.to_string())),
+            };
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), logger.clone())?.with_credentials(
+            user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+            user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+        );
+
+        let rocketchat_user = match rocketchat_api.users_info_by_id(&rocketchat_user_id) {
+            Ok(rocketchat_user) => rocketchat_user,
+            Err(err) => {
+                log::log_info(logger, &err);
+                bail_error!(ErrorKind::UnknownVirtualUser(user_id.to_string()));
+            }
+        };
+
+        let virtual_user =
+            virtual_user_handler.find_or_register(rocketchat_server.id.clone(), rocketchat_user.id, rocketchat_user.username)?;
+
+        Ok(info!(logger, "Successfully provisioned virtual user {}", virtual_user.matrix_user_id))
+    }
+}
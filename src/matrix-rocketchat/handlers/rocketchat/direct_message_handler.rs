@@ -0,0 +1,262 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use diesel::sqlite::SqliteConnection;
+use slog::Logger;
+
+use api::RocketchatApi;
+use api::rocketchat::Channel;
+use config::Config;
+use db::{RocketchatServer, UserOnRocketchatServer};
+use errors::*;
+use log;
+
+/// Number of seconds before a channel that is not a direct message channel of a logged in user
+/// is looked up again
+pub const UNKNOWN_CHANNEL_LOOKUP_INTERVAL_SECONDS: u64 = 60;
+/// Number of seconds before the participants of a group direct message channel are looked up
+/// again, because users can be added to it
+pub const GROUP_DIRECT_MESSAGE_REFRESH_INTERVAL_SECONDS: u64 = 60;
+/// Maximum number of channels in each cache, the entries that were looked up first are removed
+/// when a cache is full
+pub const MAX_CACHED_CHANNELS: usize = 1000;
+/// Room type of Rocket.Chat direct message channels
+const DIRECT_MESSAGE_CHANNEL_TYPE: &'static str = "d";
+
+lazy_static! {
+    /// Usernames of the participants of Rocket.Chat direct message channels, indexed by the
+    /// Rocket.Chat server URL and the channel ID. The participants of a direct message between
+    /// two users never change, so those channels only have to be looked up once. At most
+    /// `MAX_CACHED_CHANNELS` channels are cached.
+    static ref DIRECT_MESSAGE_PARTICIPANTS: Mutex<HashMap<(String, String), CachedParticipants>> = Mutex::new(HashMap::new());
+    /// Channels that are not direct message channels of a logged in user, indexed by the
+    /// Rocket.Chat server URL and the channel ID. They are not looked up again until the lookup
+    /// interval passed, because a user could log in in the meantime. At most `MAX_CACHED_CHANNELS`
+    /// channels are remembered, the lookup is not skipped for channels that don't fit anymore.
+    static ref UNKNOWN_CHANNELS: Mutex<HashMap<(String, String), Instant>> = Mutex::new(HashMap::new());
+}
+
+/// The participants of a Rocket.Chat direct message channel
+pub struct DirectMessageParticipants {
+    /// Usernames of all the participants, including the sender of the message
+    pub usernames: Vec<String>,
+    /// Flag to indicate that the participants were just looked up on the Rocket.Chat server
+    /// instead of being taken from the cache, so they could have changed
+    pub refreshed: bool,
+}
+
+impl DirectMessageParticipants {
+    /// Direct messages with more than two participants are group direct messages
+    pub fn is_group_direct_message(&self) -> bool {
+        self.usernames.len() > 2
+    }
+}
+
+/// Participants of a direct message channel that were looked up
+struct CachedParticipants {
+    usernames: Vec<String>,
+    looked_up_at: Instant,
+}
+
+/// Finds the participants of Rocket.Chat direct message channels.
+pub struct DirectMessageHandler<'a> {
+    /// Application service configuration
+    pub config: &'a Config,
+    /// SQL database connection
+    pub connection: &'a SqliteConnection,
+    /// Logger context
+    pub logger: &'a Logger,
+}
+
+impl<'a> DirectMessageHandler<'a> {
+    /// Remember the participants of a direct message channel, so that the channel doesn't have to
+    /// be looked up when the first message is received.
+    pub fn cache_participants(rocketchat_url: &str, direct_message_channel: &Channel) {
+        if direct_message_channel.usernames.is_empty() {
+            return;
+        }
+
+        let key = (rocketchat_url.to_string(), direct_message_channel.id.clone());
+        let cached_participants = CachedParticipants {
+            usernames: direct_message_channel.usernames.clone(),
+            looked_up_at: Instant::now(),
+        };
+        let mut participants = DIRECT_MESSAGE_PARTICIPANTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        if !participants.contains_key(&key) && participants.len() >= MAX_CACHED_CHANNELS {
+            let oldest_key = participants.iter().min_by_key(|&(_, cached)| cached.looked_up_at).map(|(key, _)| key.clone());
+            if let Some(oldest_key) = oldest_key {
+                participants.remove(&oldest_key);
+            }
+        }
+        participants.insert(key, cached_participants);
+    }
+
+    /// Get the participants of a direct message channel. Returns `None` if the channel is not a
+    /// direct message channel of a logged in user. The participants of a group direct message are
+    /// looked up again when the refresh interval passed or when the sender is not a known
+    /// participant, because users can be added to group direct messages.
+    pub fn participants(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_channel_id: &str,
+        sender_rocketchat_user_id: &str,
+        sender_rocketchat_username: &str,
+    ) -> Result<Option<DirectMessageParticipants>> {
+        let key = (rocketchat_server.rocketchat_url.clone(), rocketchat_channel_id.to_string());
+
+        // scope to release the mutexes before the channel is looked up
+        let cached_usernames = {
+            let participants = DIRECT_MESSAGE_PARTICIPANTS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            match participants.get(&key) {
+                Some(cached_participants) => {
+                    let refresh_interval = Duration::from_secs(GROUP_DIRECT_MESSAGE_REFRESH_INTERVAL_SECONDS);
+                    let is_expired = cached_participants.usernames.len() > 2 &&
+                        cached_participants.looked_up_at.elapsed() >= refresh_interval;
+                    let is_known_sender =
+                        cached_participants.usernames.iter().any(|username| username == sender_rocketchat_username);
+                    if !is_expired && is_known_sender {
+                        let participants = DirectMessageParticipants {
+                            usernames: cached_participants.usernames.clone(),
+                            refreshed: false,
+                        };
+                        return Ok(Some(participants));
+                    }
+
+                    Some(cached_participants.usernames.clone())
+                }
+                None => {
+                    let mut unknown_channels = UNKNOWN_CHANNELS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                    remove_expired_unknown_channels(&mut unknown_channels);
+                    if unknown_channels.contains_key(&key) {
+                        debug!(self.logger, "Channel {} was looked up recently, it's no direct message", rocketchat_channel_id);
+                        return Ok(None);
+                    }
+
+                    None
+                }
+            }
+        };
+
+        let looked_up_usernames = self.lookup_participants(
+            rocketchat_server,
+            rocketchat_channel_id,
+            sender_rocketchat_user_id,
+            cached_usernames.as_ref().map(|usernames| usernames.as_slice()).unwrap_or(&[]),
+        )?;
+        let participants = match looked_up_usernames {
+            Some(usernames) => {
+                Some(DirectMessageParticipants {
+                    usernames: usernames,
+                    refreshed: true,
+                })
+            }
+            // the cached participants are still used when the channel could not be looked up again
+            None => {
+                cached_usernames.map(|usernames| {
+                    DirectMessageParticipants {
+                        usernames: usernames,
+                        refreshed: false,
+                    }
+                })
+            }
+        };
+        Ok(participants)
+    }
+
+    /// Get the users that are logged in on the Rocket.Chat server and participate in a direct
+    /// message channel, the sender of the message is excluded.
+    pub fn recipients(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        participants: &DirectMessageParticipants,
+        sender_rocketchat_user_id: &str,
+    ) -> Result<Vec<UserOnRocketchatServer>> {
+        let recipients = rocketchat_server
+            .logged_in_users_on_rocketchat_server(self.connection)?
+            .into_iter()
+            .filter(|user| user.rocketchat_user_id.clone().unwrap_or_default() != sender_rocketchat_user_id)
+            .filter(|user| participants.usernames.iter().any(|username| Some(username) == user.rocketchat_username.as_ref()))
+            .collect();
+        Ok(recipients)
+    }
+
+    // The message from Rocket.Chat neither contains the type nor the participants of the channel,
+    // so both are looked up with a single rooms.info call. Only the participants can see a direct
+    // message channel, so only the logged in users that are known to participate are asked: the
+    // sender, the user whose ID forms the channel ID together with the ID of the sender (the ID of
+    // a direct message between two users is built from their IDs) and the cached participants,
+    // which include the direct messages users had when they logged in. The first answer decides
+    // whether the channel is a direct message channel.
+    fn lookup_participants(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_channel_id: &str,
+        sender_rocketchat_user_id: &str,
+        cached_usernames: &[String],
+    ) -> Result<Option<Vec<String>>> {
+        let mut users_on_rocketchat_server: Vec<UserOnRocketchatServer> = rocketchat_server
+            .logged_in_users_on_rocketchat_server(self.connection)?
+            .into_iter()
+            .filter(|user| {
+                let rocketchat_user_id = user.rocketchat_user_id.clone().unwrap_or_default();
+                let rocketchat_username = user.rocketchat_username.clone().unwrap_or_default();
+                rocketchat_user_id == sender_rocketchat_user_id ||
+                    rocketchat_channel_id == format!("{}{}", sender_rocketchat_user_id, rocketchat_user_id) ||
+                    rocketchat_channel_id == format!("{}{}", rocketchat_user_id, sender_rocketchat_user_id) ||
+                    cached_usernames.iter().any(|username| username == &rocketchat_username)
+            })
+            .collect();
+        users_on_rocketchat_server
+            .sort_by_key(|user| user.rocketchat_user_id.clone().unwrap_or_default() != sender_rocketchat_user_id);
+
+        let mut room = None;
+        for user_on_rocketchat_server in users_on_rocketchat_server {
+            let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+                .with_credentials(
+                    user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
+                    user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+                );
+
+            match rocketchat_api.rooms_info(rocketchat_channel_id) {
+                Ok(channel) => {
+                    room = Some(channel);
+                    break;
+                }
+                Err(err) => {
+                    info!(
+                        self.logger,
+                        "Could not get the room {} with the credentials of user {}",
+                        rocketchat_channel_id,
+                        user_on_rocketchat_server.matrix_user_id
+                    );
+                    log::log_info(self.logger, &err);
+                }
+            }
+        }
+
+        match room {
+            Some(ref channel) if channel.channel_type == DIRECT_MESSAGE_CHANNEL_TYPE => {
+                debug!(self.logger, "Found direct message channel {}", rocketchat_channel_id);
+                DirectMessageHandler::cache_participants(&rocketchat_server.rocketchat_url, channel);
+                Ok(Some(channel.usernames.clone()))
+            }
+            _ => {
+                debug!(self.logger, "Channel {} is not a direct message channel of a logged in user", rocketchat_channel_id);
+                let key = (rocketchat_server.rocketchat_url.clone(), rocketchat_channel_id.to_string());
+                let mut unknown_channels = UNKNOWN_CHANNELS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+                remove_expired_unknown_channels(&mut unknown_channels);
+                if unknown_channels.len() < MAX_CACHED_CHANNELS {
+                    unknown_channels.insert(key, Instant::now());
+                }
+
+                Ok(None)
+            }
+        }
+    }
+}
+
+fn remove_expired_unknown_channels(unknown_channels: &mut HashMap<(String, String), Instant>) {
+    let interval = Duration::from_secs(UNKNOWN_CHANNEL_LOOKUP_INTERVAL_SECONDS);
+    unknown_channels.retain(|_, looked_up_at| looked_up_at.elapsed() < interval);
+}
@@ -1,9 +1,11 @@
-use std::time::{SystemTime, UNIX_EPOCH};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use diesel::Connection;
 use diesel::sqlite::SqliteConnection;
 use slog::Logger;
-use ruma_identifiers::RoomId;
+use ruma_identifiers::{RoomId, UserId};
 
 use i18n::*;
 use api::{MatrixApi, RocketchatApi};
@@ -12,9 +14,18 @@ use config::Config;
 use db::{RocketchatServer, Room, UserOnRocketchatServer};
 use errors::*;
 use handlers::events::RoomHandler;
-use handlers::rocketchat::VirtualUserHandler;
+use handlers::rocketchat::{DirectMessageHandler, DirectMessageParticipants, VirtualUserHandler};
+use log;
 
 const RESEND_THRESHOLD_IN_SECONDS: i64 = 3;
+/// Number of seconds before the members of a bridged channel are synchronized with the room again
+pub const MEMBERSHIP_SYNCHRONIZATION_INTERVAL_SECONDS: u64 = 300;
+
+lazy_static! {
+    /// Time of the last membership synchronization of the bridged channels, indexed by the
+    /// Rocket.Chat server URL and the channel ID.
+    static ref MEMBERSHIP_SYNCHRONIZATIONS: Mutex<HashMap<(String, String), Instant>> = Mutex::new(HashMap::new());
+}
 
 /// Forwards messages from Rocket.Chat to Matrix
 pub struct Forwarder<'a> {
@@ -31,6 +42,10 @@ pub struct Forwarder<'a> {
 impl<'a> Forwarder<'a> {
     /// Send a message to the Matrix channel.
     pub fn send(&self, rocketchat_server: &RocketchatServer, message: &Message) -> Result<()> {
+        if message.is_membership_change() || message.is_role_change() {
+            return self.handle_membership_change(rocketchat_server, message);
+        }
+
         let virtual_user_handler = VirtualUserHandler {
             config: self.config,
             connection: self.connection,
@@ -46,30 +61,38 @@ impl<'a> Forwarder<'a> {
             )
         })?;
 
-        if !self.is_sendable_message(&user_on_rocketchat_server)? {
-            debug!(
-                self.logger,
-                "Skipping message, because the message was just posted by the user Matrix and echoed back from Rocket.Chat"
-            );
-            return Ok(());
-        }
-
-        let is_direct_message_room = message.channel_id.contains(&message.user_id);
-        let channel_id = if is_direct_message_room {
-            format!("{}#dm", message.channel_id)
-        } else {
-            message.channel_id.clone()
-        };
+        let is_sendable_message = self.is_sendable_message(&user_on_rocketchat_server)?;
 
         let matrix_room_id = match Room::matrix_id_from_rocketchat_channel_id(
             self.config,
             self.matrix_api,
             &rocketchat_server.id,
-            &channel_id,
+            &message.channel_id,
         )? {
-            Some(matrix_room_id) => matrix_room_id,
+            Some(matrix_room_id) => {
+                if let Err(err) =
+                    self.synchronize_members_periodically(rocketchat_server, &message.channel_id, matrix_room_id.clone())
+                {
+                    info!(self.logger, "Could not synchronize the members of room {}", matrix_room_id);
+                    log::log_info(self.logger, &err);
+                }
+
+                if is_sendable_message {
+                    let invited_user_id = user_on_rocketchat_server.matrix_user_id.clone();
+                    let inviting_user_id = self.config.matrix_bot_user_id()?;
+                    virtual_user_handler.add_to_room(invited_user_id, inviting_user_id, matrix_room_id.clone())?;
+                }
+
+                matrix_room_id
+            }
             None => {
-                match self.auto_bridge_direct_message_channel(&virtual_user_handler, rocketchat_server, message)? {
+                match self.direct_message_room(
+                    &virtual_user_handler,
+                    rocketchat_server,
+                    message,
+                    &user_on_rocketchat_server,
+                    is_sendable_message,
+                )? {
                     Some(matrix_room_id) => matrix_room_id,
                     None => {
                         debug!(
@@ -83,35 +106,13 @@ impl<'a> Forwarder<'a> {
             }
         };
 
-        if is_direct_message_room {
-            if Room::direct_message_room_matrix_user(
-                self.config,
-                self.matrix_api,
-                matrix_room_id.clone(),
-                Some(user_on_rocketchat_server.matrix_user_id.clone()),
-            )?
-                .is_none()
-            {
-                match self.find_matching_user_for_direct_message(rocketchat_server, message)? {
-                    Some(other_user) => {
-                        let invited_user_id = other_user.matrix_user_id.clone();
-                        let inviting_user_id = user_on_rocketchat_server.matrix_user_id.clone();
-                        virtual_user_handler.add_to_room(invited_user_id.clone(), inviting_user_id, matrix_room_id.clone())?;
-                    }
-                    None => {
-                        debug!(
-                            self.logger,
-                            "Ignoring message, because not matching user for the direct chat message was found"
-                        );
-                        return Ok(());
-                    }
-                }
-            };
-        } else {
-            let invited_user_id = user_on_rocketchat_server.matrix_user_id.clone();
-            let inviting_user_id = self.config.matrix_bot_user_id()?;
-            virtual_user_handler.add_to_room(invited_user_id, inviting_user_id, matrix_room_id.clone())?;
-        };
+        if !is_sendable_message {
+            debug!(
+                self.logger,
+                "Skipping message, because the message was just posted by the user Matrix and echoed back from Rocket.Chat"
+            );
+            return Ok(());
+        }
 
         if Some(message.user_name.clone()) != user_on_rocketchat_server.rocketchat_username.clone() {
             self.connection.transaction(|| {
@@ -141,98 +142,290 @@ impl<'a> Forwarder<'a> {
         }
     }
 
-    fn auto_bridge_direct_message_channel(
+    // Rocket.Chat sends a system message when a user joins or leaves a channel or when the roles of
+    // a user change. The members and their power levels are synchronized with the room right away,
+    // the message itself is not forwarded.
+    fn handle_membership_change(&self, rocketchat_server: &RocketchatServer, message: &Message) -> Result<()> {
+        match Room::matrix_id_from_rocketchat_channel_id(
+            self.config,
+            self.matrix_api,
+            &rocketchat_server.id,
+            &message.channel_id,
+        )? {
+            Some(matrix_room_id) => self.synchronize_members(rocketchat_server, &message.channel_id, matrix_room_id),
+            None => {
+                debug!(self.logger, "Ignoring membership change in channel `{}`, it's not bridged", message.channel_id);
+                Ok(())
+            }
+        }
+    }
+
+    // Not every Rocket.Chat server sends the join and leave system messages, so the members of the
+    // channel are also synchronized with the room when a message is received and the
+    // synchronization interval passed.
+    fn synchronize_members_periodically(
         &self,
-        virtual_user_handler: &VirtualUserHandler,
         rocketchat_server: &RocketchatServer,
-        message: &Message,
-    ) -> Result<Option<RoomId>> {
-        debug!(
-            self.logger,
-            "Got a message for a room that is not bridged yet (channel_id `{}`), checking if it's a direct message",
-            &message.channel_id
-        );
+        channel_id: &str,
+        matrix_room_id: RoomId,
+    ) -> Result<()> {
+        let key = (rocketchat_server.rocketchat_url.clone(), channel_id.to_string());
+        let is_synchronized = {
+            let synchronizations = MEMBERSHIP_SYNCHRONIZATIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            let interval = Duration::from_secs(MEMBERSHIP_SYNCHRONIZATION_INTERVAL_SECONDS);
+            synchronizations.get(&key).map(|synchronized_at| synchronized_at.elapsed() < interval).unwrap_or(false)
+        };
+
+        if is_synchronized {
+            return Ok(());
+        }
+
+        self.synchronize_members(rocketchat_server, channel_id, matrix_room_id)
+    }
+
+    // The channel is looked up with the credentials of a logged in user that is in the room.
+    fn synchronize_members(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        channel_id: &str,
+        matrix_room_id: RoomId,
+    ) -> Result<()> {
+        // scope to release the mutex before the members are synchronized
+        {
+            let key = (rocketchat_server.rocketchat_url.clone(), channel_id.to_string());
+            let mut synchronizations = MEMBERSHIP_SYNCHRONIZATIONS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            synchronizations.insert(key, Instant::now());
+        }
 
-        let user_on_rocketchat_server = match self.find_matching_user_for_direct_message(rocketchat_server, message)? {
+        let matrix_user_ids = Room::user_ids(self.matrix_api, matrix_room_id.clone(), None)?;
+        let logged_in_user_in_room = rocketchat_server
+            .logged_in_users_on_rocketchat_server(self.connection)?
+            .into_iter()
+            .find(|user| matrix_user_ids.iter().any(|id| id == &user.matrix_user_id));
+        let user_on_rocketchat_server = match logged_in_user_in_room {
             Some(user_on_rocketchat_server) => user_on_rocketchat_server,
             None => {
-                debug!(self.logger, "No matching user found. Not bridging channel {} automatically", message.channel_id);
-                return Ok(None);
+                debug!(self.logger, "Not synchronizing room {}, because no logged in user is in the room", matrix_room_id);
+                return Ok(());
             }
         };
 
         let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
             .with_credentials(
                 user_on_rocketchat_server.rocketchat_user_id.clone().unwrap_or_default(),
-                user_on_rocketchat_server.rocketchat_auth_token.clone().unwrap_or_default(),
+                user_on_rocketchat_server.decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
             );
+        let channel = match rocketchat_api.channels_info(channel_id) {
+            Ok(channel) => channel,
+            // the channel is a private group
+            Err(_) => rocketchat_api.groups_info(channel_id)?,
+        };
 
-        if let Some(direct_message_channel) =
-            rocketchat_api.direct_messages_list()?.iter().find(|dm| dm.id == message.channel_id)
-        {
-            let direct_message_sender = virtual_user_handler.find_or_register(
-                rocketchat_server.id.clone(),
-                message.user_id.clone(),
-                message.user_name.clone(),
-            )?;
-            let room_handler = RoomHandler::new(self.config, self.connection, self.logger, self.matrix_api);
-
-            let direct_message_receiver = user_on_rocketchat_server.user(self.connection)?;
-            let room_display_name_suffix =
-                t!(["defaults", "direct_message_room_display_name_suffix"]).l(&direct_message_receiver.language);
-            let room_display_name = format!("{} {}", message.user_name, room_display_name_suffix);
-            let dm_channel_id = format!("{}#dm", direct_message_channel.id.clone());
-            let matrix_room_id = room_handler.create_room(
-                dm_channel_id,
-                rocketchat_server.id.clone(),
-                direct_message_sender.matrix_user_id.clone(),
-                user_on_rocketchat_server.matrix_user_id.clone(),
-                Some(room_display_name),
-            )?;
+        let room_handler = RoomHandler::new(self.config, self.connection, self.logger, self.matrix_api);
+        room_handler.synchronize_virtual_users(rocketchat_api.as_ref(), &channel, rocketchat_server.id.clone(), matrix_room_id)
+    }
 
-            // invite the bot user into the direct message room to be able to read the room members
-            // the bot will leave as soon as the AS gets the join event
-            let invitee_id = self.config.matrix_bot_user_id()?;
-            self.matrix_api.invite(matrix_room_id.clone(), invitee_id.clone(), direct_message_sender.matrix_user_id.clone())?;
-            debug!(self.logger, "Direct message room {} successfully created", &matrix_room_id);
+    // Messages from Rocket.Chat don't contain the recipients, so the participants of the channel
+    // are looked up to find out if it's a direct message channel. The participants that are logged
+    // in on the bridge are added to the room, this includes all of them when several participants
+    // use the bridge. A message that was sent from Matrix isn't forwarded again, but the other
+    // participants are still added to the room to be able to read it.
+    fn direct_message_room(
+        &self,
+        virtual_user_handler: &VirtualUserHandler,
+        rocketchat_server: &RocketchatServer,
+        message: &Message,
+        sender: &UserOnRocketchatServer,
+        is_sendable_message: bool,
+    ) -> Result<Option<RoomId>> {
+        let direct_message_handler = DirectMessageHandler {
+            config: self.config,
+            connection: self.connection,
+            logger: self.logger,
+        };
+        let participants = match direct_message_handler.participants(
+            rocketchat_server,
+            &message.channel_id,
+            &message.user_id,
+            &message.user_name,
+        )? {
+            Some(participants) => participants,
+            None => return Ok(None),
+        };
+        let recipients = direct_message_handler.recipients(rocketchat_server, &participants, &message.user_id)?;
+        if recipients.is_empty() {
+            debug!(self.logger, "No user of the application service participates in the channel {}", message.channel_id);
+            return Ok(None);
+        }
 
-            Ok(Some(matrix_room_id))
+        let dm_channel_id = format!("{}#dm", message.channel_id);
+        match Room::matrix_id_from_rocketchat_channel_id(self.config, self.matrix_api, &rocketchat_server.id, &dm_channel_id)? {
+            Some(matrix_room_id) => {
+                self.add_to_direct_message_room(
+                    virtual_user_handler,
+                    rocketchat_server,
+                    matrix_room_id.clone(),
+                    sender,
+                    &participants,
+                    &recipients,
+                    is_sendable_message,
+                )?;
+                Ok(Some(matrix_room_id))
+            }
+            None if is_sendable_message => {
+                let matrix_room_id = self.auto_bridge_direct_message_channel(
+                    virtual_user_handler,
+                    rocketchat_server,
+                    message,
+                    sender,
+                    &participants,
+                    &recipients,
+                )?;
+                Ok(Some(matrix_room_id))
+            }
+            None => Ok(None),
+        }
+    }
+
+    fn add_to_direct_message_room(
+        &self,
+        virtual_user_handler: &VirtualUserHandler,
+        rocketchat_server: &RocketchatServer,
+        matrix_room_id: RoomId,
+        sender: &UserOnRocketchatServer,
+        participants: &DirectMessageParticipants,
+        recipients: &[UserOnRocketchatServer],
+        is_sendable_message: bool,
+    ) -> Result<()> {
+        // rooms that were created by the application service are created by the virtual user of a
+        // participant, rooms that were started on Matrix are created by the Matrix user and the
+        // virtual user of the other participant is already in the room.
+        let room_creator_id = self.matrix_api.get_room_creator(matrix_room_id.clone())?;
+        let inviting_user_id = if room_creator_id.to_string().starts_with(&format!("@{}", self.config.sender_localpart)) {
+            room_creator_id
+        } else if is_sendable_message {
+            sender.matrix_user_id.clone()
         } else {
-            debug!(
-                self.logger,
-                "User {} matched the channel_id, but does not have access to the channel. \
-                   Not bridging channel {} automatically",
-                user_on_rocketchat_server.matrix_user_id,
-                message.channel_id
-            );
-            Ok(None)
+            // the message was sent by the Matrix user who started the room, so there is nobody
+            // who has to be invited
+            return Ok(());
+        };
+
+        if is_sendable_message {
+            virtual_user_handler.add_to_room(sender.matrix_user_id.clone(), inviting_user_id.clone(), matrix_room_id.clone())?;
+        }
+
+        for recipient in recipients {
+            let invited_user_id = recipient.matrix_user_id.clone();
+            virtual_user_handler.add_to_room(invited_user_id, inviting_user_id.clone(), matrix_room_id.clone())?;
         }
+
+        // users that were added to a group direct message since it was looked up the last time
+        // are added to the room as well
+        if participants.is_group_direct_message() && participants.refreshed {
+            self.add_virtual_users_to_group_direct_message_room(
+                virtual_user_handler,
+                rocketchat_server,
+                matrix_room_id,
+                inviting_user_id,
+                sender,
+                participants,
+                recipients,
+            )?;
+        }
+
+        Ok(())
     }
 
-    // this is a pretty hacky way to find a Matrix user that could be the recipient for this
-    // message. The message itself doesn't contain any information about the recipient so the
-    // channel ID has to be checked against all users that use the application service and are
-    // logged in on the sending Rocket.Chat server, because direct message channel IDs consist of
-    // the `user_id`s of the two participants.
-    fn find_matching_user_for_direct_message(
+    fn auto_bridge_direct_message_channel(
         &self,
+        virtual_user_handler: &VirtualUserHandler,
         rocketchat_server: &RocketchatServer,
         message: &Message,
-    ) -> Result<Option<UserOnRocketchatServer>> {
-        for user_on_rocketchat_server in rocketchat_server.logged_in_users_on_rocketchat_server(self.connection)? {
-            if let Some(rocketchat_user_id) = user_on_rocketchat_server.rocketchat_user_id.clone() {
-                if message.channel_id.contains(&rocketchat_user_id) {
-                    debug!(
-                        self.logger,
-                        "Matching user with rocketchat_user_id `{}` for channel_id `{}` found.",
-                        rocketchat_user_id,
-                        &message.channel_id
-                    );
-                    return Ok(Some(user_on_rocketchat_server));
-                }
-            }
+        sender: &UserOnRocketchatServer,
+        participants: &DirectMessageParticipants,
+        recipients: &[UserOnRocketchatServer],
+    ) -> Result<RoomId> {
+        debug!(
+            self.logger,
+            "Got a message for the direct message channel `{}` that is not bridged yet, bridging it automatically",
+            &message.channel_id
+        );
+
+        // the room is named after the other participants from the perspective of the first
+        // recipient, for a direct message between two users that's the sender of the message
+        let direct_message_receiver = recipients[0].user(self.connection)?;
+        let receiver_username = recipients[0].rocketchat_username.clone().unwrap_or_default();
+        let other_usernames: Vec<String> =
+            participants.usernames.iter().filter(|username| *username != &receiver_username).cloned().collect();
+        let room_display_name_suffix =
+            t!(["defaults", "direct_message_room_display_name_suffix"]).l(&direct_message_receiver.language);
+        let room_display_name = format!("{} {}", other_usernames.join(", "), room_display_name_suffix);
+
+        let room_handler = RoomHandler::new(self.config, self.connection, self.logger, self.matrix_api);
+        let matrix_room_id = room_handler.create_direct_message_room(
+            rocketchat_server.id.clone(),
+            &message.channel_id,
+            sender.matrix_user_id.clone(),
+            recipients[0].matrix_user_id.clone(),
+            room_display_name,
+        )?;
+
+        for recipient in recipients.iter().skip(1) {
+            let invited_user_id = recipient.matrix_user_id.clone();
+            virtual_user_handler.add_to_room(invited_user_id, sender.matrix_user_id.clone(), matrix_room_id.clone())?;
+        }
+
+        if participants.is_group_direct_message() {
+            self.add_virtual_users_to_group_direct_message_room(
+                virtual_user_handler,
+                rocketchat_server,
+                matrix_room_id.clone(),
+                sender.matrix_user_id.clone(),
+                sender,
+                participants,
+                recipients,
+            )?;
+        }
+
+        Ok(matrix_room_id)
+    }
+
+    // Every participant of a group direct message is represented by a virtual user in the room, so
+    // that the Matrix users can see who is part of the conversation. The users are looked up with
+    // the credentials of a recipient, because the message only contains the usernames.
+    fn add_virtual_users_to_group_direct_message_room(
+        &self,
+        virtual_user_handler: &VirtualUserHandler,
+        rocketchat_server: &RocketchatServer,
+        matrix_room_id: RoomId,
+        inviting_user_id: UserId,
+        sender: &UserOnRocketchatServer,
+        participants: &DirectMessageParticipants,
+        recipients: &[UserOnRocketchatServer],
+    ) -> Result<()> {
+        debug!(self.logger, "Adding the participants of the group direct message to room {}", matrix_room_id);
+
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?
+            .with_credentials(
+                recipients[0].rocketchat_user_id.clone().unwrap_or_default(),
+                recipients[0].decrypted_auth_token(self.config.database_encryption_key()?.as_ref())?,
+            );
+
+        // the sender is already handled and participants that use the application service are
+        // in the room with their Matrix user
+        let bridged_usernames: Vec<String> =
+            recipients.iter().chain(Some(sender)).filter_map(|user| user.rocketchat_username.clone()).collect();
+        for username in participants.usernames.iter().filter(|username| !bridged_usernames.contains(username)) {
+            let rocketchat_user = rocketchat_api.users_info(username)?;
+            let virtual_user = virtual_user_handler.find_or_register(
+                rocketchat_server.id.clone(),
+                rocketchat_user.id,
+                rocketchat_user.username,
+            )?;
+            virtual_user_handler.add_to_room(virtual_user.matrix_user_id, inviting_user_id.clone(), matrix_room_id.clone())?;
         }
 
-        Ok(None)
+        Ok(())
     }
 }
@@ -1,12 +1,35 @@
+use std::collections::HashMap;
+use std::sync::{Mutex, ONCE_INIT, Once};
+use std::thread;
+use std::time::{Duration, Instant};
+
 use diesel::sqlite::SqliteConnection;
 use ruma_identifiers::{RoomId, UserId};
 use slog::Logger;
 
 use api::{MatrixApi, RocketchatApi};
 use config::Config;
-use db::{RocketchatServer, User, UserOnRocketchatServer};
+use db::{QueuedMessage, RocketchatServer, Room, User, UserOnRocketchatServer};
 use errors::*;
 use handlers::events::CommandHandler;
+use handlers::rocketchat::DirectMessageHandler;
+use i18n::*;
+use log;
+
+/// Number of minutes a login waits for the two-factor authentication code before it is discarded
+pub const PENDING_LOGIN_TIMEOUT_MINUTES: u64 = 5;
+/// Number of seconds between two runs of the cleanup that removes the expired pending logins
+const PENDING_LOGIN_CLEANUP_INTERVAL_SECONDS: u64 = 30;
+
+/// Starts the cleanup of the expired pending logins when the first login waits for a code
+static PENDING_LOGIN_CLEANUP: Once = ONCE_INIT;
+
+lazy_static! {
+    /// Logins that wait for a two-factor authentication code, indexed by the Matrix user ID and
+    /// the Rocket.Chat server URL. The credentials are only kept in memory and never stored in
+    /// the database, they are removed when the login times out.
+    static ref PENDING_LOGINS: Mutex<HashMap<(String, String), PendingLogin>> = Mutex::new(HashMap::new());
+}
 
 /// Provides helper method to login a user on the Rocket.Chat server.
 pub struct Login<'a> {
@@ -22,7 +45,7 @@ pub struct Login<'a> {
 
 /// Credentials to perform a login on the Rocket.Chat server. The `matrix_user_id` is used to find
 /// the corresponding matrix user.
-#[derive(Serialize, Deserialize)]
+#[derive(Clone, Serialize, Deserialize)]
 pub struct Credentials {
     /// The users unique id on the Matrix homeserver
     pub matrix_user_id: UserId,
@@ -32,6 +55,31 @@ pub struct Credentials {
     pub password: String,
     /// The URL of the Rocket.Chat server on which the user wants to login
     pub rocketchat_url: String,
+    /// The two-factor authentication code, only needed when two-factor authentication is enabled
+    /// for the user on the Rocket.Chat server
+    #[serde(default)]
+    pub totp_code: Option<String>,
+}
+
+/// A login that waits for the user to send the two-factor authentication code
+struct PendingLogin {
+    credentials: Credentials,
+    started_at: Instant,
+}
+
+/// Credentials to login with a personal access token, which can be created in the account settings
+/// on the Rocket.Chat server. This allows users without a local Rocket.Chat password (SSO/LDAP) to
+/// login.
+#[derive(Serialize, Deserialize)]
+pub struct AccessTokenCredentials {
+    /// The users unique id on the Matrix homeserver
+    pub matrix_user_id: UserId,
+    /// The users unique id on the Rocket.Chat server
+    pub rocketchat_user_id: String,
+    /// The personal access token that was created on the Rocket.Chat server
+    pub access_token: String,
+    /// The URL of the Rocket.Chat server on which the user wants to login
+    pub rocketchat_url: String,
 }
 
 impl<'a> Login<'a> {
@@ -43,24 +91,125 @@ impl<'a> Login<'a> {
         credentials: &Credentials,
         rocketchat_server: &RocketchatServer,
         admin_room_id: Option<RoomId>,
+    ) -> Result<()> {
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?;
+        let totp_code = credentials.totp_code.as_ref().map(|totp_code| totp_code.as_ref());
+        let (rocketchat_user_id, rocketchat_auth_token) =
+            match rocketchat_api.login(&credentials.rocketchat_username, &credentials.password, totp_code) {
+                Ok(login_response) => login_response,
+                Err(err) => {
+                    match *err.error_chain {
+                        // the login is finished when the user sends the code in the admin room
+                        ErrorKind::TwoFactorAuthenticationCodeRequired if admin_room_id.is_some() => {
+                            add_pending_login(credentials.clone());
+                            bail_error!(
+                                ErrorKind::TwoFactorAuthenticationCodeRequired,
+                                t!(["admin_room", "two_factor_authentication_code_required"])
+                                    .with_vars(vec![("timeout", PENDING_LOGIN_TIMEOUT_MINUTES.to_string())])
+                            );
+                        }
+                        _ => return Err(err),
+                    }
+                }
+            };
+
+        self.store_credentials(
+            &credentials.matrix_user_id,
+            rocketchat_server,
+            rocketchat_api,
+            rocketchat_user_id,
+            rocketchat_auth_token,
+            admin_room_id,
+        )
+    }
+
+    /// Finish a login that waits for a two-factor authentication code. The user can try another
+    /// code if the code is invalid, as long as the pending login didn't time out.
+    pub fn call_with_totp_code(
+        &self,
+        matrix_user_id: &UserId,
+        totp_code: &str,
+        rocketchat_server: &RocketchatServer,
+        admin_room_id: Option<RoomId>,
+    ) -> Result<()> {
+        let mut pending_login = match take_pending_login(matrix_user_id, &rocketchat_server.rocketchat_url) {
+            Some(pending_login) => pending_login,
+            None => {
+                bail_error!(
+                    ErrorKind::NoPendingLogin(matrix_user_id.to_string()),
+                    t!(["errors", "no_pending_login"]).with_vars(vec![
+                        ("timeout", PENDING_LOGIN_TIMEOUT_MINUTES.to_string()),
+                    ])
+                );
+            }
+        };
+
+        pending_login.credentials.totp_code = Some(totp_code.to_string());
+        if let Err(err) = self.call(&pending_login.credentials, rocketchat_server, admin_room_id) {
+            if let ErrorKind::InvalidTwoFactorAuthenticationCode = *err.error_chain {
+                restore_pending_login(pending_login);
+            }
+
+            return Err(err);
+        }
+
+        Ok(())
+    }
+
+    /// Login with a personal access token. The token is verified by querying the username of the
+    /// user on the Rocket.Chat server.
+    /// Stores the credentials if the token is valid.
+    /// Returns an error if the token is not accepted by the Rocket.Chat server.
+    pub fn call_with_access_token(
+        &self,
+        credentials: &AccessTokenCredentials,
+        rocketchat_server: &RocketchatServer,
+        admin_room_id: Option<RoomId>,
+    ) -> Result<()> {
+        let rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?;
+
+        self.store_credentials(
+            &credentials.matrix_user_id,
+            rocketchat_server,
+            rocketchat_api,
+            credentials.rocketchat_user_id.clone(),
+            credentials.access_token.clone(),
+            admin_room_id,
+        )
+    }
+
+    fn store_credentials(
+        &self,
+        matrix_user_id: &UserId,
+        rocketchat_server: &RocketchatServer,
+        rocketchat_api: Box<RocketchatApi>,
+        rocketchat_user_id: String,
+        rocketchat_auth_token: String,
+        admin_room_id: Option<RoomId>,
     ) -> Result<()> {
         let mut user_on_rocketchat_server =
-            UserOnRocketchatServer::find(self.connection, &credentials.matrix_user_id, rocketchat_server.id.clone())?;
-        let user = User::find(self.connection, &credentials.matrix_user_id)?;
+            UserOnRocketchatServer::find(self.connection, matrix_user_id, rocketchat_server.id.clone())?;
+        let mut user = User::find(self.connection, matrix_user_id)?;
 
-        let mut rocketchat_api = RocketchatApi::new(rocketchat_server.rocketchat_url.clone(), self.logger.clone())?;
+        // the username is queried before the credentials are stored, this way invalid credentials
+        // are never stored
+        let rocketchat_api = rocketchat_api.with_credentials(rocketchat_user_id.clone(), rocketchat_auth_token.clone());
+        let username = rocketchat_api.current_username()?;
 
-        let (rocketchat_user_id, rocketchat_auth_token) =
-            rocketchat_api.login(&credentials.rocketchat_username, &credentials.password)?;
+        let encryption_key = self.config.database_encryption_key()?;
         user_on_rocketchat_server.set_credentials(
             self.connection,
-            Some(rocketchat_user_id.clone()),
-            Some(rocketchat_auth_token.clone()),
+            encryption_key.as_ref(),
+            Some(rocketchat_user_id),
+            Some(rocketchat_auth_token),
         )?;
-
-        rocketchat_api = rocketchat_api.with_credentials(rocketchat_user_id, rocketchat_auth_token);
-        let username = rocketchat_api.current_username()?;
         user_on_rocketchat_server.set_rocketchat_username(self.connection, Some(username.clone()))?;
+        if admin_room_id.is_some() {
+            user_on_rocketchat_server.set_admin_room_id(self.connection, admin_room_id.clone())?;
+        }
+
+        self.deliver_queued_messages(&user_on_rocketchat_server, &mut user, rocketchat_api.as_ref())?;
+        self.cache_direct_message_participants(rocketchat_server, &username, rocketchat_api.as_ref());
 
         if let Some(matrix_room_id) = admin_room_id {
             let bot_matrix_user_id = self.config.matrix_bot_user_id()?;
@@ -77,8 +226,126 @@ impl<'a> Login<'a> {
         Ok(info!(
             self.logger,
             "Successfully executed login command for user {} on Rocket.Chat server {}",
-            credentials.rocketchat_username,
+            username,
             rocketchat_server.rocketchat_url
         ))
     }
+
+    /// Cache the participants of the direct message channels of the user. Direct messages are only
+    /// looked up with the credentials of users that are known to participate, so this makes the
+    /// channels known before a user who doesn't use the bridge sends a message. A failed lookup
+    /// doesn't fail the login.
+    fn cache_direct_message_participants(
+        &self,
+        rocketchat_server: &RocketchatServer,
+        username: &str,
+        rocketchat_api: &RocketchatApi,
+    ) {
+        match rocketchat_api.direct_messages_list() {
+            Ok(direct_message_channels) => {
+                for direct_message_channel in direct_message_channels {
+                    DirectMessageHandler::cache_participants(&rocketchat_server.rocketchat_url, &direct_message_channel);
+                }
+            }
+            Err(err) => {
+                info!(self.logger, "Could not get the direct message channels of user {}", username);
+                log::log_info(self.logger, &err);
+            }
+        }
+    }
+
+    /// Send the messages that were queued while the credentials of the user were invalid. Messages
+    /// for channels that were unbridged in the meantime are discarded. A failed delivery doesn't
+    /// fail the login, the remaining messages are kept for the next login.
+    fn deliver_queued_messages(
+        &self,
+        user_on_rocketchat_server: &UserOnRocketchatServer,
+        user: &mut User,
+        rocketchat_api: &RocketchatApi,
+    ) -> Result<()> {
+        let queued_messages = QueuedMessage::find_by_user(
+            self.connection,
+            &user_on_rocketchat_server.matrix_user_id,
+            user_on_rocketchat_server.rocketchat_server_id.clone(),
+        )?;
+        if queued_messages.is_empty() {
+            return Ok(());
+        }
+
+        let mut bridged_channels = HashMap::new();
+        for queued_message in queued_messages {
+            let channel_id = queued_message.rocketchat_channel_id.clone();
+            let is_bridged = match bridged_channels.get(&channel_id) {
+                Some(is_bridged) => *is_bridged,
+                None => self.is_bridged(&queued_message.rocketchat_server_id, &channel_id)?,
+            };
+            bridged_channels.insert(channel_id, is_bridged);
+
+            if !is_bridged {
+                debug!(self.logger, "Discarding queued message {}, the channel is not bridged anymore", queued_message.id);
+                queued_message.delete(self.connection)?;
+                continue;
+            }
+
+            if let Err(err) = rocketchat_api.post_chat_message(&queued_message.message, &queued_message.rocketchat_channel_id) {
+                info!(self.logger, "Could not deliver queued message {}", queued_message.id);
+                log::log_error(self.logger, &err);
+                return Ok(());
+            }
+            queued_message.delete(self.connection)?;
+        }
+
+        user.set_last_message_sent(self.connection)
+    }
+
+    // messages from direct message rooms are queued with the ID of the direct message channel,
+    // which is bridged with an alias that ends with #dm
+    fn is_bridged(&self, rocketchat_server_id: &str, rocketchat_channel_id: &str) -> Result<bool> {
+        let dm_channel_id = format!("{}#dm", rocketchat_channel_id);
+        for channel_id in &[rocketchat_channel_id, dm_channel_id.as_str()] {
+            if Room::matrix_id_from_rocketchat_channel_id(self.config, self.matrix_api, rocketchat_server_id, channel_id)?
+                .is_some()
+            {
+                return Ok(true);
+            }
+        }
+
+        Ok(false)
+    }
+}
+
+fn add_pending_login(credentials: Credentials) {
+    let pending_login = PendingLogin {
+        credentials: credentials,
+        started_at: Instant::now(),
+    };
+    restore_pending_login(pending_login);
+
+    // the credentials contain the password, they are removed after the timeout even if the user
+    // never sends the code
+    PENDING_LOGIN_CLEANUP.call_once(|| {
+        thread::spawn(|| loop {
+            thread::sleep(Duration::from_secs(PENDING_LOGIN_CLEANUP_INTERVAL_SECONDS));
+            let mut pending_logins = PENDING_LOGINS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+            remove_expired_pending_logins(&mut pending_logins);
+        });
+    });
+}
+
+fn restore_pending_login(pending_login: PendingLogin) {
+    let mut pending_logins = PENDING_LOGINS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    remove_expired_pending_logins(&mut pending_logins);
+    let key = (pending_login.credentials.matrix_user_id.to_string(), pending_login.credentials.rocketchat_url.clone());
+    pending_logins.insert(key, pending_login);
+}
+
+fn take_pending_login(matrix_user_id: &UserId, rocketchat_url: &str) -> Option<PendingLogin> {
+    let mut pending_logins = PENDING_LOGINS.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    remove_expired_pending_logins(&mut pending_logins);
+    pending_logins.remove(&(matrix_user_id.to_string(), rocketchat_url.to_string()))
+}
+
+fn remove_expired_pending_logins(pending_logins: &mut HashMap<(String, String), PendingLogin>) {
+    let timeout = Duration::from_secs(PENDING_LOGIN_TIMEOUT_MINUTES * 60);
+    pending_logins.retain(|_, pending_login| pending_login.started_at.elapsed() < timeout);
 }
@@ -1,5 +1,7 @@
 //! Rocket.Chat handlers
 
+/// Finds the participants of Rocket.Chat direct message channels
+pub mod direct_message_handler;
 /// Forwards message from Rocket.Chat to Matrix
 pub mod forwarder;
 /// Helper methods to login a user on the Rocket.Chat server
@@ -7,6 +9,7 @@ pub mod login;
 /// Provides helper methods to manage virtual users.
 pub mod virtual_user_handler;
 
+pub use self::direct_message_handler::{DirectMessageHandler, DirectMessageParticipants};
 pub use self::forwarder::Forwarder;
-pub use self::login::{Credentials, Login};
+pub use self::login::{AccessTokenCredentials, Credentials, Login};
 pub use self::virtual_user_handler::VirtualUserHandler;
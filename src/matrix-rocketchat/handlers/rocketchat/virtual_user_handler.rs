@@ -6,7 +6,7 @@ use slog::Logger;
 
 use api::MatrixApi;
 use config::Config;
-use db::{NewUser, NewUserOnRocketchatServer, Room, User, UserOnRocketchatServer};
+use db::{NewUser, NewUserOnRocketchatServer, RocketchatServer, Room, User, UserOnRocketchatServer};
 use errors::*;
 use i18n::*;
 
@@ -91,7 +91,9 @@ impl<'a> VirtualUserHandler<'a> {
             rocketchat_user_id: Some(rocketchat_user_id.clone()),
             rocketchat_username: Some(rocketchat_user_name.clone()),
         };
-        let user_on_rocketchat_server = UserOnRocketchatServer::upsert(self.connection, &new_user_on_rocketchat_server)?;
+        let encryption_key = self.config.database_encryption_key()?;
+        let user_on_rocketchat_server =
+            UserOnRocketchatServer::upsert(self.connection, encryption_key.as_ref(), &new_user_on_rocketchat_server)?;
 
         self.matrix_api.register(user_id_local_part.clone())?;
         debug!(self.logger, "Successfully registred user {}", user_on_rocketchat_server.matrix_user_id);
@@ -111,4 +113,29 @@ impl<'a> VirtualUserHandler<'a> {
 
         Ok(user_on_rocketchat_server)
     }
+
+    /// Find the Rocket.Chat server and the Rocket.Chat user ID for the Matrix ID of a virtual user.
+    /// Returns `None` if the Matrix ID is not in the namespace of the application service or if
+    /// the Rocket.Chat server is not known.
+    pub fn rocketchat_ids_from_matrix_user_id(&self, matrix_user_id: &UserId) -> Result<Option<(RocketchatServer, String)>> {
+        let hs_domain_suffix = format!(":{}", self.config.hs_domain);
+        let user_id = matrix_user_id.to_string();
+        let prefix = format!("@{}_", self.config.sender_localpart);
+        if !user_id.starts_with(&prefix) || !user_id.ends_with(&hs_domain_suffix) {
+            return Ok(None);
+        }
+
+        // the Rocket.Chat user ID and the server ID can both contain underscores, so the server ID
+        // is determined by comparing the end of the local part with the known servers
+        let user_id_local_part = &user_id[prefix.len()..user_id.len() - hs_domain_suffix.len()];
+        for rocketchat_server in RocketchatServer::find_connected_servers(self.connection)? {
+            let server_suffix = format!("_{}", rocketchat_server.id);
+            if user_id_local_part.len() > server_suffix.len() && user_id_local_part.ends_with(&server_suffix) {
+                let rocketchat_user_id = user_id_local_part[..user_id_local_part.len() - server_suffix.len()].to_string();
+                return Ok(Some((rocketchat_server, rocketchat_user_id)));
+            }
+        }
+
+        Ok(None)
+    }
 }
@@ -6,19 +6,25 @@
 
 #![recursion_limit = "256"]
 
+extern crate base64;
 #[macro_use]
 extern crate diesel;
 #[macro_use]
 extern crate diesel_codegen;
 #[macro_use]
 extern crate error_chain;
+extern crate hyper;
+extern crate hyper_native_tls;
 extern crate iron;
 #[macro_use]
 extern crate lazy_static;
+extern crate native_tls;
+extern crate openssl;
 extern crate persistent;
 extern crate pulldown_cmark;
 extern crate r2d2;
 extern crate r2d2_diesel;
+extern crate rand;
 extern crate reqwest;
 extern crate router;
 extern crate ruma_client_api;
@@ -64,6 +70,8 @@ pub mod middleware;
 pub mod models;
 /// The server that runs the application service.
 pub mod server;
+/// TLS support for the application service server.
+pub mod tls;
 
 pub use config::Config;
 pub use server::Server;
@@ -1,3 +1,5 @@
+use std::cmp;
+
 use iron::{Plugin, Request};
 use iron::typemap::Key;
 use persistent::State;
@@ -5,6 +7,25 @@ use slog::Logger;
 
 use errors::*;
 
+/// Keys of values that contain secrets, the keys are compared case insensitive. Generic keys
+/// like `token` are not part of the list, they are passed to `redact_secrets_with_keys` where
+/// they are known to contain a secret.
+const SECRET_KEYS: &'static [&'static str] = &[
+    "access_token",
+    "as_token",
+    "auth_token",
+    "authtoken",
+    "csrf_token",
+    "hs_token",
+    "password",
+    "rocketchat_auth_token",
+    "rocketchat_token",
+    "totp_code",
+    "x-auth-token",
+];
+/// Replacement for the secrets
+const REDACTED: &'static str = "[REDACTED]";
+
 /// Struct to attach a logger to an iron request.
 pub struct IronLogger;
 
@@ -51,5 +72,100 @@ fn build_message(err: &Error) -> String {
         msg = msg + " caused by: " + &format!("{}", err);
     }
 
-    msg
+    redact_secrets(&msg)
+}
+
+/// Replace the values of secrets (passwords, tokens, ...) in a text with a placeholder. Secrets
+/// are detected by their key in JSON objects (`"password": "secret"`) and in query strings or
+/// form data (`access_token=secret`).
+pub fn redact_secrets(text: &str) -> String {
+    redact_secrets_with_keys(text, &[])
+}
+
+/// Same as `redact_secrets`, but the values of the additional keys are replaced as well. The keys
+/// have to be lowercase.
+pub fn redact_secrets_with_keys(text: &str, additional_secret_keys: &[&str]) -> String {
+    let bytes = text.as_bytes();
+    let mut redacted_text = String::with_capacity(text.len());
+    let mut copied_until = 0;
+    let mut position = 0;
+
+    while position < bytes.len() {
+        // keys start at a word boundary
+        if !is_key_byte(bytes[position]) || (position > 0 && is_key_byte(bytes[position - 1])) {
+            position += 1;
+            continue;
+        }
+
+        let key_end = position + bytes[position..].iter().take_while(|byte| is_key_byte(**byte)).count();
+        let key = text[position..key_end].to_lowercase();
+        if SECRET_KEYS.contains(&key.as_ref()) || additional_secret_keys.contains(&key.as_ref()) {
+            if let Some((value_start, value_end)) = find_secret_value(bytes, position, key_end) {
+                redacted_text.push_str(&text[copied_until..value_start]);
+                redacted_text.push_str(REDACTED);
+                copied_until = value_end;
+                position = value_end;
+                continue;
+            }
+        }
+
+        position = key_end;
+    }
+
+    redacted_text.push_str(&text[copied_until..]);
+    redacted_text
+}
+
+/// Find the start and the end of the value that belongs to a key. All the delimiters are ASCII
+/// characters, so the returned positions are always at a character boundary.
+fn find_secret_value(bytes: &[u8], key_start: usize, key_end: usize) -> Option<(usize, usize)> {
+    let is_json_key = key_start > 0 && bytes[key_start - 1] == b'"' && bytes.get(key_end) == Some(&b'"');
+    if is_json_key {
+        let mut position = skip_whitespace(bytes, key_end + 1);
+        if bytes.get(position) != Some(&b':') {
+            return None;
+        }
+
+        position = skip_whitespace(bytes, position + 1);
+        if bytes.get(position) != Some(&b'"') {
+            let value_end = position + bytes[position..].iter().take_while(|byte| !b",}] \n".contains(*byte)).count();
+            return Some((position, value_end));
+        }
+
+        let value_start = position + 1;
+        let mut value_end = value_start;
+        while value_end < bytes.len() && bytes[value_end] != b'"' {
+            // escaped characters (including quotes) are part of the value
+            if bytes[value_end] == b'\\' {
+                value_end += 1;
+            }
+            value_end += 1;
+        }
+        return Some((value_start, cmp::min(value_end, bytes.len())));
+    }
+
+    if bytes.get(key_end) == Some(&b'=') {
+        let value_start = key_end + 1;
+        let value_length = bytes[value_start..].iter().take_while(|byte| !b"&\"'` \n".contains(*byte)).count();
+        return Some((value_start, value_start + value_length));
+    }
+
+    None
+}
+
+fn skip_whitespace(bytes: &[u8], position: usize) -> usize {
+    if position >= bytes.len() {
+        return position;
+    }
+
+    position + bytes[position..].iter().take_while(|byte| b" \t\r\n".contains(*byte)).count()
+}
+
+/// Only ASCII characters are part of a key, this way a key never ends inside of a multibyte
+/// character.
+fn is_key_byte(byte: u8) -> bool {
+    match byte {
+        b'a'...b'z' | b'A'...b'Z' | b'0'...b'9' | b'_' | b'-' => true,
+        _ => false,
+    }
 }
@@ -1,11 +1,13 @@
 use iron::{BeforeMiddleware, IronResult, Request};
+use iron::headers::{Authorization, Bearer};
 
 use config::Config;
 use errors::*;
 use iron::url::Url;
 use log::*;
 
-/// Compares the supplied access token to the one that is in the config
+/// Compares the supplied access token to the one that is in the config. The token is read from
+/// the `Authorization: Bearer` header and falls back to the legacy `access_token` query parameter.
 pub struct AccessToken {
     /// Application service config
     pub config: Config,
@@ -13,22 +15,29 @@ pub struct AccessToken {
 
 impl BeforeMiddleware for AccessToken {
     fn before(&self, request: &mut Request) -> IronResult<()> {
-        let url: Url = request.url.clone().into();
-        let mut query_pairs = url.query_pairs();
         let logger = IronLogger::from_request(request)?;
 
-        if let Some((_, ref token)) = query_pairs.find(|&(ref key, _)| key == "access_token") {
-            if token == &self.config.hs_token {
+        let token = match request.headers.get::<Authorization<Bearer>>() {
+            Some(authorization) => Some(authorization.token.clone()),
+            None => {
+                let url: Url = request.url.clone().into();
+                let mut query_pairs = url.query_pairs();
+                query_pairs.find(|&(ref key, _)| key == "access_token").map(|(_, token)| token.into_owned())
+            }
+        };
+
+        if let Some(token) = token {
+            if token == self.config.hs_token {
                 return Ok(());
             }
 
-            let err = simple_error!(ErrorKind::InvalidAccessToken(token.to_string()));
-            info!(logger, "{}", err);
+            let err = simple_error!(ErrorKind::InvalidAccessToken);
+            log_info(&logger, &err);
             return Err(err.into());
         }
 
         let err = simple_error!(ErrorKind::MissingAccessToken);
-        info!(logger, "{}", err);
+        log_info(&logger, &err);
         Err(err.into())
     }
 }
@@ -4,12 +4,19 @@ use iron::{BeforeMiddleware, IronResult, Request};
 use serde_json;
 
 use api::rocketchat::Message;
+use config::Config;
 use db::{ConnectionPool, RocketchatServer};
 use errors::*;
 use log::*;
 
-/// Compares the supplied access token to the one that is in the config
-pub struct RocketchatToken {}
+/// Key of the token in the messages that are sent by the Rocket.Chat outgoing webhook
+const WEBHOOK_TOKEN_KEY: &'static str = "token";
+
+/// Finds the Rocket.Chat server that sent the message by the supplied token
+pub struct RocketchatToken {
+    /// Application service config
+    pub config: Config,
+}
 
 impl BeforeMiddleware for RocketchatToken {
     fn before(&self, request: &mut Request) -> IronResult<()> {
@@ -19,9 +26,12 @@ impl BeforeMiddleware for RocketchatToken {
         let message = match serde_json::from_str::<Message>(&payload) {
             Ok(message) => message,
             Err(err) => {
-                let msg = format!("Could not deserialize message that was sent to the rocketchat endpoint: `{}`", payload);
+                let msg = format!(
+                    "Could not deserialize message that was sent to the rocketchat endpoint: `{}`",
+                    redact_secrets_with_keys(&payload, &[WEBHOOK_TOKEN_KEY])
+                );
                 let json_err = simple_error!(ErrorKind::InvalidJSON(msg));
-                error!(logger, "{}", err);
+                error!(logger, "{}", redact_secrets_with_keys(&err.to_string(), &[WEBHOOK_TOKEN_KEY]));
                 return Err(json_err.into());
             }
         };
@@ -30,17 +40,18 @@ impl BeforeMiddleware for RocketchatToken {
             Some(token) => token,
             None => {
                 let err = simple_error!(ErrorKind::MissingRocketchatToken);
-                info!(logger, "{}", err);
+                log_info(&logger, &err);
                 return Err(err.into());
             }
         };
 
         let connection = ConnectionPool::from_request(request)?;
-        let rocketchat_server = match RocketchatServer::find_by_token(&connection, token.clone())? {
+        let encryption_key = self.config.database_encryption_key()?;
+        let rocketchat_server = match RocketchatServer::find_by_token(&connection, encryption_key.as_ref(), token)? {
             Some(rocketchat_server) => rocketchat_server,
             None => {
-                let err = simple_error!(ErrorKind::InvalidRocketchatToken(token));
-                info!(logger, "{}", err);
+                let err = simple_error!(ErrorKind::InvalidRocketchatToken);
+                log_info(&logger, &err);
                 return Err(err.into());
             }
         };
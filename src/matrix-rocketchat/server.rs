@@ -10,10 +10,16 @@ use slog::Logger;
 use api::MatrixApi;
 use config::Config;
 use db::{ConnectionPool, NewUser, User};
+use db::encryption;
 use errors::*;
-use handlers::iron::{Rocketchat, RocketchatLogin, Transactions, Welcome};
+use handlers::iron::{LoginPage, Rocketchat, RocketchatLogin, Rooms, Transactions, Users, Welcome};
 use i18n::*;
 use log::IronLogger;
+use tls::TlsServer;
+
+/// Path prefixes under which the application service API is served. The spec uses
+/// `/_matrix/app/v1`, older homeservers call the endpoints without a prefix.
+const APPSERVICE_API_PREFIXES: &'static [(&'static str, &'static str)] = &[("", "legacy"), ("/_matrix/app/v1", "v1")];
 
 /// The application service server
 pub struct Server<'a> {
@@ -49,23 +55,62 @@ impl<'a> Server<'a> {
         info!(self.logger, "Starting server"; "address" => format!("{:?}", self.config.as_address));
         let mut server = Iron::new(chain);
         server.threads = threads;
-        server.http(self.config.as_address).chain_err(|| ErrorKind::ServerStartupError).map_err(Error::from)
+
+        if !self.config.use_ssl {
+            return server.http(self.config.as_address).chain_err(|| ErrorKind::ServerStartupError).map_err(Error::from);
+        }
+
+        let tls_server = TlsServer::new(self.config)?;
+        tls_server.watch(self.logger.clone());
+        server.https(self.config.as_address, tls_server).chain_err(|| ErrorKind::ServerStartupError).map_err(Error::from)
     }
 
     fn setup_routes(&self, matrix_api: Box<MatrixApi>) -> Router {
         debug!(self.logger, "Setting up routes");
         let mut router = Router::new();
         router.get("/", Welcome {}, "welcome");
-        router.put("/transactions/:txn_id", Transactions::chain(self.config.clone(), matrix_api.clone()), "transactions");
+        for &(prefix, version) in APPSERVICE_API_PREFIXES {
+            router.put(
+                format!("{}/transactions/:txn_id", prefix),
+                Transactions::chain(self.config.clone(), matrix_api.clone()),
+                format!("transactions_{}", version),
+            );
+            router.get(
+                format!("{}/rooms/:room_alias", prefix),
+                Rooms::chain(self.config.clone(), matrix_api.clone()),
+                format!("rooms_{}", version),
+            );
+            router.get(
+                format!("{}/users/:user_id", prefix),
+                Users::chain(self.config.clone(), matrix_api.clone()),
+                format!("users_{}", version),
+            );
+        }
         router.post("/rocketchat", Rocketchat::chain(self.config.clone(), matrix_api.clone()), "rocketchat");
         router.post(
             "/rocketchat/login",
             RocketchatLogin {
                 config: self.config.clone(),
-                matrix_api: matrix_api,
+                matrix_api: matrix_api.clone(),
             },
             "rocketchat_login",
         );
+        router.get(
+            "/rocketchat/login/:token",
+            LoginPage {
+                config: self.config.clone(),
+                matrix_api: matrix_api.clone(),
+            },
+            "login_page",
+        );
+        router.post(
+            "/rocketchat/login/:token",
+            LoginPage {
+                config: self.config.clone(),
+                matrix_api: matrix_api,
+            },
+            "login_page_submit",
+        );
         router
     }
 
@@ -73,7 +118,29 @@ impl<'a> Server<'a> {
         debug!(self.logger, "Setting up database {}", self.config.database_url);
         let connection = SqliteConnection::establish(&self.config.database_url).chain_err(|| ErrorKind::DBConnectionError)?;
         setup_database(&connection).chain_err(|| ErrorKind::DatabaseSetupError)?;
-        run_embedded_migrations(&connection).chain_err(|| ErrorKind::MigrationError).map_err(Error::from)
+        run_embedded_migrations(&connection).chain_err(|| ErrorKind::MigrationError)?;
+
+        match self.config.database_encryption_key()? {
+            Some(encryption_key) => {
+                // tokens that were stored before a key was configured are encrypted on startup
+                let encrypted_tokens = encryption::encrypt_plaintext_tokens(&connection, &encryption_key)?;
+                if encrypted_tokens > 0 {
+                    info!(self.logger, "Encrypted {} tokens that were stored in plaintext", encrypted_tokens);
+                }
+            }
+            None => {
+                if encryption::has_encrypted_tokens(&connection)? {
+                    bail_error!(ErrorKind::DatabaseEncryptionKeyMissing);
+                }
+                warn!(
+                    self.logger,
+                    "No database encryption key configured, the Rocket.Chat tokens are stored in plaintext. \
+                     Set `database_encryption_key` in the config to encrypt them."
+                );
+            }
+        }
+
+        Ok(())
     }
 
     fn setup_bot_user(&self, connection: &SqliteConnection, matrix_api: &MatrixApi) -> Result<()> {
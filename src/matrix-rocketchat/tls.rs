@@ -0,0 +1,161 @@
+use std::fs::{self, File};
+use std::io::Read;
+use std::sync::{Arc, RwLock};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+use hyper;
+use hyper::net::{HttpStream, SslServer};
+use hyper_native_tls::{NativeTlsServer, TlsStream};
+use native_tls::{Pkcs12 as NativeTlsPkcs12, TlsAcceptor};
+use openssl::pkcs12::Pkcs12;
+use openssl::pkey::PKey;
+use openssl::stack::Stack;
+use openssl::x509::X509;
+use slog::Logger;
+
+use config::Config;
+use errors::*;
+use log;
+
+/// Interval in which the certificate and the key are checked for changes
+const CERTIFICATE_CHECK_INTERVAL_SECONDS: u64 = 60;
+/// Password of the PKCS #12 archive that is only kept in memory to create the TLS acceptor
+const PKCS12_PASSWORD: &'static str = "matrix-rocketchat";
+
+/// TLS server that serves the certificate and the key from the config. The certificate is
+/// reloaded when the files change, so a renewed certificate is used without a restart.
+#[derive(Clone)]
+pub struct TlsServer {
+    /// Path to the PEM encoded certificate chain
+    certificate_path: String,
+    /// Path to the PEM encoded private key
+    key_path: String,
+    /// The server that wraps the incoming connections, it's replaced when the certificate is reloaded
+    server: Arc<RwLock<NativeTlsServer>>,
+}
+
+impl TlsServer {
+    /// Create a TLS server with the certificate and the key that are configured
+    pub fn new(config: &Config) -> Result<TlsServer> {
+        let certificate_path = match config.ssl_certificate_path {
+            Some(ref certificate_path) => certificate_path.clone(),
+            None => bail_error!(ErrorKind::MissingSslConfiguration("ssl_certificate_path".to_string())),
+        };
+        let key_path = match config.ssl_key_path {
+            Some(ref key_path) => key_path.clone(),
+            None => bail_error!(ErrorKind::MissingSslConfiguration("ssl_key_path".to_string())),
+        };
+
+        let server = load_server(&certificate_path, &key_path)?;
+        Ok(TlsServer {
+            certificate_path: certificate_path,
+            key_path: key_path,
+            server: Arc::new(RwLock::new(server)),
+        })
+    }
+
+    /// Load the certificate and the key again. New connections use the reloaded certificate,
+    /// established connections are not affected. The current certificate is kept if the files
+    /// are not valid.
+    pub fn reload(&self) -> Result<()> {
+        let server = load_server(&self.certificate_path, &self.key_path)?;
+        let mut current_server = match self.server.write() {
+            Ok(current_server) => current_server,
+            // the server is replaced completely, so a poisoned lock doesn't leave it in an inconsistent state
+            Err(poisoned_lock) => poisoned_lock.into_inner(),
+        };
+        *current_server = server;
+        Ok(())
+    }
+
+    /// Periodically check if the certificate or the key changed and reload them if they did.
+    pub fn watch(&self, logger: Logger) {
+        let tls_server = self.clone();
+        let mut last_modified = tls_server.last_modified();
+
+        thread::spawn(move || loop {
+            thread::sleep(Duration::from_secs(CERTIFICATE_CHECK_INTERVAL_SECONDS));
+
+            let modified = tls_server.last_modified();
+            if modified == last_modified {
+                continue;
+            }
+
+            // the files are compared again in the next interval if the reload failed, because
+            // the certificate and the key might not have been updated at the same time
+            match tls_server.reload() {
+                Ok(()) => {
+                    info!(logger, "Reloaded SSL certificate"; "path" => tls_server.certificate_path.clone());
+                    last_modified = modified;
+                }
+                Err(err) => log::log_error(&logger, &err),
+            }
+        });
+    }
+
+    fn last_modified(&self) -> (Option<SystemTime>, Option<SystemTime>) {
+        (modified_at(&self.certificate_path), modified_at(&self.key_path))
+    }
+}
+
+impl SslServer<HttpStream> for TlsServer {
+    type Stream = TlsStream<HttpStream>;
+
+    fn wrap_server(&self, stream: HttpStream) -> hyper::Result<Self::Stream> {
+        let server = match self.server.read() {
+            Ok(server) => server,
+            // the server is only replaced, never modified, so it's safe to use it after a panic
+            Err(poisoned_lock) => poisoned_lock.into_inner(),
+        };
+        server.wrap_server(stream)
+    }
+}
+
+fn load_server(certificate_path: &str, key_path: &str) -> Result<NativeTlsServer> {
+    let certificate_pem = read_file(certificate_path)?;
+    let key_pem = read_file(key_path)?;
+
+    let key = PKey::private_key_from_pem(&key_pem)
+        .chain_err(|| ErrorKind::InvalidSslCertificate(format!("Could not read private key from {}", key_path)))?;
+    let mut certificates = X509::stack_from_pem(&certificate_pem)
+        .chain_err(|| ErrorKind::InvalidSslCertificate(format!("Could not read certificate from {}", certificate_path)))?
+        .into_iter();
+    let certificate = match certificates.next() {
+        Some(certificate) => certificate,
+        None => bail_error!(ErrorKind::InvalidSslCertificate(format!("No certificate found in {}", certificate_path))),
+    };
+
+    // native-tls only accepts PKCS #12 archives, the PEM files are converted in memory
+    let mut chain = Stack::new().chain_err(|| ErrorKind::InvalidSslCertificate("Could not build chain".to_string()))?;
+    for intermediate_certificate in certificates {
+        chain.push(intermediate_certificate).chain_err(|| {
+            ErrorKind::InvalidSslCertificate(format!("Could not add certificate from {} to the chain", certificate_path))
+        })?;
+    }
+    let mut pkcs12_builder = Pkcs12::builder();
+    pkcs12_builder.ca(chain);
+    let pkcs12 = pkcs12_builder
+        .build(PKCS12_PASSWORD, "matrix-rocketchat", &key, &certificate)
+        .and_then(|pkcs12| pkcs12.to_der())
+        .chain_err(|| ErrorKind::InvalidSslCertificate("The certificate does not match the private key".to_string()))?;
+
+    let identity = NativeTlsPkcs12::from_der(&pkcs12, PKCS12_PASSWORD)
+        .chain_err(|| ErrorKind::InvalidSslCertificate("Could not load certificate".to_string()))?;
+    let acceptor = TlsAcceptor::builder(identity)
+        .and_then(|builder| builder.build())
+        .chain_err(|| ErrorKind::InvalidSslCertificate("Could not create TLS acceptor".to_string()))?;
+
+    Ok(NativeTlsServer::from(acceptor))
+}
+
+fn read_file(path: &str) -> Result<Vec<u8>> {
+    let mut content = Vec::new();
+    let mut file = File::open(path).chain_err(|| ErrorKind::ReadFileError(path.to_string()))?;
+    file.read_to_end(&mut content).chain_err(|| ErrorKind::ReadFileError(path.to_string()))?;
+    Ok(content)
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
@@ -13,7 +13,7 @@ use std::convert::TryFrom;
 
 use iron::{Chain, status};
 use matrix_rocketchat::api::MatrixApi;
-use matrix_rocketchat::api::rocketchat::v1::{LOGIN_PATH, ME_PATH, USERS_INFO_PATH};
+use matrix_rocketchat::api::rocketchat::v1::{CHANNELS_JOIN_PATH, LOGIN_PATH, ME_PATH, USERS_INFO_PATH};
 use matrix_rocketchat::db::Room;
 use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, Test, default_timeout, handlers, helpers};
 use router::Router;
@@ -24,7 +24,7 @@ use ruma_client_api::r0::room::create_room::Endpoint as CreateRoomEndpoint;
 use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
 use ruma_client_api::r0::send::send_state_event_for_empty_key::{self, Endpoint as SendStateEventForEmptyKeyEndpoint};
 use ruma_events::EventType;
-use ruma_identifiers::{RoomId, UserId};
+use ruma_identifiers::{RoomAliasId, RoomId, UserId};
 
 #[test]
 fn successfully_bridge_a_rocketchat_room() {
@@ -51,8 +51,12 @@ fn successfully_bridge_a_rocketchat_room() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -118,6 +122,115 @@ fn successfully_bridge_a_rocketchat_room() {
     assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_user_3_id_rc_id:localhost").unwrap()));
 }
 
+#[test]
+fn successfully_bridge_a_rocketchat_private_group() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (invite_forwarder, invite_receiver) = handlers::MatrixInviteUser::with_forwarder(test.config.as_url.clone());
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(InviteEndpoint::router_path(), invite_forwarder, "invite_user");
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+
+    let mut channels = HashMap::new();
+    channels.insert("normal_channel", Vec::new());
+    let mut groups = HashMap::new();
+    groups.insert("private_group", vec!["spec_user", "user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .with_custom_groups_list(groups)
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge private_group".to_string(),
+    );
+
+    // discard admin room creation message
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+
+    // the room is invite only
+    let create_room_message = create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(create_room_message.contains("\"name\":\"private_group\""));
+    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#private_group_id\""));
+    assert!(create_room_message.contains("\"preset\":\"private_chat\""));
+
+    // discard rocketchat user invite into admin room
+    invite_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let invite_spec_user = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(invite_spec_user.contains("@spec_user:localhost"));
+    let invite_virtual_spec_user = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(invite_virtual_spec_user.contains("rocketchat_spec_user_id_rc_id:localhost"));
+    let invite_user_1 = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(invite_user_1.contains("@rocketchat_user_1_id_rc_id:localhost"));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("private_group is now bridged."));
+}
+
+#[test]
+fn the_virtual_users_get_the_power_levels_for_their_roles_in_the_rocketchat_channel() {
+    let test = Test::new();
+    let (state_forwarder, state_receiver) = handlers::SendRoomState::with_forwarder();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendStateEventForEmptyKeyEndpoint::router_path(), state_forwarder, "send_state_event_for_key");
+
+    let mut channels = HashMap::new();
+    channels.insert("joined_channel", vec!["spec_user", "user_1", "user_2", "user_3"]);
+    let mut room_roles = HashMap::new();
+    room_roles.insert("user_1", vec!["owner"]);
+    room_roles.insert("user_2", vec!["moderator"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .with_custom_room_roles(room_roles)
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge joined_channel".to_string(),
+    );
+
+    // the power levels of the users are set after the virtual users joined the room
+    let mut power_levels_received_by_matrix = String::new();
+    while let Ok(state_received_by_matrix) = state_receiver.recv_timeout(default_timeout()) {
+        if state_received_by_matrix.contains("@rocketchat_user_1_id_rc_id:localhost") {
+            power_levels_received_by_matrix = state_received_by_matrix;
+            break;
+        }
+    }
+
+    assert!(power_levels_received_by_matrix.contains("\"@rocketchat:localhost\":100"));
+    assert!(power_levels_received_by_matrix.contains("\"@rocketchat_user_1_id_rc_id:localhost\":90"));
+    assert!(power_levels_received_by_matrix.contains("\"@rocketchat_user_2_id_rc_id:localhost\":50"));
+    assert!(!power_levels_received_by_matrix.contains("@rocketchat_user_3_id_rc_id:localhost"));
+    assert!(!power_levels_received_by_matrix.contains("@rocketchat_spec_user_id_rc_id:localhost"));
+}
+
 #[test]
 fn successfully_bridge_a_rocketchat_room_that_an_other_user_already_bridged() {
     let test = Test::new();
@@ -207,8 +320,12 @@ fn successfully_bridge_a_rocketchat_room_that_an_other_user_already_bridged() {
 
     // discard welcome message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -216,6 +333,8 @@ fn successfully_bridge_a_rocketchat_room_that_an_other_user_already_bridged() {
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message for other user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message for other user
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -264,19 +383,17 @@ fn susccessfully_bridge_a_rocketchat_room_that_was_unbridged_before() {
     matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
     matrix_router.post(InviteEndpoint::router_path(), invite_forwarder, "invite_user");
 
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
+
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
         .with_connected_admin_room()
         .with_logged_in_user()
         .with_bridged_room(("joined_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
         .run();
 
-    helpers::leave_room(
-        &test.config,
-        RoomId::try_from("!joined_channel_id:localhost").unwrap(),
-        UserId::try_from("@spec_user:localhost").unwrap(),
-    );
-
     helpers::send_room_message_from_matrix(
         &test.config.as_url,
         RoomId::try_from("!admin_room_id:localhost").unwrap(),
@@ -284,6 +401,12 @@ fn susccessfully_bridge_a_rocketchat_room_that_was_unbridged_before() {
         "unbridge joined_channel".to_string(),
     );
 
+    helpers::leave_room(
+        &test.config,
+        RoomId::try_from("!joined_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
     helpers::send_room_message_from_matrix(
         &test.config.as_url,
         RoomId::try_from("!admin_room_id:localhost").unwrap(),
@@ -299,12 +422,18 @@ fn susccessfully_bridge_a_rocketchat_room_that_was_unbridged_before() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard bridge message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard the notice in the unbridged room
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard unbridge message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -342,8 +471,12 @@ fn successfully_bridge_two_different_rocketchat_rooms() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -400,16 +533,87 @@ fn successfully_bridge_two_different_rocketchat_rooms() {
 }
 
 #[test]
-fn do_not_allow_to_bridge_channels_that_the_user_has_not_joined_on_the_rocketchat_server() {
+fn join_the_rocketchat_channel_when_bridging_a_channel_that_the_user_has_not_joined() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (invite_forwarder, invite_receiver) = handlers::MatrixInviteUser::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(InviteEndpoint::router_path(), invite_forwarder, "invite_user");
+
+    let (channels_join_forwarder, channels_join_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(CHANNELS_JOIN_PATH, channels_join_forwarder, "channels_join");
+
+    let mut channels = HashMap::new();
+    channels.insert("normal_channel", vec!["user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge normal_channel".to_string(),
+    );
+
+    let channels_join_payload = channels_join_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(channels_join_payload.contains("\"roomId\":\"normal_channel_id\""));
+
+    // discard rocketchat user invite into admin room
+    invite_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let invite_spec_user = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(invite_spec_user.contains("@spec_user:localhost"));
+    let invite_user_1 = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(invite_user_1.contains("@rocketchat_user_1_id_rc_id:localhost"));
+    let invite_virtual_spec_user = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(invite_virtual_spec_user.contains("@rocketchat_spec_user_id_rc_id:localhost"));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("normal_channel is now bridged."));
+}
+
+#[test]
+fn the_user_gets_a_message_when_joining_the_rocketchat_channel_failes() {
     let test = Test::new();
     let (message_forwarder, receiver) = MessageForwarder::new();
     let mut matrix_router = test.default_matrix_routes();
     matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        CHANNELS_JOIN_PATH,
+        handlers::RocketchatErrorResponder {
+            message: "Rocket.Chat channels.join error".to_string(),
+            status: status::InternalServerError,
+        },
+        "channels_join",
+    );
+
     let mut channels = HashMap::new();
     channels.insert("normal_channel", Vec::new());
 
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
         .with_connected_admin_room()
         .with_logged_in_user()
         .with_custom_channel_list(channels)
@@ -417,8 +621,12 @@ fn do_not_allow_to_bridge_channels_that_the_user_has_not_joined_on_the_rocketcha
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -430,10 +638,144 @@ fn do_not_allow_to_bridge_channels_that_the_user_has_not_joined_on_the_rocketcha
     );
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
-    assert!(message_received_by_matrix.contains(
-        "You have to join the channel normal_channel on the Rocket.Chat server \
-                                                before you can bridge it.",
-    ));
+    assert!(message_received_by_matrix.contains("An internal error occurred"));
+}
+
+#[test]
+fn successfully_bridge_all_rocketchat_rooms_the_user_is_a_member_of() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("normal_channel", Vec::new());
+    channels.insert("joined_channel", vec!["spec_user"]);
+    channels.insert("bridged_channel", vec!["spec_user"]);
+    let mut groups = HashMap::new();
+    groups.insert("private_group", vec!["spec_user"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .with_custom_groups_list(groups)
+        .with_bridged_room(("bridged_channel", "spec_user"))
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridge message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge --all".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The following rooms are now bridged:"));
+    assert!(message_received_by_matrix.contains("joined_channel"));
+    assert!(message_received_by_matrix.contains("private_group"));
+    assert!(!message_received_by_matrix.contains("normal_channel"));
+    assert!(!message_received_by_matrix.contains("bridged_channel"));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let joined_channel_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#joined_channel_id:localhost").unwrap();
+    assert!(matrix_api.get_room_alias(joined_channel_alias_id).unwrap().is_some());
+    let private_group_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#private_group_id:localhost").unwrap();
+    assert!(matrix_api.get_room_alias(private_group_alias_id).unwrap().is_some());
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!joined_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!private_group_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge --all".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("All the Rocket.Chat rooms that you are a member of are already bridged."));
+}
+
+#[test]
+fn bridge_all_continues_with_the_other_rooms_when_bridging_a_room_failes() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let create_room = handlers::MatrixCreateRoom { as_url: test.config.as_url.clone() };
+    let conditional_error = handlers::MatrixConditionalErrorResponder {
+        status: status::InternalServerError,
+        message: "Could not create room".to_string(),
+        conditional_content: "joined_channel",
+    };
+    let mut create_room_with_error = Chain::new(create_room);
+    create_room_with_error.link_before(conditional_error);
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_with_error, "create_room");
+
+    let mut channels = HashMap::new();
+    channels.insert("joined_channel", vec!["spec_user"]);
+    let mut groups = HashMap::new();
+    groups.insert("private_group", vec!["spec_user"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .with_custom_groups_list(groups)
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge --all".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The following rooms are now bridged: private_group."));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The following rooms could not be bridged: joined_channel."));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let private_group_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#private_group_id:localhost").unwrap();
+    assert!(matrix_api.get_room_alias(private_group_alias_id).unwrap().is_some());
 }
 
 #[test]
@@ -453,8 +795,12 @@ fn attempting_to_bridge_a_non_existing_channel_returns_an_error() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -486,8 +832,12 @@ fn attempting_to_bridge_an_already_bridged_channel_returns_an_error() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -546,8 +896,12 @@ fn the_room_is_not_bridged_when_setting_the_canonical_room_alias_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -605,8 +959,12 @@ fn bridge_a_room_after_setting_the_canonical_room_failed() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -645,8 +1003,12 @@ fn the_user_gets_a_message_when_creating_the_room_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -691,8 +1053,12 @@ fn the_user_gets_a_message_when_setting_the_powerlevels_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -733,8 +1099,12 @@ fn the_user_gets_a_message_when_inviting_the_user_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -800,8 +1170,12 @@ fn the_user_gets_a_message_when_getting_the_users_info_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -834,8 +1208,12 @@ fn the_user_gets_a_message_when_getting_the_room_alias_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -876,8 +1254,12 @@ fn the_user_gets_a_message_when_the_create_room_response_cannot_be_deserialized(
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -936,8 +1318,12 @@ fn the_user_gets_a_message_when_the_users_info_response_cannot_be_deserialized()
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -963,8 +1349,12 @@ fn the_user_gets_a_message_when_getting_the_room_alias_response_cannot_be_deseri
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
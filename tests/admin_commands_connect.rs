@@ -15,6 +15,7 @@ use std::thread;
 use iron::{Iron, Listening, status};
 use matrix_rocketchat::api::MatrixApi;
 use matrix_rocketchat::db::{RocketchatServer, UserOnRocketchatServer};
+use matrix_rocketchat::db::encryption;
 use matrix_rocketchat_test::{DEFAULT_LOGGER, DEFAULT_ROCKETCHAT_VERSION, IRON_THREADS, MessageForwarder, RS_TOKEN, Test,
                              default_timeout, get_free_socket_addr, handlers, helpers};
 use router::Router;
@@ -34,6 +35,8 @@ fn successfully_connect_rocketchat_server() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     let expected_message = format!("You are connected to {}", test.rocketchat_mock_url.clone().unwrap());
@@ -42,7 +45,9 @@ fn successfully_connect_rocketchat_server() {
     let connection = test.connection_pool.get().unwrap();
     let rocketchat_server =
         RocketchatServer::find_by_url(&connection, test.rocketchat_mock_url.clone().unwrap()).unwrap().unwrap();
-    assert_eq!(rocketchat_server.rocketchat_token.unwrap(), RS_TOKEN.to_string());
+    let encryption_key = test.config.database_encryption_key().unwrap();
+    let rocketchat_token = encryption::decrypt(encryption_key.as_ref(), &rocketchat_server.rocketchat_token).unwrap();
+    assert_eq!(rocketchat_token.unwrap(), RS_TOKEN.to_string());
 
     let users_on_rocketchat_server =
         UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), rocketchat_server.id);
@@ -83,6 +88,8 @@ fn attempt_to_connect_to_an_incompatible_rocketchat_server_version() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains(
@@ -128,6 +135,8 @@ fn attempt_to_connect_to_a_non_rocketchat_server() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     let expected_message = format!("No Rocket.Chat server found when querying {}", rocketchat_mock_url);
@@ -172,6 +181,8 @@ fn attempt_to_connect_to_a_server_with_the_correct_endpoint_but_an_incompatible_
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     let expected_message = format!(
@@ -207,6 +218,8 @@ fn attempt_to_connect_to_non_existing_server() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     let expected_message = format!("Could not reach Rocket.Chat server {}", rocketchat_mock_url);
@@ -234,6 +247,8 @@ fn attempt_to_connect_without_a_rocketchat_server_id() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains(
@@ -265,6 +280,8 @@ fn attempt_to_connect_with_an_incompatible_rocketchat_server_id() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains(
@@ -332,11 +349,15 @@ fn attempt_to_connect_with_a_rocketchat_server_id_that_is_already_in_use() {
     // discard first welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
 
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard first connect message
     receiver.recv_timeout(default_timeout()).unwrap();
 
     // discard second welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("The provided ID `rc_id` is already in use, please choose another one."));
@@ -385,6 +406,8 @@ fn connect_an_existing_server() {
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
 
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -436,11 +459,15 @@ fn attempt_to_connect_to_an_existing_server_with_a_token() {
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
 
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
 
     // discard other welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     let expected_message = format!(
@@ -486,8 +513,12 @@ fn attempt_to_connect_an_already_connected_room() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard first connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("This room is already connected"));
@@ -536,13 +567,17 @@ fn attempt_to_connect_a_server_with_a_token_that_is_already_in_use() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard other welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
-    assert!(message_received_by_matrix.contains("The token rt is already in use, please use another token"));
+    assert!(message_received_by_matrix.contains("The token is already in use, please use another token"));
 }
 
 #[test]
@@ -590,6 +625,8 @@ fn connecting_a_room_failes_when_the_room_topic_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
@@ -0,0 +1,310 @@
+#![feature(try_from)]
+
+extern crate diesel;
+extern crate iron;
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_events;
+extern crate ruma_identifiers;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use diesel::prelude::*;
+use iron::status;
+use matrix_rocketchat::api::MatrixApi;
+use matrix_rocketchat::api::rocketchat::v1::{CHANNELS_LIST_PATH, DIRECT_MESSAGES_LIST_PATH};
+use matrix_rocketchat::db::{QueuedMessage, RocketchatServer};
+use matrix_rocketchat::db::schema::rocketchat_servers;
+use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, Test, default_timeout, handlers, helpers};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_client_api::r0::sync::get_state_events_for_empty_key::{self, Endpoint as GetStateEventsForEmptyKey};
+use ruma_events::EventType;
+use ruma_identifiers::{RoomId, UserId};
+
+#[test]
+fn successfully_disconnect_a_rocketchat_server() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(
+        CHANNELS_LIST_PATH,
+        handlers::RocketchatChannelsList {
+            channels: HashMap::new(),
+            status: status::Ok,
+            max_count: None,
+        },
+        "channels_list",
+    );
+    rocketchat_router.get(
+        DIRECT_MESSAGES_LIST_PATH,
+        handlers::RocketchatDirectMessagesList {
+            direct_messages: HashMap::new(),
+            status: status::Ok,
+        },
+        "direct_messages_list",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let connection = test.connection_pool.get().unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    QueuedMessage::queue(
+        &connection,
+        spec_user_id.clone(),
+        "rc_id".to_string(),
+        "spec_channel_id".to_string(),
+        "queued message".to_string(),
+    ).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "disconnect".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!("The Rocket.Chat server {} is now disconnected", test.rocketchat_mock_url.clone().unwrap());
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap();
+    assert!(rocketchat_server.is_none());
+    assert!(QueuedMessage::find_by_user(&connection, &spec_user_id, "rc_id".to_string()).unwrap().is_empty());
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let topic = matrix_api.get_room_topic(RoomId::try_from("!admin_room_id:localhost").unwrap()).unwrap();
+    assert_eq!(topic, Some("".to_string()));
+}
+
+#[test]
+fn attempt_to_disconnect_a_rocketchat_server_with_bridged_rooms() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(
+        DIRECT_MESSAGES_LIST_PATH,
+        handlers::RocketchatDirectMessagesList {
+            direct_messages: HashMap::new(),
+            status: status::Ok,
+        },
+        "direct_messages_list",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("bridged_channel", "spec_user"))
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridged message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "disconnect".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("the rooms bridged_channel are still bridged"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap();
+    assert!(rocketchat_server.is_some());
+}
+
+#[test]
+fn attempt_to_disconnect_a_rocketchat_server_that_is_used_by_other_users() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    add_other_admin_room_creator_route(&mut matrix_router);
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+    connect_other_user(&test);
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard other welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard other connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "disconnect".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("it is still used by other Matrix users (@other_user:localhost)"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap();
+    assert!(rocketchat_server.is_some());
+}
+
+#[test]
+fn attempt_to_disconnect_a_rocketchat_server_that_was_connected_by_another_user() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    add_other_admin_room_creator_route(&mut matrix_router);
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+    connect_other_user(&test);
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard other welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard other connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!other_admin_room_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+        "disconnect".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!(
+        "Only the user who connected the Rocket.Chat server {} can change or disconnect it.",
+        test.rocketchat_mock_url.clone().unwrap()
+    );
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let connection = test.connection_pool.get().unwrap();
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap();
+    assert!(rocketchat_server.is_some());
+}
+
+#[test]
+fn a_rocketchat_server_without_a_stored_creator_can_only_be_disconnected_by_the_first_user() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    add_other_admin_room_creator_route(&mut matrix_router);
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+    connect_other_user(&test);
+
+    // simulate a server that was connected before the creator was stored
+    let connection = test.connection_pool.get().unwrap();
+    diesel::update(rocketchat_servers::table.find("rc_id"))
+        .set(rocketchat_servers::created_by.eq(None::<String>))
+        .execute(&*connection)
+        .unwrap();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard other welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard other connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!other_admin_room_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+        "disconnect".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!(
+        "Only the user who connected the Rocket.Chat server {} can change or disconnect it.",
+        test.rocketchat_mock_url.clone().unwrap()
+    );
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap();
+    assert!(rocketchat_server.is_some());
+}
+
+fn add_other_admin_room_creator_route(matrix_router: &mut Router) {
+    let admin_room_creator_handler = handlers::RoomStateCreate { creator: UserId::try_from("@other_user:localhost").unwrap() };
+    let admin_room_creator_params = get_state_events_for_empty_key::PathParams {
+        room_id: RoomId::try_from("!other_admin_room_id:localhost").unwrap(),
+        event_type: EventType::RoomCreate.to_string(),
+    };
+    matrix_router.get(
+        GetStateEventsForEmptyKey::request_path(admin_room_creator_params),
+        admin_room_creator_handler,
+        "get_room_creator_admin_room",
+    );
+}
+
+fn connect_other_user(test: &Test) {
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let other_user_id = UserId::try_from("@other_user:localhost").unwrap();
+    matrix_api.create_room(Some("other_admin_room".to_string()), None, &other_user_id).unwrap();
+
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!other_admin_room_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+    );
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!other_admin_room_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+        format!("connect {}", test.rocketchat_mock_url.clone().unwrap()),
+    );
+}
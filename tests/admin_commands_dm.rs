@@ -0,0 +1,264 @@
+#![feature(try_from)]
+
+extern crate iron;
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+extern crate serde_json;
+
+use std::convert::TryFrom;
+
+use iron::status;
+use matrix_rocketchat::api::MatrixApi;
+use matrix_rocketchat::api::rocketchat::Message;
+use matrix_rocketchat::api::rocketchat::v1::{DIRECT_MESSAGES_CREATE_PATH, LOGIN_PATH, ME_PATH, USERS_INFO_PATH};
+use matrix_rocketchat::db::Room;
+use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, RS_TOKEN, Test, default_timeout, handlers, helpers};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::room::create_room::Endpoint as CreateRoomEndpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomId, UserId};
+use serde_json::to_string;
+
+#[test]
+fn successfully_start_a_direct_message_with_a_rocketchat_user() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard admin room creation
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "dm other_user".to_string(),
+    );
+
+    let create_room_message = create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#spec_user_id_other_user_id#dm\""));
+    assert!(create_room_message.contains("\"name\":\"other_user (DM Rocket.Chat)\""));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The direct message room with other_user is ready"));
+
+    let room_id = RoomId::try_from("!other_userDMRocketChat_id:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    helpers::join(&test.config, room_id.clone(), spec_user_id.clone());
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), room_id.clone(), Some(other_user_id.clone())).unwrap();
+    assert_eq!(user_ids.len(), 2);
+    assert!(user_ids.iter().any(|id| id == &other_user_id));
+    assert!(user_ids.iter().any(|id| id == &spec_user_id));
+
+    // messages from Rocket.Chat are forwarded to the room that was created by the command
+    let direct_message = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_name: None,
+        user_id: "other_user_id".to_string(),
+        user_name: "other_user".to_string(),
+        text: "Hey there".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&direct_message).unwrap());
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Hey there"));
+    assert!(create_room_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn the_existing_room_is_reused_when_the_direct_message_is_started_again() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard admin room creation
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    for _ in 0..2 {
+        helpers::send_room_message_from_matrix(
+            &test.config.as_url,
+            RoomId::try_from("!admin_room_id:localhost").unwrap(),
+            UserId::try_from("@spec_user:localhost").unwrap(),
+            "dm other_user".to_string(),
+        );
+
+        let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+        assert!(message_received_by_matrix.contains("The direct message room with other_user is ready"));
+    }
+
+    // only one room is created
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(create_room_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn attempt_to_start_a_direct_message_when_the_user_is_not_logged_in() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "dm other_user".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!("You are not logged in on {}", test.rocketchat_mock_url.clone().unwrap());
+    assert!(message_received_by_matrix.contains(&expected_message));
+}
+
+#[test]
+fn attempt_to_start_a_direct_message_when_the_rocketchat_server_returns_an_error() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        LOGIN_PATH,
+        handlers::RocketchatLogin {
+            successful: true,
+            rocketchat_user_id: Some("spec_user_id".to_string()),
+        },
+        "login",
+    );
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    rocketchat_router.get(USERS_INFO_PATH, handlers::RocketchatUsersInfo {}, "users_info");
+    rocketchat_router.post(
+        DIRECT_MESSAGES_CREATE_PATH,
+        handlers::RocketchatErrorResponder {
+            status: status::InternalServerError,
+            message: "Direct message error".to_string(),
+        },
+        "direct_messages_create",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "dm other_user".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("An internal error occurred"));
+}
+
+#[test]
+fn attempt_to_start_a_direct_message_without_a_username() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "dm".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The command `dm` was used with the wrong arguments"));
+}
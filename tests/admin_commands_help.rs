@@ -95,6 +95,8 @@ fn help_command_when_not_connected_and_someone_else_has_connected_a_server_alrea
 
     // discard other users welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard other users connect messsage
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -132,7 +134,7 @@ fn help_command_when_connected() {
     let expected_curl_command = format!("curl http://{}", test.as_listening.as_ref().unwrap().socket);
     assert!(message_received_by_matrix.contains(
         "You have to login before you can use the application service, \
-                                                there are two ways to do that",
+                                                there are three ways to do that",
     ));
     assert!(message_received_by_matrix.contains(&expected_curl_command));
 }
@@ -155,8 +157,12 @@ fn help_command_when_logged_in() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -168,3 +174,48 @@ fn help_command_when_logged_in() {
                                                 (messages are no longer forwarded)",
     ));
 }
+
+#[test]
+fn help_command_for_a_specific_command() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_admin_room().run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "help bridge".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("`bridge rocketchatroomname` Bridge a Rocket.Chat room to Matrix."));
+    assert!(message_received_by_matrix.contains("`bridge --all` Bridge all the Rocket.Chat rooms that you are a member of."));
+}
+
+#[test]
+fn help_command_for_an_unknown_command() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_admin_room().run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "help bogus".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Unknown command `bogus`, send `help` to see the available commands."));
+}
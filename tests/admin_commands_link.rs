@@ -0,0 +1,302 @@
+#![feature(try_from)]
+
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use matrix_rocketchat::api::MatrixApi;
+use matrix_rocketchat::db::{LinkedRoom, Room};
+use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, Test, default_timeout, helpers};
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomAliasId, RoomId, UserId};
+
+#[test]
+fn successfully_link_an_existing_room_to_a_rocketchat_channel() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user", "user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@other_user:localhost").unwrap();
+    let room_id = RoomId::try_from("!linked_room_id:localhost").unwrap();
+    matrix_api.create_room(Some("linked_room".to_string()), None, &spec_user_id).unwrap();
+    helpers::invite(&test.config, room_id.clone(), other_user_id.clone(), spec_user_id.clone());
+    helpers::join(&test.config, room_id.clone(), other_user_id.clone());
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "link spec_channel !linked_room_id:localhost".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains(
+        "Invite the bot user @rocketchat:localhost into the room !linked_room_id:localhost within 10 minutes",
+    ));
+
+    helpers::invite(&test.config, room_id.clone(), UserId::try_from("@rocketchat:localhost").unwrap(), spec_user_id.clone());
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The room !linked_room_id:localhost is now linked to spec_channel."));
+
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#spec_channel_id:localhost").unwrap();
+    assert_eq!(matrix_api.get_room_canonical_alias(room_id.clone()).unwrap(), Some(room_alias_id.clone()));
+    assert_eq!(matrix_api.get_room_alias(room_alias_id).unwrap(), Some(room_id.clone()));
+
+    // the existing members stay in the room
+    let user_ids = Room::user_ids(&(*matrix_api), room_id.clone(), None).unwrap();
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat:localhost").unwrap()));
+    assert!(user_ids.iter().any(|id| id == &spec_user_id));
+    assert!(user_ids.iter().any(|id| id == &other_user_id));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_spec_user_id_rc_id:localhost").unwrap()));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_user_1_id_rc_id:localhost").unwrap()));
+
+    // the linked room is not mistaken for an admin room
+    assert!(!Room::is_admin_room(&(*matrix_api), &test.config, room_id).unwrap());
+}
+
+#[test]
+fn the_canonical_alias_of_a_room_is_stored_when_it_is_linked() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user", "user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let room_id = RoomId::try_from("!linked_room_id:localhost").unwrap();
+    let team_room_alias_id = RoomAliasId::try_from("#team_room:localhost").unwrap();
+    matrix_api.create_room(Some("linked_room".to_string()), None, &spec_user_id).unwrap();
+    matrix_api
+        .put_canonical_room_alias(room_id.clone(), Some(team_room_alias_id.clone()), Some(spec_user_id.clone()))
+        .unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "link spec_channel !linked_room_id:localhost".to_string(),
+    );
+    helpers::invite(&test.config, room_id.clone(), UserId::try_from("@rocketchat:localhost").unwrap(), spec_user_id);
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard pending link message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The room !linked_room_id:localhost is now linked to spec_channel."));
+
+    let connection = test.connection_pool.get().unwrap();
+    let linked_room = LinkedRoom::find_by_matrix_room_id(&connection, &room_id).unwrap().unwrap();
+    assert_eq!(linked_room.previous_canonical_alias, Some(team_room_alias_id));
+}
+
+#[test]
+fn the_users_get_power_levels_below_the_power_level_of_the_bot_user_in_a_linked_room() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user", "user_1"]);
+    let mut room_roles = HashMap::new();
+    room_roles.insert("user_1", vec!["owner"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .with_custom_room_roles(room_roles)
+        .run();
+
+    // the bot user only gets the power level that is needed to change the power levels
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let bot_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let room_id = RoomId::try_from("!linked_room_id:localhost").unwrap();
+    matrix_api.create_room(Some("linked_room".to_string()), None, &spec_user_id).unwrap();
+    matrix_api.set_default_powerlevels(room_id.clone(), spec_user_id.clone()).unwrap();
+    let mut user_powerlevels = HashMap::new();
+    user_powerlevels.insert(bot_user_id.clone(), 50);
+    matrix_api.set_user_powerlevels(room_id.clone(), spec_user_id.clone(), user_powerlevels).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "link spec_channel !linked_room_id:localhost".to_string(),
+    );
+    helpers::invite(&test.config, room_id.clone(), bot_user_id.clone(), spec_user_id.clone());
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard pending link message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The room !linked_room_id:localhost is now linked to spec_channel."));
+
+    let power_levels = matrix_api.get_room_powerlevels(room_id).unwrap();
+    let user_1_id = UserId::try_from("@rocketchat_user_1_id_rc_id:localhost").unwrap();
+    assert_eq!(power_levels.user_powerlevel(&bot_user_id), 50);
+    assert_eq!(power_levels.user_powerlevel(&user_1_id), 49);
+    assert_eq!(power_levels.user_powerlevel(&spec_user_id), 100);
+}
+
+#[test]
+fn attempt_to_link_a_room_without_the_power_level_to_change_the_power_levels() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    // the room is owned by another user, spec_user only has the default power level
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let room_owner_id = UserId::try_from("@rocketchat_room_owner:localhost").unwrap();
+    let room_id = RoomId::try_from("!linked_room_id:localhost").unwrap();
+    matrix_api.create_room(Some("linked_room".to_string()), None, &room_owner_id).unwrap();
+    matrix_api.set_default_powerlevels(room_id.clone(), room_owner_id.clone()).unwrap();
+    helpers::invite(&test.config, room_id.clone(), spec_user_id.clone(), room_owner_id.clone());
+    helpers::join(&test.config, room_id.clone(), spec_user_id.clone());
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "link spec_channel !linked_room_id:localhost".to_string(),
+    );
+
+    // discard pending link message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::invite(&test.config, room_id.clone(), UserId::try_from("@rocketchat:localhost").unwrap(), spec_user_id);
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains(
+        "You need the power level 50 in the room !linked_room_id:localhost to link it",
+    ));
+
+    let user_ids = Room::user_ids(&(*matrix_api), room_id.clone(), Some(room_owner_id)).unwrap();
+    assert!(!user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat:localhost").unwrap()));
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#spec_channel_id:localhost").unwrap();
+    assert!(matrix_api.get_room_alias(room_alias_id).unwrap().is_none());
+}
+
+#[test]
+fn attempt_to_link_a_room_that_does_not_exist() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "link spec_channel #unknown_room:localhost".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("No Matrix room with the ID or alias #unknown_room:localhost found."));
+}
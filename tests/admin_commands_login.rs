@@ -16,11 +16,12 @@ use iron::status;
 use matrix_rocketchat::api::{MatrixApi, RestApi};
 use matrix_rocketchat::api::rocketchat::v1::{LOGIN_PATH, ME_PATH};
 use matrix_rocketchat::db::{RocketchatServer, UserOnRocketchatServer};
-use matrix_rocketchat::handlers::rocketchat::Credentials;
+use matrix_rocketchat::handlers::rocketchat::{AccessTokenCredentials, Credentials};
 use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, Test, default_timeout, handlers, helpers};
 use reqwest::Method;
 use router::Router;
 use ruma_client_api::Endpoint;
+use ruma_client_api::r0::redact::redact_event::Endpoint as RedactEventEndpoint;
 use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
 use ruma_identifiers::{RoomId, UserId};
 use serde_json::to_string;
@@ -56,18 +57,483 @@ fn sucessfully_login_via_chat_mesage() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let connection = test.connection_pool.get().unwrap();
     let rocketchat_server = RocketchatServer::find(&connection, test.rocketchat_mock_url.clone().unwrap()).unwrap();
     let user_on_rocketchat_server =
         UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), rocketchat_server.id)
             .unwrap();
-    assert_eq!(user_on_rocketchat_server.rocketchat_auth_token.unwrap(), "spec_auth_token");
+    let encryption_key = test.config.database_encryption_key().unwrap();
+    assert_eq!(user_on_rocketchat_server.decrypted_auth_token(encryption_key.as_ref()).unwrap(), "spec_auth_token");
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged in."));
+}
+
+#[test]
+fn login_via_chat_message_with_a_quoted_password_that_contains_spaces() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let (login_forwarder, login_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, login_forwarder, "login");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        r#"login spec_user "my \"secret\" password""#.to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let login_payload = login_receiver.recv_timeout(default_timeout()).unwrap();
+    let login_request: HashMap<String, String> = serde_json::from_str(&login_payload).unwrap();
+    assert_eq!(login_request.get("username").unwrap(), "spec_user");
+    assert_eq!(login_request.get("password").unwrap(), r#"my "secret" password"#);
+}
+
+#[test]
+fn login_via_chat_message_keeps_backslashes_that_do_not_escape_anything() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let (login_forwarder, login_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, login_forwarder, "login");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        r#"login spec_user pa\ss\"#.to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let login_payload = login_receiver.recv_timeout(default_timeout()).unwrap();
+    let login_request: HashMap<String, String> = serde_json::from_str(&login_payload).unwrap();
+    assert_eq!(login_request.get("username").unwrap(), "spec_user");
+    assert_eq!(login_request.get("password").unwrap(), r#"pa\ss\"#);
+}
+
+#[test]
+fn login_via_chat_message_with_a_password_that_contains_an_apostrophe() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let (login_forwarder, login_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, login_forwarder, "login");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user pa'ss".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let login_payload = login_receiver.recv_timeout(default_timeout()).unwrap();
+    let login_request: HashMap<String, String> = serde_json::from_str(&login_payload).unwrap();
+    assert_eq!(login_request.get("username").unwrap(), "spec_user");
+    assert_eq!(login_request.get("password").unwrap(), "pa'ss");
+}
+
+#[test]
+fn attempt_to_login_via_chat_message_without_a_password() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains(
+        "The command `login` was used with the wrong arguments, send `help login` to see how to use it.",
+    ));
+}
+
+#[test]
+fn the_login_message_is_redacted_because_it_contains_credentials() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (redact_forwarder, redact_receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.put(RedactEventEndpoint::router_path(), redact_forwarder, "redact_event");
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard the redaction of the connect message
+    redact_receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    let redact_payload = redact_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(redact_payload.contains("The message contains credentials"));
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Your message contains credentials, it was removed from the room history."));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged in."));
+}
+
+#[test]
+fn the_user_gets_a_message_when_the_login_message_cannot_be_redacted() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.put(
+        RedactEventEndpoint::router_path(),
+        handlers::MatrixErrorResponder {
+            status: status::Forbidden,
+            message: "You don't have permission to redact events".to_string(),
+        },
+        "redact_event",
+    );
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains(
+        "Your message contains credentials, but it could not be removed from the room history. \
+         Please delete the message yourself."
+    ));
+
+    // the login is executed even if the message could not be redacted
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged in."));
+}
+
+#[test]
+fn sucessfully_login_with_a_personal_access_token_via_chat_message() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login --token spec_rocketchat_user_id spec_access_token".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged in."));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert_eq!(user_on_rocketchat_server.rocketchat_user_id.unwrap(), "spec_rocketchat_user_id");
+    let encryption_key = test.config.database_encryption_key().unwrap();
+    assert_eq!(user_on_rocketchat_server.decrypted_auth_token(encryption_key.as_ref()).unwrap(), "spec_access_token");
+    assert_eq!(user_on_rocketchat_server.rocketchat_username.unwrap(), "spec_user");
+}
+
+#[test]
+fn attempt_to_login_with_an_invalid_personal_access_token_via_chat_message() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(
+        ME_PATH,
+        handlers::RocketchatErrorResponder {
+            status: status::Unauthorized,
+            message: "You must be logged in to do this.".to_string(),
+        },
+        "me",
+    );
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login --token spec_rocketchat_user_id invalid_access_token".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Authentication failed!"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(!user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn sucessfully_login_with_two_factor_authentication_via_chat_message() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, handlers::RocketchatTwoFactorLogin { totp_code: "123456" }, "login");
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Two-factor authentication is enabled for your account."));
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login --code 123456".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("You are logged in."));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn the_user_can_retry_when_the_two_factor_authentication_code_is_invalid() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, handlers::RocketchatTwoFactorLogin { totp_code: "123456" }, "login");
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Two-factor authentication is enabled for your account."));
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login --code 654321".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The two-factor authentication code is invalid, please try again."));
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login --code 123456".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged in."));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn attempt_to_send_a_two_factor_authentication_code_without_a_pending_login() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, handlers::RocketchatTwoFactorLogin { totp_code: "123456" }, "login");
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login --code 123456".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("There is no login that waits for a two-factor authentication code."));
 }
 
 #[test]
@@ -100,8 +566,12 @@ fn wrong_password_when_logging_in_via_chat_message() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("Authentication failed!"));
@@ -131,6 +601,8 @@ fn login_multiple_times_via_chat_message() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -142,6 +614,9 @@ fn login_multiple_times_via_chat_message() {
             "login spec_user secret".to_string(),
         );
 
+        // discard redaction notice
+        receiver.recv_timeout(default_timeout()).unwrap();
+
         let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
         assert!(message_received_by_matrix.contains("You are logged in."));
     }
@@ -167,6 +642,7 @@ fn sucessfully_login_via_rest_api() {
         rocketchat_username: "spec_user".to_string(),
         password: "secret".to_string(),
         rocketchat_url: test.rocketchat_mock_url.clone().unwrap(),
+        totp_code: None,
     };
     let payload = to_string(&login_request).unwrap();
     let (response, status_code) = RestApi::call(
@@ -187,7 +663,44 @@ fn sucessfully_login_via_rest_api() {
     let user_on_rocketchat_server =
         UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), rocketchat_server.id)
             .unwrap();
-    assert_eq!(user_on_rocketchat_server.rocketchat_auth_token.unwrap(), "spec_auth_token");
+    let encryption_key = test.config.database_encryption_key().unwrap();
+    assert_eq!(user_on_rocketchat_server.decrypted_auth_token(encryption_key.as_ref()).unwrap(), "spec_auth_token");
+}
+
+#[test]
+fn sucessfully_login_with_a_personal_access_token_via_rest_api() {
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test =
+        Test::new().with_rocketchat_mock().with_custom_rocketchat_routes(rocketchat_router).with_connected_admin_room().run();
+
+    let login_request = AccessTokenCredentials {
+        matrix_user_id: UserId::try_from("@spec_user:localhost").unwrap(),
+        rocketchat_user_id: "spec_rocketchat_user_id".to_string(),
+        access_token: "spec_access_token".to_string(),
+        rocketchat_url: test.rocketchat_mock_url.clone().unwrap(),
+    };
+    let payload = to_string(&login_request).unwrap();
+    let (response, status_code) = RestApi::call(
+        Method::Post,
+        &format!("http://{}/rocketchat/login", test.as_listening.as_ref().unwrap().socket),
+        &payload,
+        &HashMap::new(),
+        None,
+    ).unwrap();
+
+    assert!(response.contains(
+        "You are logged in. Return to your Matrix client and enter help in the admin room for more instructions.",
+    ));
+    assert!(status_code.is_success());
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert_eq!(user_on_rocketchat_server.rocketchat_user_id.unwrap(), "spec_rocketchat_user_id");
+    let encryption_key = test.config.database_encryption_key().unwrap();
+    assert_eq!(user_on_rocketchat_server.decrypted_auth_token(encryption_key.as_ref()).unwrap(), "spec_access_token");
 }
 
 #[test]
@@ -209,6 +722,7 @@ fn wrong_password_when_logging_in_via_rest_api() {
         rocketchat_username: "spec_user".to_string(),
         password: "wrong_password".to_string(),
         rocketchat_url: test.rocketchat_mock_url.clone().unwrap(),
+        totp_code: None,
     };
     let payload = to_string(&login_request).unwrap();
     let (response, status_code) = RestApi::call(
@@ -246,6 +760,8 @@ fn login_multiple_times_via_rest_message() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -254,6 +770,7 @@ fn login_multiple_times_via_rest_message() {
         rocketchat_username: "spec_user".to_string(),
         password: "secret".to_string(),
         rocketchat_url: test.rocketchat_mock_url.clone().unwrap(),
+        totp_code: None,
     };
     let payload = to_string(&login_request).unwrap();
 
@@ -298,6 +815,23 @@ fn login_via_rest_api_with_invalid_payload() {
     assert_eq!(status_code, status::UnprocessableEntity);
 }
 
+#[test]
+fn the_password_is_not_included_in_the_error_when_the_login_payload_is_invalid() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().run();
+
+    let (response, status_code) = RestApi::call(
+        Method::Post,
+        &format!("http://{}/rocketchat/login", test.as_listening.as_ref().unwrap().socket),
+        r#"{"username": "spec_user", "password": "very_secret_password"}"#,
+        &HashMap::new(),
+        None,
+    ).unwrap();
+    assert_eq!(status_code, status::UnprocessableEntity);
+    assert!(response.contains("Could not process request, the submitted data is not valid"));
+    assert!(response.contains("[REDACTED]"));
+    assert!(!response.contains("very_secret_password"));
+}
+
 #[test]
 fn login_via_rest_api_with_a_non_existing_rocketchat_server() {
     let test = Test::new().run();
@@ -307,6 +841,7 @@ fn login_via_rest_api_with_a_non_existing_rocketchat_server() {
         rocketchat_username: "spec_user".to_string(),
         password: "secret".to_string(),
         rocketchat_url: "http://nonexisting.foo".to_string(),
+        totp_code: None,
     };
     let payload = to_string(&login_request).unwrap();
 
@@ -380,14 +915,20 @@ fn the_user_can_login_again_on_the_same_server_with_a_new_admin_room() {
 
     // discard first welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard first connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard first login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard second welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard second connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("You are logged in."));
@@ -419,8 +960,12 @@ fn server_does_not_respond_when_logging_in_via_chat_mesage() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
@@ -449,8 +994,12 @@ fn the_user_gets_a_message_when_the_login_response_cannot_be_deserialized() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
@@ -486,8 +1035,12 @@ fn the_user_gets_a_message_when_the_login_returns_an_error() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
@@ -525,8 +1078,12 @@ fn the_user_gets_a_message_when_the_me_response_cannot_be_deserialized() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
@@ -570,8 +1127,12 @@ fn the_user_gets_a_message_when_the_me_endpoint_returns_an_error() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
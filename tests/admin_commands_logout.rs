@@ -0,0 +1,208 @@
+#![feature(try_from)]
+
+extern crate iron;
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::convert::TryFrom;
+
+use iron::status;
+use matrix_rocketchat::api::rocketchat::v1::LOGOUT_PATH;
+use matrix_rocketchat::db::{QueuedMessage, UserOnRocketchatServer};
+use matrix_rocketchat_test::{MessageForwarder, Test, default_timeout, handlers, helpers};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomId, UserId};
+
+#[test]
+fn successfully_logout_from_the_rocketchat_server() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let (logout_forwarder, logout_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(LOGOUT_PATH, logout_forwarder, "logout");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let connection = test.connection_pool.get().unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    QueuedMessage::queue(
+        &connection,
+        spec_user_id.clone(),
+        "rc_id".to_string(),
+        "spec_channel_id".to_string(),
+        "queued message".to_string(),
+    ).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "logout".to_string(),
+    );
+
+    // the logout endpoint on the Rocket.Chat server was called
+    logout_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!("You are logged out from {}", test.rocketchat_mock_url.clone().unwrap());
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let user_on_rocketchat_server = UserOnRocketchatServer::find(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    assert!(!user_on_rocketchat_server.is_logged_in());
+    assert!(user_on_rocketchat_server.rocketchat_user_id.is_none());
+    assert!(user_on_rocketchat_server.rocketchat_auth_token.is_none());
+    assert!(QueuedMessage::find_by_user(&connection, &spec_user_id, "rc_id".to_string()).unwrap().is_empty());
+}
+
+#[test]
+fn the_credentials_are_removed_when_the_auth_token_is_already_invalid() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(
+        LOGOUT_PATH,
+        handlers::RocketchatErrorResponder {
+            message: "You must be logged in to do this.".to_string(),
+            status: status::Unauthorized,
+        },
+        "logout",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "logout".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged out from"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(!user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn the_user_stays_logged_in_when_the_logout_failes() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(
+        LOGOUT_PATH,
+        handlers::RocketchatErrorResponder {
+            message: "Logout error".to_string(),
+            status: status::InternalServerError,
+        },
+        "logout",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "logout".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("An internal error occurred"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn attempt_to_logout_when_the_user_is_not_logged_in() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "logout".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!("You are not logged in on {}", test.rocketchat_mock_url.clone().unwrap());
+    assert!(message_received_by_matrix.contains(&expected_message));
+}
@@ -16,14 +16,14 @@ use iron::status;
 use matrix_rocketchat::api::MatrixApi;
 use matrix_rocketchat::api::rocketchat::Message;
 use matrix_rocketchat::api::rocketchat::v1::{LOGIN_PATH, ME_PATH};
-use matrix_rocketchat::db::Room;
 use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, RS_TOKEN, Test, default_timeout, handlers, helpers};
 use ruma_client_api::Endpoint;
 use ruma_client_api::r0::alias::delete_alias::Endpoint as DeleteAliasEndpoint;
+use ruma_client_api::r0::membership::leave_room::Endpoint as LeaveRoomEndpoint;
 use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
 use ruma_client_api::r0::sync::get_state_events_for_empty_key::{self, Endpoint as GetStateEventsForEmptyKey};
 use ruma_events::EventType;
-use ruma_identifiers::{RoomId, UserId};
+use ruma_identifiers::{RoomAliasId, RoomId, UserId};
 use router::Router;
 use serde_json::to_string;
 
@@ -34,11 +34,15 @@ fn successfully_unbridge_a_rocketchat_room() {
     let mut matrix_router = test.default_matrix_routes();
     matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
 
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
+
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
         .with_connected_admin_room()
         .with_logged_in_user()
         .with_bridged_room(("bridged_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
         .run();
 
     // send message to create a virtual user
@@ -50,17 +54,12 @@ fn successfully_unbridge_a_rocketchat_room() {
         user_id: "new_user_id".to_string(),
         user_name: "new_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
     helpers::simulate_message_from_rocketchat(&test.config.as_url, &payload);
 
-    helpers::leave_room(
-        &test.config,
-        RoomId::try_from("!bridged_channel_id:localhost").unwrap(),
-        UserId::try_from("@spec_user:localhost").unwrap(),
-    );
-
     helpers::send_room_message_from_matrix(
         &test.config.as_url,
         RoomId::try_from("!admin_room_id:localhost").unwrap(),
@@ -70,8 +69,12 @@ fn successfully_unbridge_a_rocketchat_room() {
 
     // discard welcome message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard bridged message
@@ -79,22 +82,36 @@ fn successfully_unbridge_a_rocketchat_room() {
     // discard message from virtual user
     receiver.recv_timeout(default_timeout()).unwrap();
 
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("This room is no longer bridged to the Rocket.Chat channel bridged_channel"));
+
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("bridged_channel is now unbridged."));
 
     let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
-    let user_ids = Room::user_ids(&(*matrix_api), RoomId::try_from("!bridged_channel_id:localhost").unwrap(), None).unwrap();
+    let room_id = RoomId::try_from("!bridged_channel_id:localhost").unwrap();
     let rocketchat_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
     let new_user_id = UserId::try_from("@rocketchat_new_user_id_rc_id:localhost").unwrap();
     let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
 
-    assert!(user_ids.iter().any(|id| id == &rocketchat_user_id));
-    assert!(user_ids.iter().any(|id| id == &new_user_id));
-    assert!(!user_ids.iter().any(|id| id == &spec_user_id));
+    // messages are no longer forwarded in either direction
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#bridged_channel_id:localhost").unwrap();
+    assert!(matrix_api.get_room_canonical_alias(room_id.clone()).unwrap().is_none());
+    assert!(matrix_api.get_room_alias(room_alias_id).unwrap().is_none());
+
+    // the bot user and the virtual users gave up their power levels and left the room
+    let power_levels = matrix_api.get_room_powerlevels(room_id.clone()).unwrap();
+    assert_eq!(power_levels.user_powerlevel(&rocketchat_user_id), 0);
+    assert_eq!(power_levels.user_powerlevel(&new_user_id), 0);
+    assert!(matrix_api.send_text_message_event(room_id.clone(), rocketchat_user_id, "spec".to_string()).is_err());
+
+    // the room was created by the bridge and is handed over to the user that unbridged it
+    assert_eq!(power_levels.user_powerlevel(&spec_user_id), 100);
+    assert!(matrix_api.send_text_message_event(room_id, new_user_id, "spec".to_string()).is_err());
 }
 
 #[test]
-fn do_not_allow_to_unbridge_a_channel_with_other_matrix_users() {
+fn unbridge_a_channel_while_matrix_users_are_in_the_room() {
     let test = Test::new();
     let (message_forwarder, receiver) = MessageForwarder::new();
     let mut matrix_router = test.default_matrix_routes();
@@ -120,7 +137,8 @@ fn do_not_allow_to_unbridge_a_channel_with_other_matrix_users() {
         "login",
     );
     rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
-
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
 
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
@@ -128,6 +146,7 @@ fn do_not_allow_to_unbridge_a_channel_with_other_matrix_users() {
         .with_connected_admin_room()
         .with_logged_in_user()
         .with_bridged_room(("bridged_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
         .run();
 
     // create other admin room
@@ -175,8 +194,12 @@ fn do_not_allow_to_unbridge_a_channel_with_other_matrix_users() {
 
     // discard welcome message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message for spec user
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard bridged message for spec user
@@ -186,6 +209,8 @@ fn do_not_allow_to_unbridge_a_channel_with_other_matrix_users() {
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message for other user
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message for other user
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard bridged message for other user
@@ -199,12 +224,118 @@ fn do_not_allow_to_unbridge_a_channel_with_other_matrix_users() {
     );
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
-    assert!(message_received_by_matrix.contains("Cannot unbdrige room bridged_channel, because Matrix users"));
-    assert!(message_received_by_matrix.contains("@spec_user:localhost"));
-    assert!(message_received_by_matrix.contains("@other_user:localhost"));
-    assert!(message_received_by_matrix.contains(
-        "are still using the room. All Matrix users have to leave a room before it can be unbridged.",
-    ));
+    assert!(message_received_by_matrix.contains("This room is no longer bridged to the Rocket.Chat channel bridged_channel"));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("bridged_channel is now unbridged."));
+
+    // the Matrix users stay in the room, only the bot user leaves
+    let room_id = RoomId::try_from("!bridged_channel_id:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@other_user:localhost").unwrap();
+    let rocketchat_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    assert!(matrix_api.send_text_message_event(room_id.clone(), spec_user_id, "spec".to_string()).is_ok());
+    assert!(matrix_api.send_text_message_event(room_id.clone(), other_user_id, "spec".to_string()).is_ok());
+    assert!(matrix_api.send_text_message_event(room_id, rocketchat_user_id, "spec".to_string()).is_err());
+}
+
+#[test]
+fn a_user_that_cannot_change_the_power_levels_and_is_no_channel_owner_cannot_unbridge_a_channel() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("bridged_channel", "spec_user"))
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "unbridge bridged_channel".to_string(),
+    );
+
+    // discard welcome message for spec user
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message for spec user
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message for spec user
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridged message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You cannot unbridge the channel bridged_channel."));
+
+    // the room is still bridged and the bot user keeps its power level
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_id = RoomId::try_from("!bridged_channel_id:localhost").unwrap();
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#bridged_channel_id:localhost").unwrap();
+    assert_eq!(matrix_api.get_room_canonical_alias(room_id.clone()).unwrap(), Some(room_alias_id));
+    let power_levels = matrix_api.get_room_powerlevels(room_id).unwrap();
+    assert_eq!(power_levels.user_powerlevel(&UserId::try_from("@rocketchat:localhost").unwrap()), 100);
+    assert_eq!(power_levels.user_powerlevel(&UserId::try_from("@spec_user:localhost").unwrap()), 0);
+}
+
+#[test]
+fn a_channel_owner_that_is_not_in_the_room_cannot_unbridge_a_channel() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("bridged_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
+        .run();
+
+    helpers::leave_room(
+        &test.config,
+        RoomId::try_from("!bridged_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "unbridge bridged_channel".to_string(),
+    );
+
+    // discard welcome message for spec user
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message for spec user
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message for spec user
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridged message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You cannot unbridge the channel bridged_channel."));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_id = RoomId::try_from("!bridged_channel_id:localhost").unwrap();
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#bridged_channel_id:localhost").unwrap();
+    assert_eq!(matrix_api.get_room_canonical_alias(room_id).unwrap(), Some(room_alias_id));
 }
 
 #[test]
@@ -224,8 +355,12 @@ fn attempting_to_unbridge_a_non_existing_channel_returns_an_error() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -257,8 +392,12 @@ fn attempting_to_unbridge_an_channel_that_is_not_bridged_returns_an_error() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -287,23 +426,77 @@ fn room_is_not_unbridged_when_deleting_the_room_alias_failes() {
         },
         "delete_room_alias",
     );
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
+
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
         .with_connected_admin_room()
         .with_logged_in_user()
         .with_bridged_room(("bridged_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
         .run();
 
-    helpers::leave_room(
-        &test.config,
-        RoomId::try_from("!bridged_channel_id:localhost").unwrap(),
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridge message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
         UserId::try_from("@spec_user:localhost").unwrap(),
+        "unbridge bridged_channel".to_string(),
     );
 
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("An internal error occurred"));
+
+    // the canonical alias is restored, so that messages are still forwarded in both directions
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#bridged_channel_id:localhost").unwrap();
+    let room_canonical_alias = matrix_api.get_room_canonical_alias(RoomId::try_from("!bridged_channel_id:localhost").unwrap());
+    assert_eq!(room_canonical_alias.unwrap(), Some(room_alias_id));
+}
+
+#[test]
+fn the_room_is_unbridged_when_the_cleanup_after_deleting_the_room_alias_failes() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let error_responder = handlers::MatrixErrorResponder {
+        status: status::InternalServerError,
+        message: "Could not leave room".to_string(),
+    };
+    matrix_router.post(LeaveRoomEndpoint::router_path(), error_responder, "leave_room");
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("bridged_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
+        .run();
+
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard bridge message
@@ -317,5 +510,161 @@ fn room_is_not_unbridged_when_deleting_the_room_alias_failes() {
     );
 
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
-    assert!(message_received_by_matrix.contains("An internal error occurred"));
+    assert!(message_received_by_matrix.contains("This room is no longer bridged to the Rocket.Chat channel bridged_channel"));
+
+    // the bot user could not leave the room, but the room is detached anyway
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("bridged_channel is now unbridged."));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_id = RoomId::try_from("!bridged_channel_id:localhost").unwrap();
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#bridged_channel_id:localhost").unwrap();
+    assert!(matrix_api.get_room_canonical_alias(room_id.clone()).unwrap().is_none());
+    assert!(matrix_api.get_room_alias(room_alias_id).unwrap().is_none());
+    let power_levels = matrix_api.get_room_powerlevels(room_id).unwrap();
+    assert_eq!(power_levels.user_powerlevel(&UserId::try_from("@rocketchat:localhost").unwrap()), 0);
+    assert_eq!(power_levels.user_powerlevel(&UserId::try_from("@spec_user:localhost").unwrap()), 100);
+}
+
+#[test]
+fn unbridge_a_linked_room() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user", "user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let rocketchat_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let room_id = RoomId::try_from("!linked_room_id:localhost").unwrap();
+    matrix_api.create_room(Some("linked_room".to_string()), None, &spec_user_id).unwrap();
+    let team_room_alias_id = RoomAliasId::try_from("#team_room:localhost").unwrap();
+    matrix_api
+        .put_canonical_room_alias(room_id.clone(), Some(team_room_alias_id.clone()), Some(spec_user_id.clone()))
+        .unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "link spec_channel !linked_room_id:localhost".to_string(),
+    );
+    helpers::invite(&test.config, room_id.clone(), rocketchat_user_id.clone(), spec_user_id.clone());
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard pending link message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard linked message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "unbridge spec_channel".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("This room is no longer bridged to the Rocket.Chat channel spec_channel"));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("spec_channel is now unbridged."));
+
+    // the room is detached and gets back its canonical alias, the room creator keeps using it
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#spec_channel_id:localhost").unwrap();
+    assert!(matrix_api.get_room_alias(room_alias_id).unwrap().is_none());
+    assert_eq!(matrix_api.get_room_canonical_alias(room_id.clone()).unwrap(), Some(team_room_alias_id));
+    let user_1_id = UserId::try_from("@rocketchat_user_1_id_rc_id:localhost").unwrap();
+    assert!(matrix_api.send_text_message_event(room_id.clone(), user_1_id, "spec".to_string()).is_err());
+    assert!(matrix_api.send_text_message_event(room_id.clone(), rocketchat_user_id, "spec".to_string()).is_err());
+    assert!(matrix_api.send_text_message_event(room_id, spec_user_id, "spec".to_string()).is_ok());
+}
+
+#[test]
+fn matrix_users_whose_name_starts_with_the_bot_localpart_stay_in_the_room_when_unbridging() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let mut channels = HashMap::new();
+    channels.insert("spec_channel", vec!["spec_user", "user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let rocketchat_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let room_id = RoomId::try_from("!linked_room_id:localhost").unwrap();
+    matrix_api.create_room(Some("linked_room".to_string()), None, &spec_user_id).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "link spec_channel !linked_room_id:localhost".to_string(),
+    );
+    helpers::invite(&test.config, room_id.clone(), rocketchat_user_id.clone(), spec_user_id.clone());
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard pending link message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard linked message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let fan_user_id = UserId::try_from("@rocketchatfan:localhost").unwrap();
+    helpers::invite(&test.config, room_id.clone(), fan_user_id.clone(), spec_user_id.clone());
+    helpers::join(&test.config, room_id.clone(), fan_user_id.clone());
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        spec_user_id.clone(),
+        "unbridge spec_channel".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("This room is no longer bridged to the Rocket.Chat channel spec_channel"));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("spec_channel is now unbridged."));
+
+    // only the bot user and the virtual users left the room
+    let user_1_id = UserId::try_from("@rocketchat_user_1_id_rc_id:localhost").unwrap();
+    assert!(matrix_api.send_text_message_event(room_id.clone(), user_1_id, "spec".to_string()).is_err());
+    assert!(matrix_api.send_text_message_event(room_id.clone(), rocketchat_user_id, "spec".to_string()).is_err());
+    assert!(matrix_api.send_text_message_event(room_id, fan_user_id, "spec".to_string()).is_ok());
 }
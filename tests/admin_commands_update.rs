@@ -0,0 +1,199 @@
+#![feature(try_from)]
+
+extern crate iron;
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::convert::TryFrom;
+use std::sync::mpsc::channel;
+use std::thread;
+
+use iron::{Iron, Listening};
+use matrix_rocketchat::api::MatrixApi;
+use matrix_rocketchat::db::RocketchatServer;
+use matrix_rocketchat_test::{DEFAULT_LOGGER, DEFAULT_ROCKETCHAT_VERSION, IRON_THREADS, MessageForwarder, RS_TOKEN, Test,
+                             default_timeout, get_free_socket_addr, handlers, helpers};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomId, UserId};
+
+#[test]
+fn successfully_update_the_rocketchat_server_url() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let (tx, rx) = channel::<Listening>();
+    let socket_addr = get_free_socket_addr();
+
+    thread::spawn(move || {
+        let mut rocketchat_router = Router::new();
+        rocketchat_router.get("/api/info", handlers::RocketchatInfo { version: DEFAULT_ROCKETCHAT_VERSION }, "info");
+        let mut server = Iron::new(rocketchat_router);
+        server.threads = IRON_THREADS;
+        let listening = server.http(&socket_addr).unwrap();
+        tx.send(listening).unwrap();
+    });
+    let mut listening = rx.recv_timeout(default_timeout() * 2).unwrap();
+    let new_rocketchat_url = format!("http://{}", socket_addr);
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        format!("update url {}", new_rocketchat_url),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    listening.close().unwrap();
+
+    let expected_message = format!("The URL of the Rocket.Chat server was changed to {}", new_rocketchat_url);
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let connection = test.connection_pool.get().unwrap();
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap().unwrap();
+    assert_eq!(rocketchat_server.rocketchat_url, new_rocketchat_url);
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let topic = matrix_api.get_room_topic(RoomId::try_from("!admin_room_id:localhost").unwrap()).unwrap();
+    assert_eq!(topic, Some(new_rocketchat_url));
+}
+
+#[test]
+fn attempt_to_update_the_rocketchat_server_url_to_a_non_existing_server() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "update url http://127.0.0.1:1".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Could not reach Rocket.Chat server http://127.0.0.1:1"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let rocketchat_server = RocketchatServer::find_by_id(&connection, "rc_id").unwrap().unwrap();
+    assert_eq!(rocketchat_server.rocketchat_url, test.rocketchat_mock_url.clone().unwrap());
+}
+
+#[test]
+fn successfully_update_the_rocketchat_server_token() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "update token new_token".to_string(),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!("The token for {} was changed", test.rocketchat_mock_url.clone().unwrap());
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let connection = test.connection_pool.get().unwrap();
+    let encryption_key = test.config.database_encryption_key().unwrap();
+    let rocketchat_server =
+        RocketchatServer::find_by_token(&connection, encryption_key.as_ref(), "new_token".to_string()).unwrap().unwrap();
+    assert_eq!(rocketchat_server.id, "rc_id");
+}
+
+#[test]
+fn attempt_to_update_the_rocketchat_server_token_with_a_token_that_is_already_in_use() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        format!("update token {}", RS_TOKEN),
+    );
+
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("The token is already in use, please use another token."));
+}
+
+#[test]
+fn attempt_to_update_the_rocketchat_server_with_an_unknown_subcommand() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "update name new_name".to_string(),
+    );
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Unknown update command"));
+}
@@ -40,3 +40,27 @@ fn read_config_from_file() {
     assert_eq!(config.accept_remote_invites, true);
     assert_eq!(config.use_ssl, false);
 }
+
+#[test]
+fn read_config_with_a_power_level_that_is_not_lower_than_the_power_level_of_the_bot() {
+    let config_data = r#"hs_token: "hs_token"
+                        as_token: "as_token"
+                        as_address: "127.0.0.1:8088"
+                        as_url: "http://localhost:8088"
+                        hs_url: "http://localhost:8008"
+                        hs_domain: "matrix.local"
+                        sender_localpart: "rocketchat"
+                        database_url: "./database.sqlite3"
+                        accept_remote_invites: true
+                        use_ssl: false
+                        power_levels:
+                          owner: 100"#
+        .replace("                        ", ""); // remove the indentation of the raw string
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let config_path = temp_dir.path().join("test.config");
+
+    let mut config_file = File::create(&config_path).unwrap();
+    config_file.write_all(config_data.as_bytes()).unwrap();
+    let err = Config::read_from_file(config_path.to_str().unwrap()).unwrap_err();
+    assert_eq!(format!("{}", err), "The power level 100 for the role owner is not valid, it has to be lower than 100");
+}
@@ -0,0 +1,231 @@
+#![feature(try_from)]
+
+extern crate diesel;
+extern crate matrix_rocketchat;
+#[macro_use]
+extern crate matrix_rocketchat_test;
+extern crate ruma_identifiers;
+
+use std::convert::TryFrom;
+
+use diesel::prelude::*;
+use diesel::sqlite::SqliteConnection;
+use matrix_rocketchat::Config;
+use matrix_rocketchat::db::{RocketchatServer, UserOnRocketchatServer};
+use matrix_rocketchat::db::encryption::{self, EncryptionKey};
+use matrix_rocketchat::db::schema::{rocketchat_servers, users_on_rocketchat_servers};
+use matrix_rocketchat::errors::*;
+use matrix_rocketchat_test::{DATABASE_ENCRYPTION_KEY, RS_TOKEN, Test};
+use ruma_identifiers::UserId;
+
+const NEW_DATABASE_ENCRYPTION_KEY: &'static str = "Gd4Nwd0Gdi2wTYOAZfqtxd9P0SjtRx7CKuTPlwWBtpc=";
+
+#[test]
+fn the_tokens_are_encrypted_in_the_database() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    let stored_rocketchat_token = stored_rocketchat_token(&connection);
+    assert!(encryption::is_encrypted(&stored_rocketchat_token));
+    assert!(!stored_rocketchat_token.contains(RS_TOKEN));
+
+    let stored_auth_token = stored_auth_token(&connection);
+    assert!(encryption::is_encrypted(&stored_auth_token));
+    assert!(!stored_auth_token.contains("spec_auth_token"));
+
+    // the server is found by the keyed hash of its token
+    let encryption_key = EncryptionKey::from_base64(DATABASE_ENCRYPTION_KEY).unwrap();
+    let rocketchat_server =
+        RocketchatServer::find_by_token(&connection, Some(&encryption_key), RS_TOKEN.to_string()).unwrap().unwrap();
+    assert_eq!(rocketchat_server.id, "rc_id");
+    assert_eq!(rocketchat_server.rocketchat_token_hash, Some(encryption_key.hash(RS_TOKEN).unwrap()));
+
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert_eq!(user_on_rocketchat_server.decrypted_auth_token(Some(&encryption_key)).unwrap(), "spec_auth_token");
+}
+
+#[test]
+fn the_tokens_are_stored_in_plaintext_if_no_encryption_key_is_configured() {
+    let test = Test::new();
+    let config = Config {
+        database_encryption_key: None,
+        ..test.config.clone()
+    };
+    let test = test.with_custom_config(config)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+    let connection = test.connection_pool.get().unwrap();
+
+    assert_eq!(stored_rocketchat_token(&connection), RS_TOKEN);
+    assert_eq!(stored_auth_token(&connection), "spec_auth_token");
+    assert!(!encryption::has_encrypted_tokens(&connection).unwrap());
+
+    let rocketchat_server = RocketchatServer::find_by_token(&connection, None, RS_TOKEN.to_string()).unwrap().unwrap();
+    assert_eq!(rocketchat_server.id, "rc_id");
+    assert!(rocketchat_server.rocketchat_token_hash.is_none());
+}
+
+#[test]
+fn encrypted_tokens_cannot_be_read_without_a_key() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    assert!(encryption::has_encrypted_tokens(&connection).unwrap());
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    let err = user_on_rocketchat_server.decrypted_auth_token(None).unwrap_err();
+    assert_error_kind!(err, ErrorKind::DatabaseEncryptionKeyMissing);
+}
+
+#[test]
+fn plaintext_tokens_are_encrypted_by_the_migration() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    // simulate a database that was created before the tokens were encrypted
+    diesel::update(rocketchat_servers::table.find("rc_id"))
+        .set((
+            rocketchat_servers::rocketchat_token.eq(Some(RS_TOKEN.to_string())),
+            rocketchat_servers::rocketchat_token_hash.eq(None::<String>),
+        ))
+        .execute(&*connection)
+        .unwrap();
+    diesel::update(users_on_rocketchat_servers::table.find((UserId::try_from("@spec_user:localhost").unwrap(), "rc_id")))
+        .set(users_on_rocketchat_servers::rocketchat_auth_token.eq(Some("spec_auth_token".to_string())))
+        .execute(&*connection)
+        .unwrap();
+
+    // plaintext tokens can still be read without a key until they are migrated
+    let rocketchat_server = RocketchatServer::find_by_token(&connection, None, RS_TOKEN.to_string()).unwrap().unwrap();
+    assert_eq!(rocketchat_server.id, "rc_id");
+
+    let encryption_key = EncryptionKey::from_base64(DATABASE_ENCRYPTION_KEY).unwrap();
+    let encrypted_tokens = encryption::encrypt_plaintext_tokens(&connection, &encryption_key).unwrap();
+    assert_eq!(encrypted_tokens, 2);
+
+    assert_eq!(encryption_key.decrypt(&stored_rocketchat_token(&connection)).unwrap(), RS_TOKEN);
+    assert_eq!(encryption_key.decrypt(&stored_auth_token(&connection)).unwrap(), "spec_auth_token");
+
+    // the migration stores the hash of the token, so the server can be found with the key
+    let rocketchat_server =
+        RocketchatServer::find_by_token(&connection, Some(&encryption_key), RS_TOKEN.to_string()).unwrap().unwrap();
+    assert_eq!(rocketchat_server.id, "rc_id");
+
+    // encrypted tokens are not touched when the migration runs again
+    let encrypted_tokens = encryption::encrypt_plaintext_tokens(&connection, &encryption_key).unwrap();
+    assert_eq!(encrypted_tokens, 0);
+}
+
+#[test]
+fn the_migration_fails_if_the_tokens_were_encrypted_with_another_key() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    let wrong_encryption_key = EncryptionKey::from_base64(NEW_DATABASE_ENCRYPTION_KEY).unwrap();
+    let err = encryption::encrypt_plaintext_tokens(&connection, &wrong_encryption_key).unwrap_err();
+    assert_error_kind!(err, ErrorKind::DecryptionError);
+}
+
+#[test]
+fn reencrypt_the_tokens_with_a_new_key() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    let old_encryption_key = EncryptionKey::from_base64(DATABASE_ENCRYPTION_KEY).unwrap();
+    let new_encryption_key = EncryptionKey::from_base64(NEW_DATABASE_ENCRYPTION_KEY).unwrap();
+    let reencrypted_tokens =
+        encryption::reencrypt_tokens(&connection, Some(&old_encryption_key), &new_encryption_key).unwrap();
+    assert_eq!(reencrypted_tokens, 2);
+
+    let stored_rocketchat_token = stored_rocketchat_token(&connection);
+    assert_eq!(new_encryption_key.decrypt(&stored_rocketchat_token).unwrap(), RS_TOKEN);
+    assert!(old_encryption_key.decrypt(&stored_rocketchat_token).is_err());
+    assert_eq!(new_encryption_key.decrypt(&stored_auth_token(&connection)).unwrap(), "spec_auth_token");
+
+    // the hash of the token is updated with the new key
+    let rocketchat_server =
+        RocketchatServer::find_by_token(&connection, Some(&new_encryption_key), RS_TOKEN.to_string()).unwrap();
+    assert_eq!(rocketchat_server.unwrap().id, "rc_id");
+    let rocketchat_server =
+        RocketchatServer::find_by_token(&connection, Some(&old_encryption_key), RS_TOKEN.to_string()).unwrap();
+    assert!(rocketchat_server.is_none());
+}
+
+#[test]
+fn attempt_to_reencrypt_the_tokens_with_a_wrong_old_key() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    let wrong_encryption_key = EncryptionKey::from_base64(NEW_DATABASE_ENCRYPTION_KEY).unwrap();
+    let err = encryption::reencrypt_tokens(&connection, Some(&wrong_encryption_key), &wrong_encryption_key).unwrap_err();
+    assert_error_kind!(err, ErrorKind::DecryptionError);
+
+    // the tokens are unchanged
+    let encryption_key = EncryptionKey::from_base64(DATABASE_ENCRYPTION_KEY).unwrap();
+    assert_eq!(encryption_key.decrypt(&stored_rocketchat_token(&connection)).unwrap(), RS_TOKEN);
+}
+
+#[test]
+fn attempt_to_reencrypt_encrypted_tokens_without_the_old_key() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().run();
+    let connection = test.connection_pool.get().unwrap();
+
+    let new_encryption_key = EncryptionKey::from_base64(NEW_DATABASE_ENCRYPTION_KEY).unwrap();
+    let err = encryption::reencrypt_tokens(&connection, None, &new_encryption_key).unwrap_err();
+    assert_error_kind!(err, ErrorKind::InvalidDatabaseEncryptionKey(_));
+}
+
+#[test]
+fn encryption_keys_with_an_invalid_length_are_rejected() {
+    let err = EncryptionKey::from_base64("c2hvcnRfa2V5").err().unwrap();
+    assert_error_kind!(err, ErrorKind::InvalidDatabaseEncryptionKey(_));
+
+    let err = EncryptionKey::from_base64("not base64!").err().unwrap();
+    assert_error_kind!(err, ErrorKind::InvalidDatabaseEncryptionKey(_));
+}
+
+#[test]
+fn the_encryption_key_is_optional() {
+    let test = Test::new();
+    let config = Config {
+        database_encryption_key: None,
+        ..test.config.clone()
+    };
+
+    assert!(config.database_encryption_key().unwrap().is_none());
+}
+
+#[test]
+fn an_invalid_encryption_key_in_the_config_is_rejected() {
+    let test = Test::new();
+    let config = Config {
+        database_encryption_key: Some("base64_encoded_key".to_string()),
+        ..test.config.clone()
+    };
+
+    let err = config.database_encryption_key().err().unwrap();
+    assert_error_kind!(err, ErrorKind::InvalidDatabaseEncryptionKey(_));
+}
+
+fn stored_rocketchat_token(connection: &SqliteConnection) -> String {
+    rocketchat_servers::table
+        .find("rc_id")
+        .select(rocketchat_servers::rocketchat_token)
+        .first::<Option<String>>(connection)
+        .unwrap()
+        .unwrap()
+}
+
+fn stored_auth_token(connection: &SqliteConnection) -> String {
+    users_on_rocketchat_servers::table
+        .find((UserId::try_from("@spec_user:localhost").unwrap(), "rc_id"))
+        .select(users_on_rocketchat_servers::rocketchat_auth_token)
+        .first::<Option<String>>(connection)
+        .unwrap()
+        .unwrap()
+}
@@ -14,7 +14,7 @@ use std::convert::TryFrom;
 
 use iron::status;
 use matrix_rocketchat::api::rocketchat::Message;
-use matrix_rocketchat::api::rocketchat::v1::{DIRECT_MESSAGES_LIST_PATH, POST_CHAT_MESSAGE_PATH};
+use matrix_rocketchat::api::rocketchat::v1::{POST_CHAT_MESSAGE_PATH, ROOMS_INFO_PATH};
 use matrix_rocketchat_test::{MessageForwarder, RS_TOKEN, Test, default_timeout, handlers, helpers};
 use router::Router;
 use ruma_client_api::Endpoint;
@@ -31,11 +31,11 @@ fn successfully_forwards_a_direct_message_to_rocketchat() {
     let mut rocketchat_router = Router::new();
     let mut direct_messages = HashMap::new();
     direct_messages.insert("spec_user_id_other_user_id", vec!["spec_user", "other_user"]);
-    let direct_messages_list_handler = handlers::RocketchatDirectMessagesList {
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
         direct_messages: direct_messages,
         status: status::Ok,
     };
-    rocketchat_router.get(DIRECT_MESSAGES_LIST_PATH, direct_messages_list_handler, "direct_messages_list");
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
     let (rocketchat_message_forwarder, rocketchat_receiver) = MessageForwarder::new();
     rocketchat_router.post(POST_CHAT_MESSAGE_PATH, rocketchat_message_forwarder, "post_chat_message");
 
@@ -55,6 +55,7 @@ fn successfully_forwards_a_direct_message_to_rocketchat() {
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_from_rocketchat_payload = to_string(&direct_message_from_rocketchat).unwrap();
 
@@ -68,8 +69,12 @@ fn successfully_forwards_a_direct_message_to_rocketchat() {
 
     // discard welcome message
     matrix_receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    matrix_receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     matrix_receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    matrix_receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     matrix_receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -0,0 +1,363 @@
+#![feature(try_from)]
+
+extern crate iron;
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate reqwest;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use iron::status;
+use matrix_rocketchat::api::{MatrixApi, RestApi};
+use matrix_rocketchat::api::rocketchat::v1::{CHANNELS_JOIN_PATH, CHANNELS_KICK_PATH, CHANNELS_LEAVE_PATH, GROUPS_KICK_PATH};
+use matrix_rocketchat::db::Room;
+use matrix_rocketchat_test::{DEFAULT_LOGGER, HS_TOKEN, MessageForwarder, Test, default_timeout, handlers, helpers};
+use reqwest::{Method, StatusCode};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::membership::kick_user::Endpoint as KickUserEndpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomId, UserId};
+
+#[test]
+fn successfully_forwards_a_leave_from_matrix_to_rocketchat() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(CHANNELS_LEAVE_PATH, message_forwarder, "channels_leave");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::leave_room(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    let leave_received_by_rocketchat = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(leave_received_by_rocketchat.contains("spec_channel_id"));
+}
+
+#[test]
+fn successfully_forwards_a_join_from_matrix_to_rocketchat() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(CHANNELS_JOIN_PATH, message_forwarder, "channels_join");
+    let mut channels = HashMap::new();
+    channels.insert("public_channel", vec!["user_1"]);
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    // the room is created when the homeserver queries the alias of the channel
+    query_room_alias(&test, "%23rocketchat%23rc_id%23public_channel_id%3Alocalhost");
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!public_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        UserId::try_from("@rocketchat:localhost").unwrap(),
+    );
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!public_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    let join_received_by_rocketchat = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(join_received_by_rocketchat.contains("public_channel_id"));
+}
+
+#[test]
+fn do_not_forward_a_display_name_change_as_join_to_rocketchat() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(CHANNELS_JOIN_PATH, message_forwarder, "channels_join");
+    let mut channels = HashMap::new();
+    channels.insert("public_channel", vec!["user_1"]);
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    query_room_alias(&test, "%23rocketchat%23rc_id%23public_channel_id%3Alocalhost");
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!public_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        UserId::try_from("@rocketchat:localhost").unwrap(),
+    );
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!public_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    // discard the join
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    helpers::send_display_name_change_event_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!public_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "New Name".to_string(),
+    );
+
+    assert!(receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn successfully_forwards_a_kick_from_matrix_to_rocketchat() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(CHANNELS_KICK_PATH, message_forwarder, "channels_kick");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat:localhost").unwrap(),
+    );
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap(),
+    );
+
+    helpers::send_kick_event_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    let kick_received_by_rocketchat = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(kick_received_by_rocketchat.contains("spec_channel_id"));
+    assert!(kick_received_by_rocketchat.contains("other_user_id"));
+}
+
+#[test]
+fn successfully_forwards_a_kick_from_a_private_group_from_matrix_to_rocketchat() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(GROUPS_KICK_PATH, message_forwarder, "groups_kick");
+    let mut channels = HashMap::new();
+    channels.insert("normal_channel", Vec::new());
+    let mut groups = HashMap::new();
+    groups.insert("private_group", vec!["spec_user", "user_1"]);
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .with_custom_groups_list(groups)
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "bridge private_group".to_string(),
+    );
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!private_group_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    helpers::send_kick_event_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!private_group_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat_user_1_id_rc_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    let kick_received_by_rocketchat = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(kick_received_by_rocketchat.contains("private_group_id"));
+    assert!(kick_received_by_rocketchat.contains("user_1_id"));
+}
+
+#[test]
+fn do_not_forward_a_leave_of_a_user_that_is_not_logged_in() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(CHANNELS_LEAVE_PATH, message_forwarder, "channels_leave");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    // the user is kicked from the room, because the user is not logged in on the Rocket.Chat server
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+    );
+
+    assert!(receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn a_user_that_is_not_logged_in_is_kicked_when_joining_a_room_that_was_created_by_the_bridge() {
+    let test = Test::new();
+    let (kick_forwarder, kick_receiver) = handlers::MatrixKickUser::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.post(KickUserEndpoint::router_path(), kick_forwarder, "kick_user");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@other_user:localhost").unwrap(),
+    );
+
+    let kick_message = kick_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(kick_message.contains("@other_user:localhost"));
+    assert!(kick_message.contains("Only users that are logged in on the Rocket.Chat server can join this room"));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), RoomId::try_from("!spec_channel_id:localhost").unwrap(), None).unwrap();
+    assert!(!user_ids.iter().any(|id| id == &UserId::try_from("@other_user:localhost").unwrap()));
+}
+
+#[test]
+fn virtual_users_are_not_kicked_when_joining_a_room_that_was_created_by_the_bridge() {
+    let test = Test::new();
+    let (kick_forwarder, kick_receiver) = handlers::MatrixKickUser::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.post(KickUserEndpoint::router_path(), kick_forwarder, "kick_user");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::invite(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat_new_user_id_rc_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat:localhost").unwrap(),
+    );
+
+    helpers::join(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@rocketchat_new_user_id_rc_id:localhost").unwrap(),
+    );
+
+    assert!(kick_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn the_user_gets_a_message_when_forwarding_a_leave_failes() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        CHANNELS_LEAVE_PATH,
+        handlers::RocketchatErrorResponder {
+            message: "You are the last owner. Please set new owner before leaving the room.".to_string(),
+            status: status::BadRequest,
+        },
+        "channels_leave",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::leave_room(
+        &test.config,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridge message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Leaving the channel spec_channel on the Rocket.Chat server failed"));
+    assert!(message_received_by_matrix.contains("You are the last owner"));
+}
+
+fn query_room_alias(test: &Test, encoded_room_alias: &str) {
+    let url = format!("{}/rooms/{}", test.config.as_url, encoded_room_alias);
+    let mut params = HashMap::new();
+    params.insert("access_token", HS_TOKEN);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &params, None).unwrap();
+    assert_eq!(status, StatusCode::Ok);
+}
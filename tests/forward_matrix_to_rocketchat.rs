@@ -15,6 +15,7 @@ use iron::status;
 use matrix_rocketchat::api::MatrixApi;
 use matrix_rocketchat::api::rocketchat::v1::POST_CHAT_MESSAGE_PATH;
 use matrix_rocketchat::api::rocketchat::Message;
+use matrix_rocketchat::db::{MAX_QUEUED_MESSAGES_PER_USER, QueuedMessage, UserOnRocketchatServer};
 use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, RS_TOKEN, Test, default_timeout, handlers, helpers};
 use router::Router;
 use ruma_client_api::Endpoint;
@@ -95,6 +96,7 @@ fn do_not_forward_messages_from_virtual_user_to_avoid_loops() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
     helpers::simulate_message_from_rocketchat(&test.config.as_url, &payload);
@@ -147,7 +149,7 @@ fn ignore_messages_from_rooms_with_empty_room_canonical_alias() {
 
     let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
     matrix_api.create_room(Some("room".to_string()), None, &UserId::try_from("@rocketchat:localhost").unwrap()).unwrap();
-    matrix_api.put_canonical_room_alias(RoomId::try_from("!room_id:localhost").unwrap(), None).unwrap();
+    matrix_api.put_canonical_room_alias(RoomId::try_from("!room_id:localhost").unwrap(), None, None).unwrap();
 
     helpers::invite(
         &test.config,
@@ -196,6 +198,7 @@ fn ignore_messages_with_a_message_type_that_is_not_supported() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
     helpers::simulate_message_from_rocketchat(&test.config.as_url, &payload);
@@ -244,8 +247,12 @@ fn the_user_gets_a_message_when_forwarding_a_message_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard bridge message
@@ -287,8 +294,12 @@ fn the_user_gets_a_message_when_when_getting_the_canonical_room_alias_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard first error message, because the bot doesn't
@@ -330,8 +341,12 @@ fn the_user_gets_a_message_when_when_getting_the_canonical_room_alias_response_c
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard first error message, because the bot doesn't
@@ -344,3 +359,197 @@ fn the_user_gets_a_message_when_when_getting_the_canonical_room_alias_response_c
     let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
     assert!(message_received_by_matrix.contains("An internal error occurred"));
 }
+
+#[test]
+fn the_user_is_asked_to_login_again_when_the_rocketchat_session_expired() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        POST_CHAT_MESSAGE_PATH,
+        handlers::RocketchatErrorResponder {
+            message: "You must be logged in to do this.".to_string(),
+            status: status::Unauthorized,
+        },
+        "post_chat_message",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "first message".to_string(),
+    );
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "second message".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard bridge message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("expired or was revoked"));
+
+    // the user is only notified once
+    assert!(receiver.recv_timeout(default_timeout()).is_err());
+
+    let connection = test.connection_pool.get().unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let user_on_rocketchat_server = UserOnRocketchatServer::find(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    assert!(user_on_rocketchat_server.has_invalid_credentials());
+    assert_eq!(user_on_rocketchat_server.admin_room_id, Some(RoomId::try_from("!admin_room_id:localhost").unwrap()));
+
+    let queued_messages = QueuedMessage::find_by_user(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    assert_eq!(queued_messages.len(), 2);
+    assert_eq!(queued_messages[0].message, "first message");
+    assert_eq!(queued_messages[1].message, "second message");
+}
+
+#[test]
+fn queued_messages_are_forwarded_when_the_user_logs_in_again() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(POST_CHAT_MESSAGE_PATH, message_forwarder, "post_chat_message");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    let connection = test.connection_pool.get().unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let mut user_on_rocketchat_server = UserOnRocketchatServer::find(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    user_on_rocketchat_server.invalidate_credentials(&connection).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "queued message".to_string(),
+    );
+
+    // the message is not forwarded while the credentials are invalid
+    assert!(receiver.recv_timeout(default_timeout()).is_err());
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    let message_received_by_rocketchat = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_rocketchat.contains("queued message"));
+    assert!(message_received_by_rocketchat.contains("spec_channel"));
+
+    let user_on_rocketchat_server = UserOnRocketchatServer::find(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    assert!(!user_on_rocketchat_server.has_invalid_credentials());
+    assert!(QueuedMessage::find_by_user(&connection, &spec_user_id, "rc_id".to_string()).unwrap().is_empty());
+}
+
+#[test]
+fn queued_messages_for_channels_that_are_not_bridged_anymore_are_discarded_when_the_user_logs_in_again() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(POST_CHAT_MESSAGE_PATH, message_forwarder, "post_chat_message");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    let connection = test.connection_pool.get().unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let mut user_on_rocketchat_server = UserOnRocketchatServer::find(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    user_on_rocketchat_server.invalidate_credentials(&connection).unwrap();
+
+    QueuedMessage::queue(
+        &connection,
+        spec_user_id.clone(),
+        "rc_id".to_string(),
+        "unbridged_channel_id".to_string(),
+        "message for an unbridged channel".to_string(),
+    ).unwrap();
+    QueuedMessage::queue(
+        &connection,
+        spec_user_id.clone(),
+        "rc_id".to_string(),
+        "spec_channel_id".to_string(),
+        "message for a bridged channel".to_string(),
+    ).unwrap();
+
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login spec_user secret".to_string(),
+    );
+
+    let message_received_by_rocketchat = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_rocketchat.contains("message for a bridged channel"));
+
+    // the message for the unbridged channel is not forwarded
+    assert!(receiver.recv_timeout(default_timeout()).is_err());
+
+    assert!(QueuedMessage::find_by_user(&connection, &spec_user_id, "rc_id".to_string()).unwrap().is_empty());
+}
+
+#[test]
+fn messages_are_discarded_when_the_queue_of_the_user_is_full() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let connection = test.connection_pool.get().unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    for i in 0..MAX_QUEUED_MESSAGES_PER_USER {
+        let is_queued = QueuedMessage::queue(
+            &connection,
+            spec_user_id.clone(),
+            "rc_id".to_string(),
+            "spec_channel_id".to_string(),
+            format!("message {}", i),
+        ).unwrap();
+        assert!(is_queued);
+    }
+
+    let is_queued = QueuedMessage::queue(
+        &connection,
+        spec_user_id.clone(),
+        "rc_id".to_string(),
+        "spec_channel_id".to_string(),
+        "one message too many".to_string(),
+    ).unwrap();
+    assert!(!is_queued);
+
+    let queued_messages = QueuedMessage::find_by_user(&connection, &spec_user_id, "rc_id".to_string()).unwrap();
+    assert_eq!(queued_messages.len(), MAX_QUEUED_MESSAGES_PER_USER);
+    assert_eq!(queued_messages.last().unwrap().message, format!("message {}", MAX_QUEUED_MESSAGES_PER_USER - 1));
+}
@@ -15,14 +15,16 @@ use std::convert::TryFrom;
 use iron::{Chain, status};
 use matrix_rocketchat::api::{MatrixApi, RestApi};
 use matrix_rocketchat::api::rocketchat::Message;
-use matrix_rocketchat::db::{Room, User, UserOnRocketchatServer};
+use matrix_rocketchat::db::{GrantedPowerlevel, NewGrantedPowerlevel, Room, User, UserOnRocketchatServer};
 use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, RS_TOKEN, Test, default_timeout, handlers, helpers};
 use reqwest::{Method, StatusCode};
 use ruma_client_api::Endpoint;
 use ruma_client_api::r0::membership::invite_user::Endpoint as InviteUserEndpoint;
 use ruma_client_api::r0::membership::join_room_by_id::Endpoint as JoinRoomByIdEndpoint;
+use ruma_client_api::r0::membership::kick_user::Endpoint as KickUserEndpoint;
 use ruma_client_api::r0::profile::set_display_name::Endpoint as SetDisplayNameEndpoint;
 use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_client_api::r0::send::send_state_event_for_empty_key::Endpoint as SendStateEventForEmptyKeyEndpoint;
 use ruma_identifiers::{RoomId, UserId};
 use serde_json::to_string;
 
@@ -54,6 +56,7 @@ fn successfully_forwards_a_text_message_from_rocketchat_to_matrix_when_the_user_
         user_id: "new_user_id".to_string(),
         user_name: "new_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
@@ -80,8 +83,12 @@ fn successfully_forwards_a_text_message_from_rocketchat_to_matrix_when_the_user_
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard room bridged message
@@ -122,6 +129,7 @@ fn successfully_forwards_a_text_message_from_rocketchat_to_matrix_when_the_user_
         user_id: "new_user_id".to_string(),
         user_name: "new_spec_user".to_string(),
         text: "spec_message 2".to_string(),
+        message_type: None,
     };
     let second_payload = to_string(&second_message).unwrap();
 
@@ -163,6 +171,7 @@ fn successfully_forwards_a_text_message_from_rocketchat_to_matrix_when_the_user_
         user_id: "spec_user_id".to_string(),
         user_name: "spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
@@ -187,8 +196,12 @@ fn successfully_forwards_a_text_message_from_rocketchat_to_matrix_when_the_user_
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard room bridged message
@@ -228,6 +241,7 @@ fn successfully_forwards_a_text_message_from_rocketchat_to_matrix_when_the_user_
         user_id: "spec_user_id".to_string(),
         user_name: "spec_user".to_string(),
         text: "spec_message 2".to_string(),
+        message_type: None,
     };
     let second_payload = to_string(&second_message).unwrap();
 
@@ -262,6 +276,7 @@ fn update_the_display_name_when_the_user_changed_it_on_the_rocketchat_server() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
@@ -280,6 +295,7 @@ fn update_the_display_name_when_the_user_changed_it_on_the_rocketchat_server() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user_new".to_string(),
         text: "spec_message 2".to_string(),
+        message_type: None,
     };
     let second_payload_with_new_username = to_string(&second_message_with_new_username).unwrap();
 
@@ -336,6 +352,7 @@ fn message_is_forwarded_even_if_setting_the_display_name_failes() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
@@ -343,8 +360,12 @@ fn message_is_forwarded_even_if_setting_the_display_name_failes() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard room bridged message
@@ -399,13 +420,18 @@ fn no_message_is_forwarded_when_inviting_the_user_failes() {
         user_id: "new_user_id".to_string(),
         user_name: "new_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard room bridged message
@@ -435,13 +461,18 @@ fn ignore_messages_to_a_room_that_is_not_bridged() {
         user_id: "new_user_id".to_string(),
         user_name: "new_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -473,13 +504,18 @@ fn ignore_messages_forwarded_from_rocketchat_if_the_non_virtual_user_just_sent_a
         user_id: "spec_user_id".to_string(),
         user_name: "spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard room bridged message
@@ -510,20 +546,17 @@ fn do_not_forward_messages_when_the_channel_was_bridged_but_is_unbridged_now() {
         },
         "set_display_name",
     );
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["owner"]);
 
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
         .with_connected_admin_room()
         .with_logged_in_user()
         .with_bridged_room(("spec_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
         .run();
 
-    helpers::leave_room(
-        &test.config,
-        RoomId::try_from("!spec_channel_id:localhost").unwrap(),
-        UserId::try_from("@spec_user:localhost").unwrap(),
-    );
-
     helpers::send_room_message_from_matrix(
         &test.config.as_url,
         RoomId::try_from("!admin_room_id:localhost").unwrap(),
@@ -539,6 +572,7 @@ fn do_not_forward_messages_when_the_channel_was_bridged_but_is_unbridged_now() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
@@ -546,18 +580,279 @@ fn do_not_forward_messages_when_the_channel_was_bridged_but_is_unbridged_now() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
     // discard room bridged message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard the notice in the unbridged room
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard room unbridged message
     receiver.recv_timeout(default_timeout()).unwrap();
 
     assert!(receiver.recv_timeout(default_timeout()).is_err());
 }
 
+#[test]
+fn virtual_users_that_left_the_rocketchat_channel_are_kicked_when_a_message_is_received() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (kick_forwarder, kick_receiver) = handlers::MatrixKickUser::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(KickUserEndpoint::router_path(), kick_forwarder, "kick_user");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    // a user that was in the channel before, but left it on the Rocket.Chat server
+    let room_id = RoomId::try_from("!spec_channel_id:localhost").unwrap();
+    let bot_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let left_user_id = UserId::try_from("@rocketchat_left_user_id_rc_id:localhost").unwrap();
+    helpers::invite(&test.config, room_id.clone(), left_user_id.clone(), bot_user_id);
+    helpers::join(&test.config, room_id.clone(), left_user_id.clone());
+
+    let message = Message {
+        message_id: "spec_id".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_channel_id".to_string(),
+        channel_name: Some("spec_channel".to_string()),
+        user_id: "new_user_id".to_string(),
+        user_name: "new_spec_user".to_string(),
+        text: "spec_message".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&message).unwrap());
+
+    let kick_message = kick_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(kick_message.contains("@rocketchat_left_user_id_rc_id:localhost"));
+    assert!(kick_message.contains("Left the channel on Rocket.Chat"));
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard room bridged message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("spec_message"));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), room_id, None).unwrap();
+    assert!(!user_ids.iter().any(|id| id == &left_user_id));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@spec_user:localhost").unwrap()));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_spec_user_id_rc_id:localhost").unwrap()));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_new_user_id_rc_id:localhost").unwrap()));
+
+    // only users that are not in the channel anymore are kicked
+    assert!(kick_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn virtual_users_are_kicked_right_away_when_rocketchat_sends_that_a_user_left_the_channel() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let (kick_forwarder, kick_receiver) = handlers::MatrixKickUser::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(KickUserEndpoint::router_path(), kick_forwarder, "kick_user");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    // the members are synchronized with the first message
+    let message = Message {
+        message_id: "spec_id".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_channel_id".to_string(),
+        channel_name: Some("spec_channel".to_string()),
+        user_id: "spec_user_id".to_string(),
+        user_name: "spec_user".to_string(),
+        text: "spec_message".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&message).unwrap());
+
+    // a user that was in the channel before, but left it on the Rocket.Chat server
+    let room_id = RoomId::try_from("!spec_channel_id:localhost").unwrap();
+    let bot_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let left_user_id = UserId::try_from("@rocketchat_left_user_id_rc_id:localhost").unwrap();
+    helpers::invite(&test.config, room_id.clone(), left_user_id.clone(), bot_user_id);
+    helpers::join(&test.config, room_id.clone(), left_user_id.clone());
+
+    let user_left_message = Message {
+        message_id: "spec_user_left_id".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_channel_id".to_string(),
+        channel_name: Some("spec_channel".to_string()),
+        user_id: "left_user_id".to_string(),
+        user_name: "left_user".to_string(),
+        text: "left_user".to_string(),
+        message_type: Some("ul".to_string()),
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&user_left_message).unwrap());
+
+    let kick_message = kick_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(kick_message.contains("@rocketchat_left_user_id_rc_id:localhost"));
+    assert!(kick_message.contains("Left the channel on Rocket.Chat"));
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard room bridged message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("spec_message"));
+
+    // the system message is not forwarded
+    assert!(receiver.recv_timeout(default_timeout()).is_err());
+    assert!(kick_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn logged_in_users_get_the_power_level_for_their_role_in_the_rocketchat_channel() {
+    let test = Test::new();
+    let (state_forwarder, state_receiver) = handlers::SendRoomState::with_forwarder();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendStateEventForEmptyKeyEndpoint::router_path(), state_forwarder, "send_state_event_for_key");
+    let mut room_roles = HashMap::new();
+    room_roles.insert("spec_user", vec!["moderator"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .with_custom_room_roles(room_roles)
+        .run();
+
+    // the members are synchronized right away when a user joins the channel
+    let user_joined_message = Message {
+        message_id: "spec_user_joined_id".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_channel_id".to_string(),
+        channel_name: Some("spec_channel".to_string()),
+        user_id: "new_user_id".to_string(),
+        user_name: "new_user".to_string(),
+        text: "new_user".to_string(),
+        message_type: Some("uj".to_string()),
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&user_joined_message).unwrap());
+
+    let mut power_levels_received_by_matrix = String::new();
+    while let Ok(state_received_by_matrix) = state_receiver.recv_timeout(default_timeout()) {
+        if state_received_by_matrix.contains("@spec_user:localhost") {
+            power_levels_received_by_matrix = state_received_by_matrix;
+            break;
+        }
+    }
+
+    assert!(power_levels_received_by_matrix.contains("\"@spec_user:localhost\":50"));
+}
+
+#[test]
+fn the_power_level_of_a_logged_in_user_is_not_lowered_when_the_user_has_no_role_in_the_rocketchat_channel() {
+    let test = Test::new().with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    // the power level was granted on Matrix
+    let room_id = RoomId::try_from("!spec_channel_id:localhost").unwrap();
+    let bot_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let mut user_powerlevels = HashMap::new();
+    user_powerlevels.insert(spec_user_id.clone(), 50);
+    matrix_api.set_user_powerlevels(room_id.clone(), bot_user_id, user_powerlevels).unwrap();
+
+    let user_joined_message = Message {
+        message_id: "spec_user_joined_id".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_channel_id".to_string(),
+        channel_name: Some("spec_channel".to_string()),
+        user_id: "new_user_id".to_string(),
+        user_name: "new_user".to_string(),
+        text: "new_user".to_string(),
+        message_type: Some("uj".to_string()),
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&user_joined_message).unwrap());
+
+    let power_levels = matrix_api.get_room_powerlevels(room_id).unwrap();
+    assert_eq!(power_levels.user_powerlevel(&spec_user_id), 50);
+}
+
+#[test]
+fn the_power_level_that_was_granted_for_a_role_is_revoked_when_the_user_loses_the_role() {
+    let test = Test::new().with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_bridged_room(("spec_channel", "spec_user"))
+        .run();
+
+    // the bridge granted the power level for the moderator role
+    let room_id = RoomId::try_from("!spec_channel_id:localhost").unwrap();
+    let bot_user_id = UserId::try_from("@rocketchat:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let mut user_powerlevels = HashMap::new();
+    user_powerlevels.insert(spec_user_id.clone(), 50);
+    matrix_api.set_user_powerlevels(room_id.clone(), bot_user_id, user_powerlevels).unwrap();
+    let connection = test.connection_pool.get().unwrap();
+    let new_granted_powerlevel = NewGrantedPowerlevel {
+        matrix_room_id: room_id.clone(),
+        matrix_user_id: spec_user_id.clone(),
+        powerlevel: 50,
+        previous_powerlevel: 0,
+    };
+    GrantedPowerlevel::insert(&connection, &new_granted_powerlevel).unwrap();
+
+    let role_removed_message = Message {
+        message_id: "spec_role_removed_id".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "spec_channel_id".to_string(),
+        channel_name: Some("spec_channel".to_string()),
+        user_id: "spec_user_id".to_string(),
+        user_name: "spec_user".to_string(),
+        text: "spec_user".to_string(),
+        message_type: Some("subscription-role-removed".to_string()),
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&role_removed_message).unwrap());
+
+    let power_levels = matrix_api.get_room_powerlevels(room_id.clone()).unwrap();
+    assert_eq!(power_levels.user_powerlevel(&spec_user_id), 0);
+    assert!(GrantedPowerlevel::find(&connection, &room_id, &spec_user_id).unwrap().is_none());
+}
+
 #[test]
 fn returns_unauthorized_when_the_rs_token_is_missing() {
     let test = Test::new().run();
@@ -569,6 +864,7 @@ fn returns_unauthorized_when_the_rs_token_is_missing() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
@@ -591,6 +887,7 @@ fn returns_forbidden_when_the_rs_token_does_not_match_a_server() {
         user_id: "virtual_spec_user_id".to_string(),
         user_name: "virtual_spec_user".to_string(),
         text: "spec_message".to_string(),
+        message_type: None,
     };
     let payload = to_string(&message).unwrap();
 
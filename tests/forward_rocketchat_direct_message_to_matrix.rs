@@ -15,8 +15,8 @@ use std::convert::TryFrom;
 use iron::status;
 use matrix_rocketchat::api::MatrixApi;
 use matrix_rocketchat::api::rocketchat::Message;
-use matrix_rocketchat::api::rocketchat::v1::DIRECT_MESSAGES_LIST_PATH;
-use matrix_rocketchat::db::{Room, UserOnRocketchatServer};
+use matrix_rocketchat::api::rocketchat::v1::{DIRECT_MESSAGES_LIST_PATH, ROOMS_INFO_PATH};
+use matrix_rocketchat::db::{NewUser, NewUserOnRocketchatServer, Room, User, UserOnRocketchatServer};
 use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, RS_TOKEN, Test, default_timeout, handlers, helpers};
 use router::Router;
 use ruma_client_api::Endpoint;
@@ -44,12 +44,12 @@ fn successfully_forwards_a_direct_message() {
     matrix_router.post(RegisterEndpoint::router_path(), register_forwarder, "register");
     let mut rocketchat_router = Router::new();
     let mut direct_messages = HashMap::new();
-    direct_messages.insert("spec_user_id_other_user_id", vec!["spec_user", "other_user"]);
-    let direct_messages_list_handler = handlers::RocketchatDirectMessagesList {
+    direct_messages.insert("other_user_idspec_user_id", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
         direct_messages: direct_messages,
         status: status::Ok,
     };
-    rocketchat_router.get(DIRECT_MESSAGES_LIST_PATH, direct_messages_list_handler, "direct_messages_list");
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
 
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
@@ -61,11 +61,12 @@ fn successfully_forwards_a_direct_message() {
     let first_direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let first_direct_message_payload = to_string(&first_direct_message).unwrap();
 
@@ -81,7 +82,7 @@ fn successfully_forwards_a_direct_message() {
     create_room_receiver.recv_timeout(default_timeout()).unwrap();
 
     let create_room_message = create_room_receiver.recv_timeout(default_timeout()).unwrap();
-    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#spec_user_id_other_user_id#dm\""));
+    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#other_user_idspec_user_id#dm\""));
     assert!(create_room_message.contains("\"name\":\"other_user (DM Rocket.Chat)\""));
 
     // discard bot registration
@@ -95,8 +96,12 @@ fn successfully_forwards_a_direct_message() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -121,11 +126,12 @@ fn successfully_forwards_a_direct_message() {
     let second_direct_message = Message {
         message_id: "spec_id_2".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Yay".to_string(),
+        message_type: None,
     };
     let second_direct_message_payload = to_string(&second_direct_message).unwrap();
 
@@ -141,12 +147,12 @@ fn the_bot_user_stays_in_the_direct_message_room_if_the_user_leaves() {
 
     let mut rocketchat_router = Router::new();
     let mut direct_messages = HashMap::new();
-    direct_messages.insert("spec_user_id_other_user_id", vec!["spec_user", "other_user"]);
-    let direct_messages_list_handler = handlers::RocketchatDirectMessagesList {
+    direct_messages.insert("other_user_idspec_user_id", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
         direct_messages: direct_messages,
         status: status::Ok,
     };
-    rocketchat_router.get(DIRECT_MESSAGES_LIST_PATH, direct_messages_list_handler, "direct_messages_list");
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
 
     let mut matrix_router = test.default_matrix_routes();
     let (forget_message_forwarder, forget_receiver) = MessageForwarder::new();
@@ -164,11 +170,12 @@ fn the_bot_user_stays_in_the_direct_message_room_if_the_user_leaves() {
     let direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -213,12 +220,12 @@ fn successfully_forwards_a_direct_message_to_a_room_that_was_bridged_before() {
 
     let mut rocketchat_router = Router::new();
     let mut direct_messages = HashMap::new();
-    direct_messages.insert("spec_user_id_other_user_id", vec!["spec_user", "other_user"]);
-    let direct_messages_list_handler = handlers::RocketchatDirectMessagesList {
+    direct_messages.insert("other_user_idspec_user_id", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
         direct_messages: direct_messages,
         status: status::Ok,
     };
-    rocketchat_router.get(DIRECT_MESSAGES_LIST_PATH, direct_messages_list_handler, "direct_messages_list");
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
 
     let mut matrix_router = test.default_matrix_routes();
     let (message_forwarder, receiver) = MessageForwarder::new();
@@ -236,11 +243,12 @@ fn successfully_forwards_a_direct_message_to_a_room_that_was_bridged_before() {
     let direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -248,8 +256,12 @@ fn successfully_forwards_a_direct_message_to_a_room_that_was_bridged_before() {
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -277,11 +289,12 @@ fn successfully_forwards_a_direct_message_to_a_room_that_was_bridged_before() {
     let direct_message = Message {
         message_id: "spec_id_2".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey again".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -309,12 +322,12 @@ fn do_not_forwards_a_direct_message_to_a_room_if_the_user_is_no_longer_logged_in
 
     let mut rocketchat_router = Router::new();
     let mut direct_messages = HashMap::new();
-    direct_messages.insert("spec_user_id_other_user_id", vec!["spec_user", "other_user"]);
-    let direct_messages_list_handler = handlers::RocketchatDirectMessagesList {
+    direct_messages.insert("other_user_idspec_user_id", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
         direct_messages: direct_messages,
         status: status::Ok,
     };
-    rocketchat_router.get(DIRECT_MESSAGES_LIST_PATH, direct_messages_list_handler, "direct_messages_list");
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
 
     let mut matrix_router = test.default_matrix_routes();
     let (message_forwarder, receiver) = MessageForwarder::new();
@@ -332,11 +345,12 @@ fn do_not_forwards_a_direct_message_to_a_room_if_the_user_is_no_longer_logged_in
     let direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -344,8 +358,12 @@ fn do_not_forwards_a_direct_message_to_a_room_if_the_user_is_no_longer_logged_in
 
     // discard welcome message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard connect message
     receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
     // discard login message
     receiver.recv_timeout(default_timeout()).unwrap();
 
@@ -378,11 +396,12 @@ fn do_not_forwards_a_direct_message_to_a_room_if_the_user_is_no_longer_logged_in
     let direct_message = Message {
         message_id: "spec_id_2".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey again".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -398,11 +417,11 @@ fn no_room_is_created_when_the_user_doesn_not_have_access_to_the_matching_direct
     let mut matrix_router = test.default_matrix_routes();
     matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
     let mut rocketchat_router = Router::new();
-    let direct_messages_list_handler = handlers::RocketchatDirectMessagesList {
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
         direct_messages: HashMap::new(),
         status: status::Ok,
     };
-    rocketchat_router.get(DIRECT_MESSAGES_LIST_PATH, direct_messages_list_handler, "direct_messages_list");
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
 
     let test = test.with_matrix_routes(matrix_router)
         .with_rocketchat_mock()
@@ -414,11 +433,12 @@ fn no_room_is_created_when_the_user_doesn_not_have_access_to_the_matching_direct
     let direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -446,6 +466,7 @@ fn no_room_is_created_when_no_matching_user_for_the_room_name_is_found() {
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -456,19 +477,19 @@ fn no_room_is_created_when_no_matching_user_for_the_room_name_is_found() {
 }
 
 #[test]
-fn no_room_is_created_when_getting_the_direct_message_list_failes() {
+fn no_room_is_created_when_getting_the_room_info_failes() {
     let test = Test::new();
     let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
     let mut matrix_router = test.default_matrix_routes();
     matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
     let mut rocketchat_router = Router::new();
     rocketchat_router.get(
-        DIRECT_MESSAGES_LIST_PATH,
+        ROOMS_INFO_PATH,
         handlers::RocketchatErrorResponder {
             status: status::InternalServerError,
-            message: "Getting DMs failed".to_string(),
+            message: "Getting the room failed".to_string(),
         },
-        "direct_messages_list",
+        "rooms_info",
     );
 
     let test = test.with_matrix_routes(matrix_router)
@@ -481,11 +502,12 @@ fn no_room_is_created_when_getting_the_direct_message_list_failes() {
     let direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -496,16 +518,16 @@ fn no_room_is_created_when_getting_the_direct_message_list_failes() {
 }
 
 #[test]
-fn no_room_is_created_when_the_direct_message_list_response_cannot_be_deserialized() {
+fn no_room_is_created_when_the_room_info_response_cannot_be_deserialized() {
     let test = Test::new();
     let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
     let mut matrix_router = test.default_matrix_routes();
     matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
     let mut rocketchat_router = Router::new();
     rocketchat_router.get(
-        DIRECT_MESSAGES_LIST_PATH,
+        ROOMS_INFO_PATH,
         handlers::InvalidJsonResponse { status: status::Ok },
-        "direct_messages_list",
+        "rooms_info",
     );
 
     let test = test.with_matrix_routes(matrix_router)
@@ -518,11 +540,12 @@ fn no_room_is_created_when_the_direct_message_list_response_cannot_be_deserializ
     let direct_message = Message {
         message_id: "spec_id_1".to_string(),
         token: Some(RS_TOKEN.to_string()),
-        channel_id: "spec_user_id_other_user_id".to_string(),
+        channel_id: "other_user_idspec_user_id".to_string(),
         channel_name: None,
         user_id: "other_user_id".to_string(),
         user_name: "other_user".to_string(),
         text: "Hey there".to_string(),
+        message_type: None,
     };
     let direct_message_payload = to_string(&direct_message).unwrap();
 
@@ -531,3 +554,365 @@ fn no_room_is_created_when_the_direct_message_list_response_cannot_be_deserializ
     // no room is created on the Matrix server
     create_room_receiver.recv_timeout(default_timeout()).is_err();
 }
+
+#[test]
+fn a_channel_that_is_no_direct_message_is_looked_up_only_once_within_the_lookup_interval() {
+    let test = Test::new();
+    let (rooms_info_forwarder, rooms_info_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_forwarder, "rooms_info");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    let message = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "not_bridged_channel_id".to_string(),
+        channel_name: Some("not_bridged_channel".to_string()),
+        user_id: "spec_user_id".to_string(),
+        user_name: "spec_user".to_string(),
+        text: "Hey there".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&message).unwrap());
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&message).unwrap());
+
+    // a single request for the first message, the second message doesn't trigger a lookup
+    rooms_info_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(rooms_info_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn a_channel_is_not_looked_up_with_the_credentials_of_users_that_are_not_known_to_participate() {
+    let test = Test::new();
+    let (rooms_info_forwarder, rooms_info_receiver) = MessageForwarder::new();
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_forwarder, "rooms_info");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    let message = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "other_user_idthird_user_id".to_string(),
+        channel_name: None,
+        user_id: "other_user_id".to_string(),
+        user_name: "other_user".to_string(),
+        text: "Hey there".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&message).unwrap());
+
+    assert!(rooms_info_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn successfully_forwards_a_direct_message_when_the_channel_id_does_not_contain_the_user_ids() {
+    let test = Test::new();
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+    let mut rocketchat_router = Router::new();
+    let mut direct_messages = HashMap::new();
+    direct_messages.insert("Xk3aFq9ZpR2", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
+        direct_messages: direct_messages.clone(),
+        status: status::Ok,
+    };
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
+    // the channel is known from the direct messages of the user, which are looked up on login
+    rocketchat_router.get(
+        DIRECT_MESSAGES_LIST_PATH,
+        handlers::RocketchatDirectMessagesList {
+            direct_messages: direct_messages,
+            status: status::Ok,
+        },
+        "direct_messages_list",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    let direct_message = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "Xk3aFq9ZpR2".to_string(),
+        channel_name: None,
+        user_id: "other_user_id".to_string(),
+        user_name: "other_user".to_string(),
+        text: "Hey there".to_string(),
+        message_type: None,
+    };
+    let direct_message_payload = to_string(&direct_message).unwrap();
+
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &direct_message_payload);
+
+    // discard admin room creation message
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let create_room_message = create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#Xk3aFq9ZpR2#dm\""));
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Hey there"));
+}
+
+#[test]
+fn no_room_is_created_when_the_channel_id_contains_the_user_id_but_the_channel_is_no_direct_message() {
+    let test = Test::new();
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+    let mut rocketchat_router = Router::new();
+    let mut direct_messages = HashMap::new();
+    direct_messages.insert("other_user_idspec_user_id", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
+        direct_messages: direct_messages,
+        status: status::Ok,
+    };
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    let message = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "other_user_idspec_user_id_third_user_id".to_string(),
+        channel_name: None,
+        user_id: "other_user_id".to_string(),
+        user_name: "other_user".to_string(),
+        text: "Hey there".to_string(),
+        message_type: None,
+    };
+    let payload = to_string(&message).unwrap();
+
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &payload);
+
+    // discard admin room creation message
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+
+    // no room is created on the Matrix server
+    assert!(create_room_receiver.recv_timeout(default_timeout()).is_err());
+}
+
+#[test]
+fn successfully_forwards_direct_messages_when_both_participants_use_the_application_service() {
+    let test = Test::new();
+    let (invite_forwarder, invite_receiver) = handlers::MatrixInviteUser::with_forwarder(test.config.as_url.clone());
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(InviteEndpoint::router_path(), invite_forwarder, "invite_user");
+    let mut rocketchat_router = Router::new();
+    let mut direct_messages = HashMap::new();
+    direct_messages.insert("other_user_idspec_user_id", vec!["spec_user", "other_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
+        direct_messages: direct_messages,
+        status: status::Ok,
+    };
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // the other user is logged in on the Rocket.Chat server via the application service as well
+    let connection = test.connection_pool.get().unwrap();
+    let other_user_id = UserId::try_from("@other_user:localhost").unwrap();
+    User::insert(
+        &connection,
+        &NewUser {
+            language: "en",
+            matrix_user_id: other_user_id.clone(),
+        },
+    ).unwrap();
+    UserOnRocketchatServer::upsert(
+        &connection,
+        test.config.database_encryption_key().unwrap().as_ref(),
+        &NewUserOnRocketchatServer {
+            is_virtual_user: false,
+            matrix_user_id: other_user_id.clone(),
+            rocketchat_server_id: "rc_id".to_string(),
+            rocketchat_user_id: Some("other_user_id".to_string()),
+            rocketchat_auth_token: Some("other_auth_token".to_string()),
+            rocketchat_username: Some("other_user".to_string()),
+        },
+    ).unwrap();
+
+    // discard admin room invite
+    invite_receiver.recv_timeout(default_timeout()).unwrap();
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let direct_message_from_other_user = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "other_user_idspec_user_id".to_string(),
+        channel_name: None,
+        user_id: "other_user_id".to_string(),
+        user_name: "other_user".to_string(),
+        text: "Hey there".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&direct_message_from_other_user).unwrap());
+
+    // the recipient is invited, not the sender
+    let spec_user_invite = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(spec_user_invite.contains("\"user_id\":\"@spec_user:localhost\""));
+    // discard bot invite into direct message room
+    invite_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Hey there"));
+
+    let direct_message_from_spec_user = Message {
+        message_id: "spec_id_2".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "other_user_idspec_user_id".to_string(),
+        channel_name: None,
+        user_id: "spec_user_id".to_string(),
+        user_name: "spec_user".to_string(),
+        text: "Hi, nice to hear from you".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&direct_message_from_spec_user).unwrap());
+
+    // the virtual user of the spec user joins the room and the other user is invited
+    let spec_user_virtual_user_invite = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(spec_user_virtual_user_invite.contains("\"user_id\":\"@rocketchat_spec_user_id_rc_id:localhost\""));
+    let other_user_invite = invite_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(other_user_invite.contains("\"user_id\":\"@other_user:localhost\""));
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Hi, nice to hear from you"));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let virtual_other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    let user_ids = Room::user_ids(
+        &(*matrix_api),
+        RoomId::try_from("!other_userDMRocketChat_id:localhost").unwrap(),
+        Some(virtual_other_user_id.clone()),
+    ).unwrap();
+    assert!(user_ids.iter().any(|id| id == &virtual_other_user_id));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_spec_user_id_rc_id:localhost").unwrap()));
+}
+
+#[test]
+fn successfully_forwards_a_group_direct_message() {
+    let test = Test::new();
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+    let mut rocketchat_router = Router::new();
+    let mut direct_messages = HashMap::new();
+    direct_messages.insert("group_dm_id", vec!["spec_user", "other_user", "third_user"]);
+    let rooms_info_handler = handlers::RocketchatRoomsInfo {
+        direct_messages: direct_messages.clone(),
+        status: status::Ok,
+    };
+    rocketchat_router.get(ROOMS_INFO_PATH, rooms_info_handler, "rooms_info");
+    // the channel is known from the direct messages of the user, which are looked up on login
+    rocketchat_router.get(
+        DIRECT_MESSAGES_LIST_PATH,
+        handlers::RocketchatDirectMessagesList {
+            direct_messages: direct_messages,
+            status: status::Ok,
+        },
+        "direct_messages_list",
+    );
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    let direct_message = Message {
+        message_id: "spec_id_1".to_string(),
+        token: Some(RS_TOKEN.to_string()),
+        channel_id: "group_dm_id".to_string(),
+        channel_name: None,
+        user_id: "other_user_id".to_string(),
+        user_name: "other_user".to_string(),
+        text: "Hey everyone".to_string(),
+        message_type: None,
+    };
+    helpers::simulate_message_from_rocketchat(&test.config.as_url, &to_string(&direct_message).unwrap());
+
+    // discard admin room creation message
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let create_room_message = create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#group_dm_id#dm\""));
+    assert!(create_room_message.contains("\"name\":\"other_user, third_user (DM Rocket.Chat)\""));
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Hey everyone"));
+
+    let room_id = RoomId::try_from("!other_userthird_userDMRocketChat_id:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    let third_user_id = UserId::try_from("@rocketchat_third_user_id_rc_id:localhost").unwrap();
+    helpers::join(&test.config, room_id.clone(), spec_user_id.clone());
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), room_id, Some(other_user_id.clone())).unwrap();
+    assert!(user_ids.iter().any(|id| id == &spec_user_id));
+    assert!(user_ids.iter().any(|id| id == &other_user_id));
+    assert!(user_ids.iter().any(|id| id == &third_user_id));
+}
@@ -0,0 +1,51 @@
+extern crate matrix_rocketchat;
+
+use matrix_rocketchat::log::{redact_secrets, redact_secrets_with_keys};
+
+#[test]
+fn secrets_in_json_are_redacted() {
+    let json = r#"{"username": "spec_user", "password": "sec\"ret", "rocketchat_token": "rt", "totp_code": 123456}"#;
+    assert_eq!(
+        redact_secrets(json),
+        r#"{"username": "spec_user", "password": "[REDACTED]", "rocketchat_token": "[REDACTED]", "totp_code": [REDACTED]}"#
+    );
+}
+
+#[test]
+fn generic_keys_are_only_redacted_when_they_are_passed_explicitly() {
+    let json = r#"{"token": "rt", "code": 404, "text": "spec message"}"#;
+    assert_eq!(redact_secrets(json), json);
+    assert_eq!(
+        redact_secrets_with_keys(json, &["token"]),
+        r#"{"token": "[REDACTED]", "code": 404, "text": "spec message"}"#
+    );
+}
+
+#[test]
+fn secrets_in_query_strings_and_form_data_are_redacted() {
+    let url = "Could not call REST API endpoint http://localhost:8008/_matrix/client/r0/register?access_token=at&kind=user";
+    assert_eq!(
+        redact_secrets(url),
+        "Could not call REST API endpoint http://localhost:8008/_matrix/client/r0/register?access_token=[REDACTED]&kind=user"
+    );
+
+    let form_data = "csrf_token=abc&username=spec_user&password=secret";
+    assert_eq!(redact_secrets(form_data), "csrf_token=[REDACTED]&username=spec_user&password=[REDACTED]");
+}
+
+#[test]
+fn keys_are_compared_case_insensitive() {
+    assert_eq!(redact_secrets(r#"{"authToken": "spec_auth_token"}"#), r#"{"authToken": "[REDACTED]"}"#);
+}
+
+#[test]
+fn keys_that_only_contain_a_secret_key_are_not_redacted() {
+    let text = r#"{"errcode": "M_FORBIDDEN", "tokens": "3", "my_password": "unchanged"} tokenize=unchanged"#;
+    assert_eq!(redact_secrets(text), text);
+}
+
+#[test]
+fn text_without_secrets_is_not_changed() {
+    let text = "Could not process request, the submitted data is not valid: `not json` äöü";
+    assert_eq!(redact_secrets(text), text);
+}
@@ -0,0 +1,267 @@
+#![feature(try_from)]
+
+extern crate iron;
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate reqwest;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::sync::mpsc::Receiver;
+
+use iron::status;
+use matrix_rocketchat::api::RestApi;
+use matrix_rocketchat::api::rocketchat::v1::{LOGIN_PATH, ME_PATH};
+use matrix_rocketchat::db::{LoginToken, MAX_FAILED_LOGIN_ATTEMPTS, UserOnRocketchatServer};
+use matrix_rocketchat_test::{MessageForwarder, Test, default_timeout, handlers, helpers};
+use reqwest::{Method, StatusCode};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomId, UserId};
+
+#[test]
+fn the_user_gets_a_login_link_when_sending_login_without_arguments() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    let token = request_login_link(&test, &receiver);
+
+    let connection = test.connection_pool.get().unwrap();
+    let login_token = LoginToken::find_valid(&connection, &token).unwrap().unwrap();
+    assert_eq!(login_token.matrix_user_id, UserId::try_from("@spec_user:localhost").unwrap());
+    assert_eq!(login_token.admin_room_id, RoomId::try_from("!admin_room_id:localhost").unwrap());
+    assert_eq!(login_token.rocketchat_server_id, "rc_id");
+}
+
+#[test]
+fn successfully_login_via_the_login_page() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        LOGIN_PATH,
+        handlers::RocketchatLogin {
+            successful: true,
+            rocketchat_user_id: None,
+        },
+        "login",
+    );
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    let token = request_login_link(&test, &receiver);
+    let (login_page, status_code) = call_login_page(&test, Method::Get, &token, "");
+    assert_eq!(status_code, status::Ok);
+    assert!(login_page.contains("<form method=\"post\">"));
+
+    let payload = format!("csrf_token={}&username=spec_user&password=secret", csrf_token(&login_page));
+    let (response, status_code) = call_login_page(&test, Method::Post, &token, &payload);
+    assert_eq!(status_code, status::Ok);
+    assert!(response.contains("You are logged in. Return to your Matrix client"));
+
+    // the admin room gets a confirmation
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("You are logged in."));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(user_on_rocketchat_server.is_logged_in());
+
+    // the link can only be used once
+    let (response, status_code) = call_login_page(&test, Method::Get, &token, "");
+    assert_eq!(status_code, status::NotFound);
+    assert!(response.contains("This login link is invalid or expired."));
+}
+
+#[test]
+fn the_login_form_is_shown_again_when_the_login_failes() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        LOGIN_PATH,
+        handlers::RocketchatLogin {
+            successful: false,
+            rocketchat_user_id: None,
+        },
+        "login",
+    );
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    let token = request_login_link(&test, &receiver);
+    let (login_page, _) = call_login_page(&test, Method::Get, &token, "");
+
+    let payload = format!("csrf_token={}&username=spec_user&password=wrong_password", csrf_token(&login_page));
+    let (response, status_code) = call_login_page(&test, Method::Post, &token, &payload);
+    assert_eq!(status_code, status::Unauthorized);
+    assert!(response.contains("Authentication failed!"));
+    assert!(response.contains("<form method=\"post\">"));
+
+    // the link can still be used after a failed login
+    let (_, status_code) = call_login_page(&test, Method::Get, &token, "");
+    assert_eq!(status_code, status::Ok);
+}
+
+#[test]
+fn the_login_page_asks_for_the_two_factor_authentication_code_when_it_is_required() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(LOGIN_PATH, handlers::RocketchatTwoFactorLogin { totp_code: "123456" }, "login");
+    rocketchat_router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    let token = request_login_link(&test, &receiver);
+    let (login_page, _) = call_login_page(&test, Method::Get, &token, "");
+
+    let payload = format!("csrf_token={}&username=spec_user&password=secret", csrf_token(&login_page));
+    let (totp_page, status_code) = call_login_page(&test, Method::Post, &token, &payload);
+    assert_eq!(status_code, status::Ok);
+    assert!(totp_page.contains("Two-factor authentication is enabled for your account."));
+    assert!(totp_page.contains("name=\"totp_code\""));
+    assert!(!totp_page.contains("name=\"password\""));
+
+    let payload = format!("csrf_token={}&totp_code=654321", csrf_token(&totp_page));
+    let (response, _) = call_login_page(&test, Method::Post, &token, &payload);
+    assert!(response.contains("The two-factor authentication code is invalid, please try again."));
+    assert!(response.contains("name=\"totp_code\""));
+
+    let payload = format!("csrf_token={}&totp_code=123456", csrf_token(&totp_page));
+    let (response, status_code) = call_login_page(&test, Method::Post, &token, &payload);
+    assert_eq!(status_code, status::Ok);
+    assert!(response.contains("You are logged in. Return to your Matrix client"));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn the_login_link_cannot_be_used_anymore_after_too_many_failed_logins() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let mut rocketchat_router = Router::new();
+    rocketchat_router.post(
+        LOGIN_PATH,
+        handlers::RocketchatLogin {
+            successful: false,
+            rocketchat_user_id: None,
+        },
+        "login",
+    );
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .run();
+
+    let token = request_login_link(&test, &receiver);
+    let (login_page, _) = call_login_page(&test, Method::Get, &token, "");
+    let payload = format!("csrf_token={}&username=spec_user&password=wrong_password", csrf_token(&login_page));
+
+    for _ in 1..MAX_FAILED_LOGIN_ATTEMPTS {
+        let (response, _) = call_login_page(&test, Method::Post, &token, &payload);
+        assert!(response.contains("Authentication failed!"));
+    }
+
+    let (response, _) = call_login_page(&test, Method::Post, &token, &payload);
+    assert!(response.contains("The login failed too many times, this login link can't be used anymore."));
+
+    let (response, status_code) = call_login_page(&test, Method::Get, &token, "");
+    assert_eq!(status_code, status::NotFound);
+    assert!(response.contains("This login link is invalid or expired."));
+}
+
+#[test]
+fn attempt_to_submit_the_login_form_with_an_invalid_csrf_token() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+    let test = test.with_matrix_routes(matrix_router).with_rocketchat_mock().with_connected_admin_room().run();
+
+    let token = request_login_link(&test, &receiver);
+
+    let payload = "csrf_token=forged_token&username=spec_user&password=secret";
+    let (response, status_code) = call_login_page(&test, Method::Post, &token, payload);
+    assert_eq!(status_code, status::Forbidden);
+    assert!(response.contains("The login form could not be verified."));
+
+    let connection = test.connection_pool.get().unwrap();
+    let user_on_rocketchat_server =
+        UserOnRocketchatServer::find(&connection, &UserId::try_from("@spec_user:localhost").unwrap(), "rc_id".to_string())
+            .unwrap();
+    assert!(!user_on_rocketchat_server.is_logged_in());
+}
+
+#[test]
+fn attempt_to_open_the_login_page_with_an_invalid_token() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().run();
+
+    let (response, status_code) = call_login_page(&test, Method::Get, "invalid_token", "");
+    assert_eq!(status_code, status::NotFound);
+    assert!(response.contains("This login link is invalid or expired."));
+}
+
+fn request_login_link(test: &Test, receiver: &Receiver<String>) -> String {
+    helpers::send_room_message_from_matrix(
+        &test.config.as_url,
+        RoomId::try_from("!admin_room_id:localhost").unwrap(),
+        UserId::try_from("@spec_user:localhost").unwrap(),
+        "login".to_string(),
+    );
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let login_url = format!("{}/rocketchat/login/", test.config.as_url);
+    let token_start = message_received_by_matrix.find(&login_url).unwrap() + login_url.len();
+    message_received_by_matrix[token_start..].chars().take_while(|c| c.is_alphanumeric()).collect()
+}
+
+fn call_login_page(test: &Test, method: Method, token: &str, payload: &str) -> (String, StatusCode) {
+    let url = format!("http://{}/rocketchat/login/{}", test.as_listening.as_ref().unwrap().socket, token);
+    RestApi::call(method, &url, payload, &HashMap::new(), None).unwrap()
+}
+
+fn csrf_token(login_page: &str) -> String {
+    let csrf_token_field = "name=\"csrf_token\" value=\"";
+    let csrf_token_start = login_page.find(csrf_token_field).unwrap() + csrf_token_field.len();
+    login_page[csrf_token_start..].chars().take_while(|c| *c != '"').collect()
+}
@@ -14,7 +14,7 @@ use persistent::Write;
 use router::Router;
 use ruma_client_api::r0::alias::get_alias;
 use ruma_client_api::r0::account::register;
-use ruma_client_api::r0::membership::invite_user;
+use ruma_client_api::r0::membership::{invite_user, kick_user};
 use ruma_client_api::r0::room::create_room;
 use ruma_client_api::r0::sync::get_member_events;
 use ruma_events::EventType;
@@ -83,6 +83,42 @@ impl Handler for RocketchatLogin {
     }
 }
 
+pub struct RocketchatTwoFactorLogin {
+    pub totp_code: &'static str,
+}
+
+impl Handler for RocketchatTwoFactorLogin {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got two-factor login request");
+
+        let request_payload = extract_payload(request);
+        let login_payload: serde_json::Value = serde_json::from_str(&request_payload).unwrap();
+
+        let error = match login_payload["code"].as_str() {
+            Some(code) if code == self.totp_code => {
+                let payload = r#"{
+                    "status": "success",
+                    "data": {
+                        "authToken": "spec_auth_token",
+                        "userId": "spec_user_id"
+                    }
+                 }"#;
+                return Ok(Response::with((status::Ok, payload)));
+            }
+            Some(_) => "totp-invalid",
+            None => "totp-required",
+        };
+
+        let error_response = RocketchatErrorResponse {
+            status: Some("error".to_string()),
+            message: Some(format!("TOTP error [{}]", error)),
+            error: Some(error.to_string()),
+        };
+        let payload = serde_json::to_string(&error_response).unwrap();
+        Ok(Response::with((status::Unauthorized, payload)))
+    }
+}
+
 pub struct RocketchatMe {
     pub username: String,
 }
@@ -103,15 +139,27 @@ impl Handler for RocketchatMe {
 pub struct RocketchatChannelsList {
     pub channels: HashMap<&'static str, Vec<&'static str>>,
     pub status: status::Status,
+    pub max_count: Option<usize>,
 }
 
 impl Handler for RocketchatChannelsList {
-    fn handle(&self, _request: &mut Request) -> IronResult<Response> {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
         debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got channel list request");
 
-        let mut channels: Vec<String> = Vec::new();
+        let url: Url = request.url.clone().into();
+        let query_pairs: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let offset = query_pairs.get("offset").and_then(|offset| offset.parse().ok()).unwrap_or(0);
+        let mut count = query_pairs.get("count").and_then(|count| count.parse().ok()).unwrap_or(self.channels.len());
+        if let Some(max_count) = self.max_count {
+            count = ::std::cmp::min(count, max_count);
+        }
 
-        for (channel_name, user_names) in self.channels.iter() {
+        let mut channel_names: Vec<&'static str> = self.channels.keys().cloned().collect();
+        channel_names.sort();
+
+        let mut channels: Vec<String> = Vec::new();
+        for channel_name in channel_names.iter().skip(offset).take(count) {
+            let user_names = &self.channels[channel_name];
             let channel = r#"{
                 "_id": "CHANNEL_NAME_id",
                 "name": "CHANNEL_NAME",
@@ -134,7 +182,13 @@ impl Handler for RocketchatChannelsList {
             channels.push(channel);
         }
 
-        let payload = "{ \"channels\": [".to_string() + &channels.join(",") + "], \"success\": true }";
+        let payload = format!(
+            "{{ \"channels\": [{}], \"offset\": {}, \"count\": {}, \"total\": {}, \"success\": true }}",
+            channels.join(","),
+            offset,
+            channels.len(),
+            self.channels.len()
+        );
 
         Ok(Response::with((self.status, payload)))
     }
@@ -173,7 +227,237 @@ impl Handler for RocketchatDirectMessagesList {
     }
 }
 
+pub struct RocketchatDirectMessagesCreate {}
+
+impl Handler for RocketchatDirectMessagesCreate {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got direct messages create request");
+
+        let user_id = request
+            .headers
+            .get_raw("X-User-Id")
+            .and_then(|values| values.first().cloned())
+            .map(|value| String::from_utf8(value).unwrap())
+            .unwrap_or_default();
+        let request_payload = extract_payload(request);
+        let payload: serde_json::Value = serde_json::from_str(&request_payload).unwrap();
+        let username = payload["username"].as_str().unwrap_or_default();
+        // the IDs of the mock users are the username followed by `_id`
+        let own_username = user_id.rfind("_id").map(|suffix_start| user_id[..suffix_start].to_string()).unwrap_or_default();
+
+        // the ID of a direct message channel consists of the IDs of both participants
+        let payload = r#"{
+            "room": {
+                "_id": "USER_ID_USERNAME_id",
+                "_updatedAt": "2017-05-25T21:51:04.429Z",
+                "t": "d",
+                "msgs": 0,
+                "ts": "2017-05-12T14:49:01.806Z",
+                "usernames": [
+                    "OWN_NAME",
+                    "USERNAME"
+                ]
+            },
+            "success": true
+        }"#
+            .replace("USER_ID", &user_id)
+            .replace("OWN_NAME", &own_username)
+            .replace("USERNAME", username);
+
+        Ok(Response::with((status::Ok, payload)))
+    }
+}
 
+pub struct RocketchatGroupsList {
+    pub groups: HashMap<&'static str, Vec<&'static str>>,
+    pub status: status::Status,
+}
+
+impl Handler for RocketchatGroupsList {
+    fn handle(&self, _request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got groups list request");
+
+        let mut groups = Vec::new();
+        for (group_name, user_names) in self.groups.iter() {
+            let group = r#"{
+                "_id": "GROUP_NAME_id",
+                "name": "GROUP_NAME",
+                "t": "p",
+                "usernames": [
+                    "GROUP_USERNAMES"
+                ],
+                "msgs": 0,
+                "u": {
+                    "_id": "spec_user_id",
+                    "username": "spec_username"
+                },
+                "ts": "2017-02-12T13:20:22.092Z",
+                "ro": false,
+                "sysMes": true,
+                "_updatedAt": "2017-02-12T13:20:22.092Z"
+            }"#
+                .replace("GROUP_NAME", group_name)
+                .replace("GROUP_USERNAMES", &user_names.join("\",\""));
+            groups.push(group);
+        }
+
+        let payload = "{ \"groups\": [".to_string() + &groups.join(",") + "], \"success\": true }";
+
+        Ok(Response::with((self.status, payload)))
+    }
+}
+
+pub struct RocketchatRoomRoles {
+    pub roles: HashMap<&'static str, Vec<&'static str>>,
+}
+
+impl Handler for RocketchatRoomRoles {
+    fn handle(&self, _request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got room roles request");
+
+        let mut roles = Vec::new();
+        for (username, user_roles) in self.roles.iter() {
+            let room_roles = r#"{
+                "_id": "USERNAME_roles_id",
+                "rid": "spec_channel_id",
+                "u": {
+                    "_id": "USERNAME_id",
+                    "username": "USERNAME"
+                },
+                "roles": [
+                    "ROLES"
+                ]
+            }"#
+                .replace("USERNAME", username)
+                .replace("ROLES", &user_roles.join("\",\""));
+            roles.push(room_roles);
+        }
+
+        let payload = "{ \"roles\": [".to_string() + &roles.join(",") + "], \"success\": true }";
+
+        Ok(Response::with((status::Ok, payload)))
+    }
+}
+
+pub struct RocketchatChannelsInfo {
+    pub channels: HashMap<&'static str, Vec<&'static str>>,
+}
+
+impl Handler for RocketchatChannelsInfo {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got channels info request");
+
+        let url: Url = request.url.clone().into();
+        let query_pairs: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let room_id = query_pairs.get("roomId").cloned().unwrap_or_default();
+
+        let channel = self.channels.iter().find(|&(channel_name, _)| format!("{}_id", channel_name) == room_id);
+        let (status, payload) = match channel {
+            Some((channel_name, user_names)) => {
+                let payload = r#"{
+                    "channel": {
+                        "_id": "CHANNEL_NAME_id",
+                        "name": "CHANNEL_NAME",
+                        "t": "c",
+                        "usernames": [
+                            "CHANNEL_USERNAMES"
+                        ],
+                        "msgs": 0,
+                        "ts": "2017-02-12T13:20:22.092Z",
+                        "_updatedAt": "2017-02-12T13:20:22.092Z"
+                    },
+                    "success": true
+                }"#
+                    .replace("CHANNEL_NAME", channel_name)
+                    .replace("CHANNEL_USERNAMES", &user_names.join("\",\""));
+                (status::Ok, payload)
+            }
+            None => (status::BadRequest, r#"{"success": false, "error": "Channel not found"}"#.to_string()),
+        };
+
+        Ok(Response::with((status, payload)))
+    }
+}
+
+pub struct RocketchatGroupsInfo {
+    pub groups: HashMap<&'static str, Vec<&'static str>>,
+}
+
+impl Handler for RocketchatGroupsInfo {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got groups info request");
+
+        let url: Url = request.url.clone().into();
+        let query_pairs: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let room_id = query_pairs.get("roomId").cloned().unwrap_or_default();
+
+        let group = self.groups.iter().find(|&(group_name, _)| format!("{}_id", group_name) == room_id);
+        let (status, payload) = match group {
+            Some((group_name, user_names)) => {
+                let payload = r#"{
+                    "group": {
+                        "_id": "GROUP_NAME_id",
+                        "name": "GROUP_NAME",
+                        "t": "p",
+                        "usernames": [
+                            "GROUP_USERNAMES"
+                        ],
+                        "msgs": 0,
+                        "ts": "2017-02-12T13:20:22.092Z",
+                        "_updatedAt": "2017-02-12T13:20:22.092Z"
+                    },
+                    "success": true
+                }"#
+                    .replace("GROUP_NAME", group_name)
+                    .replace("GROUP_USERNAMES", &user_names.join("\",\""));
+                (status::Ok, payload)
+            }
+            None => (status::BadRequest, r#"{"success": false, "error": "Group not found"}"#.to_string()),
+        };
+
+        Ok(Response::with((status, payload)))
+    }
+}
+
+pub struct RocketchatRoomsInfo {
+    pub direct_messages: HashMap<&'static str, Vec<&'static str>>,
+    pub status: status::Status,
+}
+
+impl Handler for RocketchatRoomsInfo {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Rocket.Chat mock server got rooms info request");
+
+        let url: Url = request.url.clone().into();
+        let query_pairs: HashMap<String, String> = url.query_pairs().into_owned().collect();
+        let room_id = query_pairs.get("roomId").cloned().unwrap_or_default();
+
+        let (status, payload) = match self.direct_messages.get(room_id.as_str()) {
+            Some(user_names) => {
+                let payload = r#"{
+                    "room": {
+                        "_id": "DIRECT_MESSAGE_ID",
+                        "_updatedAt": "2017-05-25T21:51:04.429Z",
+                        "t": "d",
+                        "msgs": 5,
+                        "ts": "2017-05-12T14:49:01.806Z",
+                        "lm": "2017-05-25T21:51:04.414Z",
+                        "usernames": [
+                            "USER_NAMES"
+                        ]
+                    },
+                    "success": true
+                }"#
+                    .replace("DIRECT_MESSAGE_ID", &room_id)
+                    .replace("USER_NAMES", &user_names.join("\",\""));
+                (self.status, payload)
+            }
+            None => (status::BadRequest, r#"{"success": false, "error": "Not allowed [error-not-allowed]"}"#.to_string()),
+        };
+
+        Ok(Response::with((status, payload)))
+    }
+}
 
 pub struct RocketchatUsersInfo {}
 
@@ -184,8 +468,17 @@ impl Handler for RocketchatUsersInfo {
         let url: Url = request.url.clone().into();
         let mut query_pairs = url.query_pairs();
 
-        let (status, payload) = match query_pairs.find(|&(ref key, _)| key == "username") {
-            Some((_, ref username)) => {
+        // the IDs of the mock users are the username followed by `_id`
+        let username = query_pairs.find(|&(ref key, _)| key == "username" || key == "userId").and_then(|(key, value)| {
+            if key == "username" {
+                Some(value.to_string())
+            } else {
+                value.rfind("_id").map(|suffix_start| value[..suffix_start].to_string())
+            }
+        });
+
+        let (status, payload) = match username {
+            Some(ref username) => {
                 (
                     status::Ok,
                     r#"{
@@ -560,6 +853,34 @@ impl Handler for GetRoomAlias {
     }
 }
 
+pub struct PutRoomAlias {}
+
+impl Handler for PutRoomAlias {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Matrix mock server got put room alias request");
+
+        let params = request.extensions.get::<Router>().unwrap().clone();
+        let url_room_alias = params.find("room_alias").unwrap();
+        let decoded_room_alias = percent_decode(url_room_alias.as_bytes()).decode_utf8().unwrap();
+        let room_alias = RoomAliasId::try_from(decoded_room_alias.as_ref()).unwrap();
+
+        let request_payload = extract_payload(request);
+        let payload: serde_json::Value = serde_json::from_str(&request_payload).unwrap();
+        let room_id = RoomId::try_from(payload["room_id"].as_str().unwrap_or_default()).unwrap();
+
+        if let Err(err) = add_alias_to_room(request, room_id, room_alias) {
+            debug!(DEFAULT_LOGGER, "{}", err);
+            let payload = r#"{
+                "errcode":"M_UNKNOWN",
+                "error":"Room alias already exists."
+            }"#;
+            return Ok(Response::with((status::Conflict, payload.to_string())));
+        }
+
+        Ok(Response::with((status::Ok, "{}".to_string())))
+    }
+}
+
 pub struct DeleteRoomAlias {}
 
 impl Handler for DeleteRoomAlias {
@@ -604,6 +925,7 @@ impl Handler for GetRoomState {
         let user_id = user_id_from_request(request);
 
         let state_result = match serde_json::from_value::<EventType>(event_type_value).unwrap() {
+            EventType::RoomPowerLevels => return get_power_levels_from_room(request, room_id, user_id),
             EventType::RoomCreate => get_state_from_room(request, room_id, user_id.clone(), "creator".to_string()),
             EventType::RoomCanonicalAlias => get_state_from_room(request, room_id, user_id.clone(), "alias".to_string()),
             EventType::RoomTopic => get_state_from_room(request, room_id, user_id.clone(), "topic".to_string()),
@@ -1036,6 +1358,78 @@ fn get_state_from_room(
     Ok(Some((state_key.clone(), room_state.to_string())))
 }
 
+fn get_power_levels_from_room(request: &mut Request, room_id: RoomId, user_id: UserId) -> IronResult<Response> {
+    let mut power_levels = serde_json::Map::new();
+    for key in &["ban", "events", "invite", "kick", "redact", "users"] {
+        match get_state_from_room(request, room_id.clone(), user_id.clone(), key.to_string()) {
+            Ok(Some((k, v))) => {
+                power_levels.insert(k, serde_json::from_str(&v).unwrap());
+            }
+            Ok(None) => {}
+            Err(err) => {
+                let payload = r#"{
+                    "errcode":"M_GUEST_ACCESS_FORBIDDEN",
+                    "error":"ERR_MSG"
+                }"#
+                    .replace("ERR_MSG", err);
+                return Ok(Response::with((status::Forbidden, payload.to_string())));
+            }
+        }
+    }
+
+    if power_levels.is_empty() {
+        let payload = r#"{
+            "errcode":"M_NOT_FOUND",
+            "error":"Event not found."
+        }"#;
+        return Ok(Response::with((status::NotFound, payload.to_string())));
+    }
+
+    let payload = serde_json::to_string(&power_levels).unwrap();
+    Ok(Response::with((status::Ok, payload)))
+}
+
+pub struct MatrixKickUser {
+    pub as_url: String,
+}
+
+impl MatrixKickUser {
+    pub fn with_forwarder(as_url: String) -> (Chain, Receiver<String>) {
+        let (message_forwarder, receiver) = MessageForwarder::new();
+        let mut chain = Chain::new(MatrixKickUser { as_url: as_url });
+        chain.link_before(message_forwarder);
+        (chain, receiver)
+    }
+}
+
+impl Handler for MatrixKickUser {
+    fn handle(&self, request: &mut Request) -> IronResult<Response> {
+        debug!(DEFAULT_LOGGER, "Matrix mock server got kick user request");
+        let params = request.extensions.get::<Router>().unwrap().clone();
+        let url_room_id = params.find("room_id").unwrap();
+        let decoded_room_id = percent_decode(url_room_id.as_bytes()).decode_utf8().unwrap();
+        let room_id = RoomId::try_from(decoded_room_id.as_ref()).unwrap();
+
+        let request_payload = extract_payload(request);
+        let kick_payload: kick_user::BodyParams = serde_json::from_str(&request_payload).unwrap();
+        let user_id = kick_payload.user_id.clone();
+
+        if let Err(err) = add_membership_event_to_room(request, user_id.clone(), room_id.clone(), MembershipState::Leave) {
+            debug!(DEFAULT_LOGGER, "{}", err);
+            let payload = r#"{
+                    "errcode":"M_UNKNOWN",
+                    "error":"ERR_MSG"
+                }"#
+                .replace("ERR_MSG", err);
+            return Ok(Response::with((status::Conflict, payload.to_string())));
+        }
+
+        helpers::send_leave_event_from_matrix(&self.as_url, room_id, user_id);
+
+        Ok(Response::with((status::Ok, "{}")))
+    }
+}
+
 fn add_membership_event_to_room(
     request: &mut Request,
     user_id: UserId,
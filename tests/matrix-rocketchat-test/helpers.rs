@@ -117,6 +117,57 @@ pub fn send_leave_event_from_matrix(as_url: &str, room_id: RoomId, user_id: User
     simulate_message_from_matrix(as_url, &leave_payload);
 }
 
+pub fn send_kick_event_from_matrix(as_url: &str, room_id: RoomId, user_id: UserId, kicker_id: UserId) {
+    let kick_event = MemberEvent {
+        content: MemberEventContent {
+            avatar_url: None,
+            displayname: None,
+            membership: MembershipState::Leave,
+            third_party_invite: None,
+        },
+        event_id: EventId::new("localhost").unwrap(),
+        event_type: EventType::RoomMember,
+        invite_room_state: None,
+        prev_content: None,
+        room_id: room_id,
+        state_key: format!("{}", user_id),
+        unsigned: None,
+        user_id: kicker_id,
+    };
+
+    let events = Events { events: vec![Box::new(Event::RoomMember(kick_event))] };
+    let kick_payload = to_string(&events).unwrap();
+    simulate_message_from_matrix(as_url, &kick_payload);
+}
+
+pub fn send_display_name_change_event_from_matrix(as_url: &str, room_id: RoomId, user_id: UserId, display_name: String) {
+    let display_name_event = MemberEvent {
+        content: MemberEventContent {
+            avatar_url: None,
+            displayname: Some(display_name),
+            membership: MembershipState::Join,
+            third_party_invite: None,
+        },
+        event_id: EventId::new("localhost").unwrap(),
+        event_type: EventType::RoomMember,
+        invite_room_state: None,
+        prev_content: Some(MemberEventContent {
+            avatar_url: None,
+            displayname: None,
+            membership: MembershipState::Join,
+            third_party_invite: None,
+        }),
+        room_id: room_id,
+        state_key: format!("{}", user_id),
+        unsigned: None,
+        user_id: user_id,
+    };
+
+    let events = Events { events: vec![Box::new(Event::RoomMember(display_name_event))] };
+    let display_name_payload = to_string(&events).unwrap();
+    simulate_message_from_matrix(as_url, &display_name_payload);
+}
+
 pub fn send_room_message_from_matrix(as_url: &str, room_id: RoomId, user_id: UserId, body: String) {
     let message_event = MessageEvent {
         content: MessageEventContent::Text(TextMessageEventContent {
@@ -176,5 +227,5 @@ pub fn logout_user_from_rocketchat_server_on_bridge(
     let mut user_on_rocketchat_server = UserOnRocketchatServer::find(connection, &matrix_user_id, rocketchat_server_id)
         .unwrap();
     let rocketchat_user_id = user_on_rocketchat_server.rocketchat_user_id.clone();
-    user_on_rocketchat_server.set_credentials(connection, rocketchat_user_id, None).unwrap();
+    user_on_rocketchat_server.set_credentials(connection, None, rocketchat_user_id, None).unwrap();
 }
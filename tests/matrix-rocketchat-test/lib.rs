@@ -47,7 +47,10 @@ use iron::prelude::*;
 use iron::typemap::Key;
 use matrix_rocketchat::{Config, Server};
 use matrix_rocketchat::api::MatrixApi;
-use matrix_rocketchat::api::rocketchat::v1::{CHANNELS_LIST_PATH, LOGIN_PATH, ME_PATH, USERS_INFO_PATH};
+use matrix_rocketchat::api::rocketchat::v1::{CHANNELS_INFO_PATH, CHANNELS_LIST_PATH, CHANNELS_ROLES_PATH,
+                                             DIRECT_MESSAGES_CREATE_PATH, GROUPS_INFO_PATH, GROUPS_LIST_PATH, GROUPS_ROLES_PATH,
+                                             LOGIN_PATH, ME_PATH, USERS_INFO_PATH};
+use matrix_rocketchat::config::default_power_levels;
 use matrix_rocketchat::db::ConnectionPool;
 use persistent::Write;
 use r2d2::Pool;
@@ -55,11 +58,13 @@ use r2d2_diesel::ConnectionManager;
 use router::Router;
 use ruma_identifiers::{RoomAliasId, RoomId, UserId};
 use ruma_client_api::Endpoint;
+use ruma_client_api::r0::alias::create_alias::Endpoint as CreateAliasEndpoint;
 use ruma_client_api::r0::alias::get_alias::Endpoint as GetAliasEndpoint;
 use ruma_client_api::r0::alias::delete_alias::Endpoint as DeleteAliasEndpoint;
 use ruma_client_api::r0::membership::invite_user::Endpoint as InviteUserEndpoint;
 use ruma_client_api::r0::account::register::Endpoint as RegisterEndpoint;
 use ruma_client_api::r0::membership::join_room_by_id::Endpoint as JoinRoomByIdEndpoint;
+use ruma_client_api::r0::membership::kick_user::Endpoint as KickUserEndpoint;
 use ruma_client_api::r0::membership::leave_room::Endpoint as LeaveRoomEndpoint;
 use ruma_client_api::r0::room::create_room::Endpoint as CreateRoomEndpoint;
 use ruma_client_api::r0::send::send_state_event_for_empty_key::Endpoint as SendStateEventForEmptyKeyEndpoint;
@@ -79,6 +84,8 @@ const AS_TOKEN: &'static str = "at";
 pub const HS_TOKEN: &'static str = "ht";
 /// Rocket.Chat token used in the tests
 pub const RS_TOKEN: &'static str = "rt";
+/// Key that is used to encrypt the tokens in the test database
+pub const DATABASE_ENCRYPTION_KEY: &'static str = "p9GrHzfpgj/I3sC4KAa+QkgI6MCtHj210oBblbOWrVo=";
 /// Number of threads that iron uses when running tests
 pub const IRON_THREADS: usize = 4;
 /// The version the mock Rocket.Chat server announces
@@ -180,6 +187,9 @@ pub struct Test {
     pub config: Config,
     /// Connection pool to get connection to the test database
     pub connection_pool: Pool<ConnectionManager<SqliteConnection>>,
+    /// A list of Rocket.Chat private groups that are returned when querying the Rocket.Chat mock
+    /// groups.list endpoint
+    pub groups: Option<HashMap<&'static str, Vec<&'static str>>>,
     /// The matrix homeserver mock listening server
     pub hs_listening: Option<Listening>,
     /// Routes that the homeserver mock can handle
@@ -188,6 +198,9 @@ pub struct Test {
     pub rocketchat_mock_router: Option<Router>,
     /// The Rocket.Chat mock listening server
     pub rocketchat_listening: Option<Listening>,
+    /// Roles of Rocket.Chat users that are returned when querying the Rocket.Chat mock
+    /// channels.roles and groups.roles endpoints
+    pub room_roles: Option<HashMap<&'static str, Vec<&'static str>>>,
     /// The URL of the Rocket.Chat mock server
     pub rocketchat_mock_url: Option<String>,
     /// Temp directory to store data during the test, it has to be part of the struct so that it
@@ -215,12 +228,14 @@ impl Test {
             channels: None,
             config: config,
             connection_pool: connection_pool,
+            groups: None,
             hs_listening: None,
             with_logged_in_user: false,
             matrix_homeserver_mock_router: None,
             rocketchat_mock_router: None,
             rocketchat_listening: None,
             rocketchat_mock_url: None,
+            room_roles: None,
             temp_dir: temp_dir,
             with_admin_room: false,
             with_connected_admin_room: false,
@@ -283,6 +298,20 @@ impl Test {
         self
     }
 
+    /// Set the roles of Rocket.Chat users that are returned when querying the Rocket.Chat mock
+    /// channels.roles and groups.roles endpoints
+    pub fn with_custom_room_roles(mut self, room_roles: HashMap<&'static str, Vec<&'static str>>) -> Test {
+        self.room_roles = Some(room_roles);
+        self
+    }
+
+    /// Set a list of Rocket.Chat private groups that are returned when querying the Rocket.Chat
+    /// mock groups.list endpoint
+    pub fn with_custom_groups_list(mut self, groups: HashMap<&'static str, Vec<&'static str>>) -> Test {
+        self.groups = Some(groups);
+        self
+    }
+
     /// Run the application service so that a test can interact with it.
     pub fn run(mut self) -> Test {
         self.run_matrix_homeserver_mock();
@@ -368,6 +397,7 @@ impl Test {
             );
             router.get(ME_PATH, handlers::RocketchatMe { username: "spec_user".to_string() }, "me");
             router.get(USERS_INFO_PATH, handlers::RocketchatUsersInfo {}, "users_info");
+            router.post(DIRECT_MESSAGES_CREATE_PATH, handlers::RocketchatDirectMessagesCreate {}, "direct_messages_create");
         }
 
         let mut channels = match self.channels.clone() {
@@ -385,12 +415,37 @@ impl Test {
                 CHANNELS_LIST_PATH,
                 handlers::RocketchatChannelsList {
                     status: status::Ok,
-                    channels: channels,
+                    channels: channels.clone(),
+                    max_count: None,
                 },
                 "channels_list",
             );
+            router.get(CHANNELS_INFO_PATH, handlers::RocketchatChannelsInfo { channels: channels }, "channels_info");
         }
 
+        let groups = match self.groups.clone() {
+            Some(groups) => groups,
+            None => HashMap::new(),
+        };
+
+        router.get(
+            GROUPS_LIST_PATH,
+            handlers::RocketchatGroupsList {
+                status: status::Ok,
+                groups: groups.clone(),
+            },
+            "groups_list",
+        );
+        router.get(GROUPS_INFO_PATH, handlers::RocketchatGroupsInfo { groups: groups }, "groups_info");
+
+        let room_roles = match self.room_roles.clone() {
+            Some(room_roles) => room_roles,
+            None => HashMap::new(),
+        };
+
+        router.get(CHANNELS_ROLES_PATH, handlers::RocketchatRoomRoles { roles: room_roles.clone() }, "channels_roles");
+        router.get(GROUPS_ROLES_PATH, handlers::RocketchatRoomRoles { roles: room_roles }, "groups_roles");
+
         thread::spawn(move || {
             let mut server = Iron::new(router);
             server.threads = IRON_THREADS;
@@ -475,6 +530,9 @@ impl Test {
         let leave_room_handler = handlers::MatrixLeaveRoom { as_url: self.config.as_url.clone() };
         router.post(LeaveRoomEndpoint::router_path(), leave_room_handler, "leave_room");
 
+        let kick_user_handler = handlers::MatrixKickUser { as_url: self.config.as_url.clone() };
+        router.post(KickUserEndpoint::router_path(), kick_user_handler, "kick_user");
+
         router.get("/_matrix/client/versions", handlers::MatrixVersion { versions: default_matrix_api_versions() }, "versions");
 
         let mut get_state_event = Chain::new(handlers::GetRoomState {});
@@ -504,6 +562,8 @@ impl Test {
         get_room_alias.link_before(handlers::PermissionCheck {});
         router.get(GetAliasEndpoint::router_path(), get_room_alias, "get_room_alias");
 
+        router.put(CreateAliasEndpoint::router_path(), handlers::PutRoomAlias {}, "put_room_alias");
+
         router.delete(DeleteAliasEndpoint::router_path(), handlers::DeleteRoomAlias {}, "delete_room_alias");
 
         router.post("*", handlers::EmptyJson {}, "default_post");
@@ -550,6 +610,8 @@ pub fn build_test_config(temp_dir: &TempDir) -> Config {
         use_ssl: false,
         ssl_certificate_path: None,
         ssl_key_path: None,
+        database_encryption_key: Some(DATABASE_ENCRYPTION_KEY.to_string()),
+        power_levels: default_power_levels(),
     }
 }
 
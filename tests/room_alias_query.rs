@@ -0,0 +1,166 @@
+#![feature(try_from)]
+
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate reqwest;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use matrix_rocketchat::api::{MatrixApi, RestApi};
+use matrix_rocketchat::db::{NewUser, NewUserOnRocketchatServer, Room, User, UserOnRocketchatServer};
+use matrix_rocketchat_test::{DEFAULT_LOGGER, HS_TOKEN, Test, default_timeout, handlers};
+use reqwest::{Method, StatusCode};
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::room::create_room::Endpoint as CreateRoomEndpoint;
+use ruma_client_api::r0::send::send_state_event_for_empty_key::Endpoint as SendStateEventForEmptyKeyEndpoint;
+use ruma_identifiers::{RoomId, UserId};
+
+#[test]
+fn the_room_is_created_when_the_homeserver_queries_the_alias_of_a_public_channel() {
+    let test = Test::new();
+    let (state_forwarder, state_receiver) = handlers::SendRoomState::with_forwarder();
+    let (create_room_forwarder, create_room_receiver) = handlers::MatrixCreateRoom::with_forwarder(test.config.as_url.clone());
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendStateEventForEmptyKeyEndpoint::router_path(), state_forwarder, "send_state_event_for_key");
+    matrix_router.post(CreateRoomEndpoint::router_path(), create_room_forwarder, "create_room");
+
+    let mut channels = HashMap::new();
+    channels.insert("public_channel", vec!["spec_user", "user_1"]);
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    // discard admin room creation
+    create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    // discard admin room name and topic
+    state_receiver.recv_timeout(default_timeout()).unwrap();
+    state_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let status = query_room_alias(&test, "%23rocketchat%23rc_id%23public_channel_id%3Alocalhost");
+    assert_eq!(status, StatusCode::Ok);
+
+    let create_room_message = create_room_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(create_room_message.contains("\"name\":\"public_channel\""));
+    assert!(create_room_message.contains("\"room_alias_name\":\"rocketchat#rc_id#public_channel_id\""));
+
+    // discard power levels
+    state_receiver.recv_timeout(default_timeout()).unwrap();
+
+    let history_visibility_received_by_matrix = state_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(history_visibility_received_by_matrix.contains("\"history_visibility\":\"joined\""));
+
+    let join_rule_received_by_matrix = state_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(join_rule_received_by_matrix.contains("\"join_rule\":\"public\""));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_id = RoomId::try_from("!public_channel_id:localhost").unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), room_id, None).unwrap();
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat:localhost").unwrap()));
+    assert!(user_ids.iter().any(|id| id == &UserId::try_from("@rocketchat_user_1_id_rc_id:localhost").unwrap()));
+}
+
+#[test]
+fn the_channel_is_looked_up_with_any_logged_in_user_when_the_owner_of_the_rocketchat_server_is_not_logged_in() {
+    let mut channels = HashMap::new();
+    channels.insert("public_channel", vec!["other_user"]);
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_custom_channel_list(channels).run();
+
+    let connection = test.connection_pool.get().unwrap();
+    let other_user_id = UserId::try_from("@other_user:localhost").unwrap();
+    User::insert(
+        &connection,
+        &NewUser {
+            language: "en",
+            matrix_user_id: other_user_id.clone(),
+        },
+    ).unwrap();
+    UserOnRocketchatServer::upsert(
+        &connection,
+        test.config.database_encryption_key().unwrap().as_ref(),
+        &NewUserOnRocketchatServer {
+            is_virtual_user: false,
+            matrix_user_id: other_user_id,
+            rocketchat_server_id: "rc_id".to_string(),
+            rocketchat_user_id: Some("other_user_id".to_string()),
+            rocketchat_auth_token: Some("other_auth_token".to_string()),
+            rocketchat_username: Some("other_user".to_string()),
+        },
+    ).unwrap();
+
+    let status = query_room_alias(&test, "%23rocketchat%23rc_id%23public_channel_id%3Alocalhost");
+    assert_eq!(status, StatusCode::Ok);
+}
+
+#[test]
+fn returns_not_found_when_the_channel_does_not_exist_on_the_rocketchat_server() {
+    let mut channels = HashMap::new();
+    channels.insert("public_channel", vec!["spec_user"]);
+    let test = Test::new()
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .with_custom_channel_list(channels)
+        .run();
+
+    let status = query_room_alias(&test, "%23rocketchat%23rc_id%23unknown_channel_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn returns_not_found_when_the_rocketchat_server_is_unknown() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let status = query_room_alias(&test, "%23rocketchat%23unknown_id%23public_channel_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn returns_not_found_when_no_user_is_logged_in_on_the_rocketchat_server() {
+    let mut channels = HashMap::new();
+    channels.insert("public_channel", vec!["spec_user"]);
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_custom_channel_list(channels).run();
+
+    let status = query_room_alias(&test, "%23rocketchat%23rc_id%23public_channel_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn returns_not_found_when_the_alias_is_not_in_the_namespace_of_the_application_service() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let status = query_room_alias(&test, "%23other%23rc_id%23public_channel_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+
+    let status = query_room_alias(&test, "%23rocketchat%23rc_id%23public_channel_id%3Aother.homeserver");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn the_room_alias_query_is_served_with_the_spec_path_prefix() {
+    let test = Test::new().run();
+    let url = format!("{}/_matrix/app/v1/rooms/{}", test.config.as_url, "%23other_alias%3Alocalhost");
+    let mut params = HashMap::new();
+    params.insert("access_token", HS_TOKEN);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &params, None).unwrap();
+    assert_eq!(status, StatusCode::NotFound);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &HashMap::new(), None).unwrap();
+    assert_eq!(status, StatusCode::Unauthorized);
+}
+
+fn query_room_alias(test: &Test, encoded_room_alias: &str) -> StatusCode {
+    let url = format!("{}/rooms/{}", test.config.as_url, encoded_room_alias);
+    let mut params = HashMap::new();
+    params.insert("access_token", HS_TOKEN);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &params, None).unwrap();
+    status
+}
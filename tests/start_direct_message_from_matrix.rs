@@ -0,0 +1,167 @@
+#![feature(try_from)]
+
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate router;
+extern crate ruma_client_api;
+extern crate ruma_identifiers;
+
+use std::convert::TryFrom;
+
+use matrix_rocketchat::api::MatrixApi;
+use matrix_rocketchat::api::rocketchat::v1::POST_CHAT_MESSAGE_PATH;
+use matrix_rocketchat::db::Room;
+use matrix_rocketchat_test::{DEFAULT_LOGGER, MessageForwarder, Test, default_timeout, helpers};
+use router::Router;
+use ruma_client_api::Endpoint;
+use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
+use ruma_identifiers::{RoomAliasId, RoomId, UserId};
+
+#[test]
+fn successfully_start_a_direct_message_by_inviting_a_virtual_user() {
+    let test = Test::new();
+    let mut rocketchat_router = Router::new();
+    let (rocketchat_message_forwarder, rocketchat_receiver) = MessageForwarder::new();
+    rocketchat_router.post(POST_CHAT_MESSAGE_PATH, rocketchat_message_forwarder, "post_chat_message");
+
+    let test = test.with_rocketchat_mock()
+        .with_custom_rocketchat_routes(rocketchat_router)
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    let room_id = RoomId::try_from("!dm_room_id:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    helpers::create_room(&test.config, "dm_room", spec_user_id.clone(), other_user_id.clone());
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#spec_user_id_other_user_id#dm:localhost").unwrap();
+    assert_eq!(matrix_api.get_room_alias(room_alias_id).unwrap(), Some(room_id.clone()));
+    assert!(Room::is_direct_message_room(&(*matrix_api), room_id.clone()).unwrap());
+
+    let user_ids = Room::user_ids(&(*matrix_api), room_id.clone(), Some(other_user_id.clone())).unwrap();
+    assert_eq!(user_ids.len(), 2);
+    assert!(user_ids.iter().any(|id| id == &spec_user_id));
+    assert!(user_ids.iter().any(|id| id == &other_user_id));
+
+    helpers::send_room_message_from_matrix(&test.config.as_url, room_id, spec_user_id, "Hi there".to_string());
+
+    let message_received_by_rocketchat = rocketchat_receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_rocketchat.contains("Hi there"));
+}
+
+#[test]
+fn the_virtual_user_leaves_the_room_when_the_inviter_is_not_logged_in() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let room_id = RoomId::try_from("!dm_room_id:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    helpers::create_room(&test.config, "dm_room", spec_user_id.clone(), other_user_id.clone());
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    let expected_message = format!("You are not logged in on {}", test.rocketchat_mock_url.clone().unwrap());
+    assert!(message_received_by_matrix.contains(&expected_message));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), room_id, Some(spec_user_id)).unwrap();
+    assert!(!user_ids.iter().any(|id| id == &other_user_id));
+}
+
+#[test]
+fn the_virtual_user_leaves_the_room_when_the_room_has_more_than_two_members() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let room_id = RoomId::try_from("!dm_room_id:localhost").unwrap();
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    let third_user_id = UserId::try_from("@third_user:localhost").unwrap();
+    helpers::create_room(&test.config, "dm_room", spec_user_id.clone(), third_user_id.clone());
+    helpers::join(&test.config, room_id.clone(), third_user_id);
+    helpers::invite(&test.config, room_id.clone(), other_user_id.clone(), spec_user_id.clone());
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("Direct message rooms must only contain you and the Rocket.Chat user"));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), room_id, Some(spec_user_id)).unwrap();
+    assert!(!user_ids.iter().any(|id| id == &other_user_id));
+}
+
+#[test]
+fn the_inviter_is_invited_to_the_existing_room_when_the_direct_message_is_already_bridged() {
+    let test = Test::new();
+    let (message_forwarder, receiver) = MessageForwarder::new();
+    let mut matrix_router = test.default_matrix_routes();
+    matrix_router.put(SendMessageEventEndpoint::router_path(), message_forwarder, "send_message_event");
+
+    let test = test.with_matrix_routes(matrix_router)
+        .with_rocketchat_mock()
+        .with_connected_admin_room()
+        .with_logged_in_user()
+        .run();
+
+    // discard welcome message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard connect message
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard redaction notice
+    receiver.recv_timeout(default_timeout()).unwrap();
+    // discard login message
+    receiver.recv_timeout(default_timeout()).unwrap();
+
+    let spec_user_id = UserId::try_from("@spec_user:localhost").unwrap();
+    let other_user_id = UserId::try_from("@rocketchat_other_user_id_rc_id:localhost").unwrap();
+    helpers::create_room(&test.config, "first_dm_room", spec_user_id.clone(), other_user_id.clone());
+    helpers::create_room(&test.config, "second_dm_room", spec_user_id.clone(), other_user_id.clone());
+
+    let message_received_by_matrix = receiver.recv_timeout(default_timeout()).unwrap();
+    assert!(message_received_by_matrix.contains("There already is a direct message room with other_user"));
+
+    let matrix_api = MatrixApi::new(&test.config, DEFAULT_LOGGER.clone()).unwrap();
+    let room_alias_id = RoomAliasId::try_from("#rocketchat#rc_id#spec_user_id_other_user_id#dm:localhost").unwrap();
+    let first_room_id = RoomId::try_from("!first_dm_room_id:localhost").unwrap();
+    assert_eq!(matrix_api.get_room_alias(room_alias_id).unwrap(), Some(first_room_id));
+
+    let second_room_id = RoomId::try_from("!second_dm_room_id:localhost").unwrap();
+    let user_ids = Room::user_ids(&(*matrix_api), second_room_id, Some(spec_user_id)).unwrap();
+    assert!(!user_ids.iter().any(|id| id == &other_user_id));
+}
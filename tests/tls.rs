@@ -0,0 +1,150 @@
+extern crate matrix_rocketchat;
+#[macro_use]
+extern crate matrix_rocketchat_test;
+extern crate openssl;
+extern crate tempdir;
+
+use std::fs::File;
+use std::io::Write;
+use std::path::Path;
+
+use matrix_rocketchat::Config;
+use matrix_rocketchat::errors::*;
+use matrix_rocketchat::tls::TlsServer;
+use matrix_rocketchat_test::{TEMP_DIR_NAME, build_test_config};
+use openssl::asn1::Asn1Time;
+use openssl::bn::BigNum;
+use openssl::hash::MessageDigest;
+use openssl::pkey::PKey;
+use openssl::rsa::Rsa;
+use openssl::x509::{X509, X509NameBuilder};
+use tempdir::TempDir;
+
+#[test]
+fn load_the_certificate_and_the_key_from_the_config() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let (certificate, key) = generate_certificate();
+    let config = ssl_config(&temp_dir, &certificate, &key);
+
+    let tls_server = TlsServer::new(&config).unwrap();
+
+    // write a renewed certificate
+    let (renewed_certificate, renewed_key) = generate_certificate();
+    write_file(&temp_dir.path().join("cert.pem"), &renewed_certificate);
+    write_file(&temp_dir.path().join("key.pem"), &renewed_key);
+    assert!(tls_server.reload().is_ok());
+}
+
+#[test]
+fn attempt_to_start_the_server_with_ssl_when_the_certificate_path_is_missing() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let config = Config {
+        use_ssl: true,
+        ssl_certificate_path: None,
+        ssl_key_path: Some("key.pem".to_string()),
+        ..build_test_config(&temp_dir)
+    };
+
+    let err = TlsServer::new(&config).err().unwrap();
+    assert_error_kind!(err, ErrorKind::MissingSslConfiguration(_));
+}
+
+#[test]
+fn attempt_to_start_the_server_with_ssl_when_the_key_path_is_missing() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let config = Config {
+        use_ssl: true,
+        ssl_certificate_path: Some("cert.pem".to_string()),
+        ssl_key_path: None,
+        ..build_test_config(&temp_dir)
+    };
+
+    let err = TlsServer::new(&config).err().unwrap();
+    assert_error_kind!(err, ErrorKind::MissingSslConfiguration(_));
+}
+
+#[test]
+fn attempt_to_start_the_server_with_ssl_when_the_certificate_file_does_not_exist() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let config = Config {
+        use_ssl: true,
+        ssl_certificate_path: Some(temp_dir.path().join("missing.pem").to_str().unwrap().to_string()),
+        ssl_key_path: Some(temp_dir.path().join("missing_key.pem").to_str().unwrap().to_string()),
+        ..build_test_config(&temp_dir)
+    };
+
+    let err = TlsServer::new(&config).err().unwrap();
+    assert_error_kind!(err, ErrorKind::ReadFileError(_));
+}
+
+#[test]
+fn attempt_to_start_the_server_with_ssl_when_the_certificate_is_invalid() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let (_, key) = generate_certificate();
+    let config = ssl_config(&temp_dir, b"not a certificate", &key);
+
+    let err = TlsServer::new(&config).err().unwrap();
+    assert_error_kind!(err, ErrorKind::InvalidSslCertificate(_));
+}
+
+#[test]
+fn attempt_to_start_the_server_with_ssl_when_the_key_does_not_match_the_certificate() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let (certificate, _) = generate_certificate();
+    let (_, other_key) = generate_certificate();
+    let config = ssl_config(&temp_dir, &certificate, &other_key);
+
+    let err = TlsServer::new(&config).err().unwrap();
+    assert_error_kind!(err, ErrorKind::InvalidSslCertificate(_));
+}
+
+#[test]
+fn the_current_certificate_is_kept_when_the_renewed_certificate_is_invalid() {
+    let temp_dir = TempDir::new(TEMP_DIR_NAME).unwrap();
+    let (certificate, key) = generate_certificate();
+    let config = ssl_config(&temp_dir, &certificate, &key);
+    let tls_server = TlsServer::new(&config).unwrap();
+
+    write_file(&temp_dir.path().join("cert.pem"), b"not a certificate");
+
+    let err = tls_server.reload().err().unwrap();
+    assert_error_kind!(err, ErrorKind::InvalidSslCertificate(_));
+}
+
+fn ssl_config(temp_dir: &TempDir, certificate: &[u8], key: &[u8]) -> Config {
+    let certificate_path = temp_dir.path().join("cert.pem");
+    let key_path = temp_dir.path().join("key.pem");
+    write_file(&certificate_path, certificate);
+    write_file(&key_path, key);
+
+    Config {
+        use_ssl: true,
+        ssl_certificate_path: Some(certificate_path.to_str().unwrap().to_string()),
+        ssl_key_path: Some(key_path.to_str().unwrap().to_string()),
+        ..build_test_config(temp_dir)
+    }
+}
+
+fn write_file(path: &Path, content: &[u8]) {
+    let mut file = File::create(path).unwrap();
+    file.write_all(content).unwrap();
+}
+
+fn generate_certificate() -> (Vec<u8>, Vec<u8>) {
+    let key = PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap();
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_text("CN", "localhost").unwrap();
+    let name = name.build();
+
+    let mut certificate = X509::builder().unwrap();
+    certificate.set_version(2).unwrap();
+    certificate.set_serial_number(&BigNum::from_u32(1).unwrap().to_asn1_integer().unwrap()).unwrap();
+    certificate.set_subject_name(&name).unwrap();
+    certificate.set_issuer_name(&name).unwrap();
+    certificate.set_pubkey(&key).unwrap();
+    certificate.set_not_before(&Asn1Time::days_from_now(0).unwrap()).unwrap();
+    certificate.set_not_after(&Asn1Time::days_from_now(1).unwrap()).unwrap();
+    certificate.sign(&key, MessageDigest::sha256()).unwrap();
+
+    (certificate.build().to_pem().unwrap(), key.private_key_to_pem().unwrap())
+}
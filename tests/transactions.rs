@@ -13,7 +13,8 @@ use matrix_rocketchat::api::RestApi;
 use matrix_rocketchat::models::Events;
 use matrix_rocketchat_test::{HS_TOKEN, MessageForwarder, Test, default_timeout, helpers};
 use reqwest::{Method, StatusCode};
-use ruma_client_api::Endpoint;
+use reqwest::header::{Authorization, Bearer, Headers};
+use ruma_client_api::{Endpoint, Method as RumaHttpMethod};
 use ruma_client_api::r0::send::send_message_event::Endpoint as SendMessageEventEndpoint;
 use ruma_events::EventType;
 use ruma_events::call::hangup::{HangupEvent, HangupEventContent};
@@ -103,3 +104,44 @@ fn returns_ok_when_the_hs_access_token_is_correct() {
 
     assert_eq!(status, StatusCode::Ok);
 }
+
+#[test]
+fn returns_ok_when_the_hs_access_token_is_sent_in_the_authorization_header() {
+    let test = Test::new().run();
+    let url = test.config.as_url.clone() + "/transactions/txn_id";
+    let events = Events { events: Vec::new() };
+    let payload = serde_json::to_string(&events).unwrap();
+    let mut headers = Headers::new();
+    headers.set(Authorization(Bearer { token: HS_TOKEN.to_string() }));
+
+    let (_, status) = RestApi::call(Method::Put, &url, &payload, &HashMap::new(), Some(headers)).unwrap();
+
+    assert_eq!(status, StatusCode::Ok);
+}
+
+#[test]
+fn returns_forbidden_when_the_hs_access_token_in_the_authorization_header_is_wrong() {
+    let test = Test::new().run();
+    let url = test.config.as_url.clone() + "/transactions/txn_id";
+    let mut headers = Headers::new();
+    headers.set(Authorization(Bearer { token: "wrong_token".to_string() }));
+
+    let (_, status) = RestApi::call(Method::Put, &url, "{}", &HashMap::new(), Some(headers)).unwrap();
+
+    assert_eq!(status, StatusCode::Forbidden);
+}
+
+#[test]
+fn the_transactions_endpoint_is_served_with_the_spec_path_prefix() {
+    let test = Test::new().run();
+    let url = test.config.as_url.clone() + "/_matrix/app/v1/transactions/txn_id";
+    let events = Events { events: Vec::new() };
+    let payload = serde_json::to_string(&events).unwrap();
+    let mut params = HashMap::new();
+    params.insert("access_token", HS_TOKEN);
+
+    // the access token is moved to the authorization header when calling matrix endpoints
+    let (_, status) = RestApi::call_matrix(RumaHttpMethod::Put, &url, &payload, &params).unwrap();
+
+    assert_eq!(status, StatusCode::Ok);
+}
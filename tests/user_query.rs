@@ -0,0 +1,94 @@
+extern crate matrix_rocketchat;
+extern crate matrix_rocketchat_test;
+extern crate reqwest;
+
+use std::collections::HashMap;
+
+use matrix_rocketchat::api::RestApi;
+use matrix_rocketchat::db::UserOnRocketchatServer;
+use matrix_rocketchat_test::{HS_TOKEN, Test};
+use reqwest::{Method, StatusCode};
+
+#[test]
+fn the_virtual_user_is_registered_when_the_homeserver_queries_its_user_id() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let status = query_user_id(&test, "%40rocketchat_user_1_id_rc_id%3Alocalhost");
+    assert_eq!(status, StatusCode::Ok);
+
+    let connection = test.connection_pool.get().unwrap();
+    let virtual_user =
+        UserOnRocketchatServer::find_by_rocketchat_user_id(&connection, "rc_id".to_string(), "user_1_id".to_string(), true)
+            .unwrap()
+            .unwrap();
+    assert_eq!(virtual_user.matrix_user_id.to_string(), "@rocketchat_user_1_id_rc_id:localhost");
+    assert_eq!(virtual_user.rocketchat_username, Some("user_1".to_string()));
+
+    // querying the user again doesn't register it twice
+    let status = query_user_id(&test, "%40rocketchat_user_1_id_rc_id%3Alocalhost");
+    assert_eq!(status, StatusCode::Ok);
+}
+
+#[test]
+fn returns_not_found_when_the_user_does_not_exist_on_the_rocketchat_server() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let status = query_user_id(&test, "%40rocketchat_unknown_rc_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+
+    let connection = test.connection_pool.get().unwrap();
+    let virtual_user =
+        UserOnRocketchatServer::find_by_rocketchat_user_id(&connection, "rc_id".to_string(), "unknown".to_string(), true)
+            .unwrap();
+    assert!(virtual_user.is_none());
+}
+
+#[test]
+fn returns_not_found_when_the_rocketchat_server_is_unknown() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let status = query_user_id(&test, "%40rocketchat_user_1_id_other_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn returns_not_found_when_no_user_is_logged_in_on_the_rocketchat_server() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().run();
+
+    let status = query_user_id(&test, "%40rocketchat_user_1_id_rc_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn returns_not_found_when_the_user_id_is_not_in_the_namespace_of_the_application_service() {
+    let test = Test::new().with_rocketchat_mock().with_connected_admin_room().with_logged_in_user().run();
+
+    let status = query_user_id(&test, "%40other_user_1_id_rc_id%3Alocalhost");
+    assert_eq!(status, StatusCode::NotFound);
+
+    let status = query_user_id(&test, "%40rocketchat_user_1_id_rc_id%3Aother.homeserver");
+    assert_eq!(status, StatusCode::NotFound);
+}
+
+#[test]
+fn the_user_query_is_served_with_the_spec_path_prefix() {
+    let test = Test::new().run();
+    let url = format!("{}/_matrix/app/v1/users/{}", test.config.as_url, "%40other_user%3Alocalhost");
+    let mut params = HashMap::new();
+    params.insert("access_token", HS_TOKEN);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &params, None).unwrap();
+    assert_eq!(status, StatusCode::NotFound);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &HashMap::new(), None).unwrap();
+    assert_eq!(status, StatusCode::Unauthorized);
+}
+
+fn query_user_id(test: &Test, encoded_user_id: &str) -> StatusCode {
+    let url = format!("{}/users/{}", test.config.as_url, encoded_user_id);
+    let mut params = HashMap::new();
+    params.insert("access_token", HS_TOKEN);
+
+    let (_, status) = RestApi::call(Method::Get, &url, "", &params, None).unwrap();
+    status
+}